@@ -1,11 +1,19 @@
 pub mod misc {
+    #[cfg(feature = "block")]
+    pub mod block;
     #[cfg(feature = "chat")]
     pub mod chat;
     pub mod client_settings;
     pub mod difficulty;
     pub mod dimension;
     pub mod game_mode;
+    #[cfg(feature = "profile")]
+    pub mod game_profile;
     pub mod property;
+    #[cfg(feature = "slot")]
+    pub mod slot;
+    #[cfg(feature = "status")]
+    pub mod status;
 }
 
 pub mod position {
@@ -15,8 +23,19 @@ pub mod position {
 }
 
 pub mod prelude {
+    #[cfg(feature = "block")]
+    pub use crate::misc::block::{BlockPalette, BlockRegistry, BlockState, LegacyBlocks};
+    #[cfg(feature = "chat")]
+    pub use crate::misc::chat::{ChatColor, ChatComponent, ChatEvent, ChatPosition};
+    #[cfg(feature = "profile")]
+    pub use crate::misc::game_profile::GameProfile;
+    #[cfg(feature = "slot")]
+    pub use crate::misc::slot::{ItemStack, NbtCompound, OptionalNbt, Slot};
+    #[cfg(feature = "status")]
+    pub use crate::misc::status::{
+        Favicon, PlayerSample, StatusPlayers, StatusResponse, StatusVersion,
+    };
     pub use crate::misc::{
-        chat::{ChatColor, ChatComponent, ChatEvent, ChatPosition},
         client_settings::{ChatMode, DisplayedSkinParts},
         difficulty::Difficulty,
         dimension::Dimension,
@@ -0,0 +1,539 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    fmt,
+    io::{self, Error, ErrorKind},
+    str::FromStr,
+};
+
+use protocol_internal::{ProtocolVersion, ProtocolVersionEnum};
+use serde::Deserialize;
+
+/// A block with the value of each of its properties, written like
+/// `minecraft:oak_log[axis=y]`.
+///
+/// Sent as its id in the version of the connection, which a
+/// [`BlockRegistry`] resolves both ways.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockState {
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl Default for BlockState {
+    fn default() -> Self {
+        Self::new("minecraft:air")
+    }
+}
+
+impl BlockState {
+    pub fn new<N: Into<String>>(name: N) -> Self {
+        Self {
+            name: name.into(),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    pub fn with<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.properties.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+}
+
+impl fmt::Display for BlockState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if self.properties.is_empty() {
+            return Ok(());
+        }
+
+        f.write_str("[")?;
+        for (i, (key, value)) in self.properties.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        f.write_str("]")
+    }
+}
+
+impl FromStr for BlockState {
+    type Err = Error;
+
+    /// Names without a namespace are in `minecraft`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::new(ErrorKind::InvalidData, format!("invalid block state {}", s));
+
+        let (name, properties) = match s.find('[') {
+            Some(start) if s.ends_with(']') => (&s[..start], &s[start + 1..s.len() - 1]),
+            Some(_) => return Err(invalid()),
+            None => (s, ""),
+        };
+        if name.is_empty() {
+            return Err(invalid());
+        }
+
+        let mut state = match name.contains(':') {
+            true => Self::new(name),
+            false => Self::new(format!("minecraft:{}", name)),
+        };
+        for property in properties.split(',').filter(|p| !p.is_empty()) {
+            let mut split = property.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(key), Some(value)) if !key.is_empty() => {
+                    state.properties.insert(key.into(), value.into());
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(state)
+    }
+}
+
+/// The global palette of a version from 1.13 on, every block state by id.
+#[derive(Clone, Debug, Default)]
+pub struct BlockPalette {
+    states: HashMap<u32, BlockState>,
+    ids: HashMap<BlockState, u32>,
+    defaults: HashMap<String, u32>,
+}
+
+#[derive(Deserialize)]
+struct BlockReport {
+    states: Vec<BlockStateReport>,
+}
+
+#[derive(Deserialize)]
+struct BlockStateReport {
+    id: u32,
+    #[serde(default)]
+    properties: BTreeMap<String, String>,
+    #[serde(default)]
+    default: bool,
+}
+
+impl BlockPalette {
+    /// Reads the `reports/blocks.json` of the game's data generator.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let report: HashMap<String, BlockReport> =
+            serde_json::from_str(json).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+
+        let mut palette = Self::default();
+        for (name, block) in report {
+            for state in block.states {
+                let id = state.id;
+                if state.default {
+                    palette.defaults.insert(name.clone(), id);
+                }
+                palette.insert(
+                    id,
+                    BlockState {
+                        name: name.clone(),
+                        properties: state.properties,
+                    },
+                );
+            }
+        }
+
+        Ok(palette)
+    }
+
+    pub fn insert(&mut self, id: u32, state: BlockState) {
+        self.defaults.entry(state.name.clone()).or_insert(id);
+        self.ids.insert(state.clone(), id);
+        self.states.insert(id, state);
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn state(&self, id: u32) -> Option<&BlockState> {
+        self.states.get(&id)
+    }
+
+    /// The id of `state`, properties it leaves out taking the values of the
+    /// block's default state.
+    pub fn state_id(&self, state: &BlockState) -> Option<u32> {
+        if let Some(id) = self.ids.get(state) {
+            return Some(*id);
+        }
+
+        let mut complete = self.default_state(&state.name)?.clone();
+        for (key, value) in &state.properties {
+            match complete.properties.get_mut(key) {
+                Some(default) => *default = value.clone(),
+                None => return None,
+            }
+        }
+        self.ids.get(&complete).copied()
+    }
+
+    pub fn default_state(&self, name: &str) -> Option<&BlockState> {
+        self.state(*self.defaults.get(name)?)
+    }
+}
+
+/// The block states before the flattening of 1.13, `id << 4 | metadata`.
+#[derive(Clone, Debug, Default)]
+pub struct LegacyBlocks {
+    states: HashMap<u16, BlockState>,
+    ids: HashMap<BlockState, u16>,
+    by_name: HashMap<String, Vec<u16>>,
+}
+
+impl LegacyBlocks {
+    /// Reads a mapping of `"id:metadata"` to the flattened block state, like
+    /// `{"17:4": "minecraft:oak_log[axis=x]"}`.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let mapping: BTreeMap<String, String> =
+            serde_json::from_str(json).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+
+        let mut entries = Vec::with_capacity(mapping.len());
+        for (legacy, state) in mapping {
+            let invalid = || {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid legacy id {}", legacy),
+                )
+            };
+
+            let mut split = legacy.splitn(2, ':');
+            let id: u16 = split.next().unwrap_or("").parse().map_err(|_| invalid())?;
+            let metadata: u16 = match split.next() {
+                Some(metadata) => metadata.parse().map_err(|_| invalid())?,
+                None => 0,
+            };
+            if id > 0x0FFF || metadata > 0x0F {
+                return Err(invalid());
+            }
+
+            entries.push((id << 4 | metadata, state.parse()?));
+        }
+
+        // the lowest metadata wins when several map to the same state
+        entries.sort_by_key(|(legacy, _)| *legacy);
+
+        let mut blocks = Self::default();
+        for (legacy, state) in entries {
+            blocks.insert(legacy, state);
+        }
+        Ok(blocks)
+    }
+
+    pub fn insert(&mut self, legacy: u16, state: BlockState) {
+        self.ids.entry(state.clone()).or_insert(legacy);
+        self.by_name
+            .entry(state.name.clone())
+            .or_default()
+            .push(legacy);
+        self.states.insert(legacy, state);
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn state(&self, legacy: u16) -> Option<&BlockState> {
+        self.states.get(&legacy)
+    }
+
+    /// The legacy id of `state`.
+    ///
+    /// Properties that used to be worked out by the client, like the
+    /// connections of a fence, have no legacy id of their own; the state of
+    /// the same block with the most matching properties is taken instead.
+    pub fn legacy_id(&self, state: &BlockState) -> Option<u16> {
+        if let Some(legacy) = self.ids.get(state) {
+            return Some(*legacy);
+        }
+
+        self.by_name
+            .get(&state.name)?
+            .iter()
+            .max_by_key(|legacy| {
+                let properties = &self.states[legacy].properties;
+                let matching = state
+                    .properties
+                    .iter()
+                    .filter(|(key, value)| properties.get(*key) == Some(value))
+                    .count();
+                // prefer the lowest id between equals
+                (matching, std::cmp::Reverse(**legacy))
+            })
+            .copied()
+    }
+}
+
+/// Block states by id for every version: legacy ids before 1.13, the
+/// version's global palette after.
+#[derive(Clone, Debug, Default)]
+pub struct BlockRegistry {
+    legacy: LegacyBlocks,
+    palettes: Vec<(ProtocolVersionEnum, BlockPalette)>,
+}
+
+impl BlockRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_legacy(mut self, legacy: LegacyBlocks) -> Self {
+        self.legacy = legacy;
+        self
+    }
+
+    /// The palette of the versions from `since` until the next palette.
+    pub fn with_palette(mut self, since: ProtocolVersionEnum, palette: BlockPalette) -> Self {
+        self.palettes.retain(|(version, _)| *version != since);
+        self.palettes.push((since, palette));
+        self.palettes.sort_by_key(|(version, _)| *version);
+        self
+    }
+
+    pub fn legacy(&self) -> &LegacyBlocks {
+        &self.legacy
+    }
+
+    /// `None` before 1.13, or when no palette was added up to `version`.
+    pub fn palette(&self, version: &ProtocolVersion) -> Option<&BlockPalette> {
+        if *version < ProtocolVersionEnum::V1_13 {
+            return None;
+        }
+
+        self.palettes
+            .iter()
+            .rev()
+            .find(|(since, _)| *version >= *since)
+            .map(|(_, palette)| palette)
+    }
+
+    /// The id `state` is sent as to `version`.
+    pub fn state_id(&self, state: &BlockState, version: &ProtocolVersion) -> Option<i32> {
+        if *version < ProtocolVersionEnum::V1_13 {
+            self.legacy.legacy_id(state).map(i32::from)
+        } else {
+            self.palette(version)?.state_id(state).map(|id| id as i32)
+        }
+    }
+
+    pub fn state(&self, id: i32, version: &ProtocolVersion) -> Option<&BlockState> {
+        if *version < ProtocolVersionEnum::V1_13 {
+            self.legacy.state(u16::try_from(id).ok()?)
+        } else {
+            self.palette(version)?.state(u32::try_from(id).ok()?)
+        }
+    }
+
+    /// Like [`Self::state_id`], failing on a state `version` doesn't have.
+    pub fn block_id(&self, state: &BlockState, version: &ProtocolVersion) -> io::Result<i32> {
+        self.state_id(state, version).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} does not exist in {:?}", state, version),
+            )
+        })
+    }
+
+    /// Like [`Self::state`], failing on an id `version` doesn't have.
+    pub fn block_state(&self, id: i32, version: &ProtocolVersion) -> io::Result<&BlockState> {
+        self.state(id, version).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("no block state {} in {:?}", id, version),
+            )
+        })
+    }
+
+    /// The state id in `version`, from 1.13 on, of a legacy block.
+    pub fn legacy_to_flattened(&self, legacy: u16, version: &ProtocolVersion) -> Option<u32> {
+        self.palette(version)?.state_id(self.legacy.state(legacy)?)
+    }
+
+    /// The legacy block of a state id in `version`, from 1.13 on.
+    pub fn flattened_to_legacy(&self, id: u32, version: &ProtocolVersion) -> Option<u16> {
+        self.legacy.legacy_id(self.palette(version)?.state(id)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // excerpts of the 1.13.2 and 1.16.5 reports
+    const BLOCKS_1_13: &str = r#"{
+        "minecraft:air": {"states": [{"id": 0, "default": true}]},
+        "minecraft:stone": {"states": [{"id": 1, "default": true}]},
+        "minecraft:oak_log": {
+            "properties": {"axis": ["x", "y", "z"]},
+            "states": [
+                {"id": 72, "properties": {"axis": "x"}},
+                {"id": 73, "properties": {"axis": "y"}, "default": true},
+                {"id": 74, "properties": {"axis": "z"}}
+            ]
+        },
+        "minecraft:oak_fence": {
+            "properties": {"east": ["true", "false"], "north": ["true", "false"]},
+            "states": [
+                {"id": 3539, "properties": {"east": "true", "north": "true"}},
+                {"id": 3540, "properties": {"east": "true", "north": "false"}},
+                {"id": 3541, "properties": {"east": "false", "north": "true"}},
+                {"id": 3542, "properties": {"east": "false", "north": "false"}, "default": true}
+            ]
+        }
+    }"#;
+    const BLOCKS_1_16: &str = r#"{
+        "minecraft:air": {"states": [{"id": 0, "default": true}]},
+        "minecraft:stone": {"states": [{"id": 1, "default": true}]},
+        "minecraft:oak_log": {
+            "properties": {"axis": ["x", "y", "z"]},
+            "states": [
+                {"id": 73, "properties": {"axis": "x"}},
+                {"id": 74, "properties": {"axis": "y"}, "default": true},
+                {"id": 75, "properties": {"axis": "z"}}
+            ]
+        }
+    }"#;
+    const LEGACY: &str = r#"{
+        "0:0": "minecraft:air",
+        "1:0": "minecraft:stone",
+        "17:0": "minecraft:oak_log[axis=y]",
+        "17:4": "minecraft:oak_log[axis=x]",
+        "17:8": "minecraft:oak_log[axis=z]",
+        "85:0": "minecraft:oak_fence[east=false,north=false]"
+    }"#;
+
+    fn registry() -> BlockRegistry {
+        BlockRegistry::new()
+            .with_legacy(LegacyBlocks::from_json(LEGACY).unwrap())
+            .with_palette(
+                ProtocolVersionEnum::V1_16,
+                BlockPalette::from_json(BLOCKS_1_16).unwrap(),
+            )
+            .with_palette(
+                ProtocolVersionEnum::V1_13,
+                BlockPalette::from_json(BLOCKS_1_13).unwrap(),
+            )
+    }
+
+    #[test]
+    fn test_parse_block_state() {
+        let state: BlockState = "oak_fence[north=true,east=false]".parse().unwrap();
+        assert_eq!(state.name, "minecraft:oak_fence");
+        assert_eq!(state.property("north"), Some("true"));
+        assert_eq!(
+            state.to_string(),
+            "minecraft:oak_fence[east=false,north=true]"
+        );
+        assert_eq!(
+            "minecraft:stone".parse::<BlockState>().unwrap(),
+            BlockState::new("minecraft:stone")
+        );
+
+        assert!("".parse::<BlockState>().is_err());
+        assert!("stone[axis".parse::<BlockState>().is_err());
+        assert!("stone[=y]".parse::<BlockState>().is_err());
+    }
+
+    #[test]
+    fn test_state_ids() {
+        let registry = registry();
+        let v1_8 = ProtocolVersionEnum::V1_8.into();
+        let v1_13 = ProtocolVersionEnum::V1_13_2.into();
+        let v1_15 = ProtocolVersionEnum::V1_15_2.into();
+        let v1_16 = ProtocolVersionEnum::V1_16_5.into();
+
+        let log = BlockState::new("minecraft:oak_log").with("axis", "x");
+        assert_eq!(registry.state_id(&log, &v1_8), Some(17 << 4 | 4));
+        assert_eq!(registry.state_id(&log, &v1_13), Some(72));
+        assert_eq!(registry.state_id(&log, &v1_15), Some(72));
+        assert_eq!(registry.state_id(&log, &v1_16), Some(73));
+
+        // left out properties are the default ones
+        let log = BlockState::new("minecraft:oak_log");
+        assert_eq!(registry.state_id(&log, &v1_13), Some(73));
+        assert_eq!(
+            registry.state(74, &v1_16),
+            Some(&BlockState::new("minecraft:oak_log").with("axis", "y"))
+        );
+        assert_eq!(
+            registry
+                .state(17 << 4 | 8, &v1_8)
+                .and_then(|s| s.property("axis")),
+            Some("z")
+        );
+
+        let fence = BlockState::new("minecraft:oak_fence").with("north", "true");
+        assert_eq!(registry.state_id(&fence, &v1_13), Some(3541));
+        assert_eq!(registry.state_id(&fence, &v1_8), Some(85 << 4));
+        assert_eq!(registry.state_id(&fence, &v1_16), None);
+        assert_eq!(
+            registry.state_id(&log.clone().with("color", "red"), &v1_13),
+            None
+        );
+        assert_eq!(registry.state(-1, &v1_8), None);
+    }
+
+    #[test]
+    fn test_flattening() {
+        let registry = registry();
+        let v1_13 = ProtocolVersionEnum::V1_13.into();
+        let v1_16 = ProtocolVersionEnum::V1_16.into();
+
+        assert_eq!(registry.legacy_to_flattened(17 << 4 | 8, &v1_13), Some(74));
+        assert_eq!(registry.legacy_to_flattened(17 << 4 | 8, &v1_16), Some(75));
+        assert_eq!(registry.legacy_to_flattened(17 << 4 | 12, &v1_13), None);
+        assert_eq!(registry.flattened_to_legacy(73, &v1_16), Some(17 << 4 | 4));
+        assert_eq!(registry.flattened_to_legacy(3539, &v1_13), Some(85 << 4));
+        assert_eq!(
+            registry.flattened_to_legacy(1, &ProtocolVersionEnum::V1_8.into()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sparse_palette() {
+        let palette = BlockPalette::from_json(
+            r#"{"minecraft:stone": {"states": [{"id": 4294967295, "default": true}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(palette.len(), 1);
+        assert_eq!(
+            palette.state(u32::MAX),
+            Some(&BlockState::new("minecraft:stone"))
+        );
+        assert_eq!(palette.state(0), None);
+    }
+
+    #[test]
+    fn test_block_ids() {
+        let registry = registry();
+        let v1_8 = ProtocolVersionEnum::V1_8.into();
+        let v1_16 = ProtocolVersionEnum::V1_16_5.into();
+        let log = BlockState::new("minecraft:oak_log").with("axis", "z");
+
+        assert_eq!(registry.block_id(&log, &v1_8).unwrap(), 17 << 4 | 8);
+        assert_eq!(registry.block_id(&log, &v1_16).unwrap(), 75);
+        assert_eq!(registry.block_state(75, &v1_16).unwrap(), &log);
+
+        let unknown = BlockState::new("minecraft:bedrock");
+        assert!(registry.block_id(&unknown, &v1_16).is_err());
+        assert!(registry.block_state(0x7F, &v1_16).is_err());
+        // no palette for the version
+        assert!(BlockRegistry::new().block_state(0, &v1_16).is_err());
+    }
+}
@@ -3,7 +3,7 @@ use std::borrow::Cow;
 use protocol_internal::{ProtocolSupportDecoder, ProtocolSupportEncoder};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChatComponent<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -31,7 +31,7 @@ pub struct ChatComponent<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hover_event: Option<ChatEvent<HoverEvent>>,
 
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub extra: Vec<ChatComponent<'a>>,
 }
 
@@ -128,6 +128,43 @@ impl<'a> ChatComponent<'a> {
     }
 }
 
+impl<'a> ChatComponent<'a> {
+    /// Flattens the component to `§` formatted text, as understood by
+    /// clients older than 1.7.
+    pub fn to_legacy_string(&self) -> String {
+        let mut legacy = String::new();
+        self.write_legacy(&mut legacy);
+        legacy
+    }
+
+    fn write_legacy(&self, dst: &mut String) {
+        if let Some(color) = self.color {
+            dst.push('§');
+            dst.push(color.to_code());
+        }
+
+        let formats = [
+            (self.obfuscated, 'k'),
+            (self.bold, 'l'),
+            (self.strikethrough, 'm'),
+            (self.underlined, 'n'),
+            (self.italic, 'o'),
+        ];
+        for (_, code) in formats.iter().filter(|(set, _)| *set == Some(true)) {
+            dst.push('§');
+            dst.push(*code);
+        }
+
+        if let Some(text) = &self.text {
+            dst.push_str(text);
+        }
+
+        for extra in &self.extra {
+            extra.write_legacy(dst);
+        }
+    }
+}
+
 impl<'a> ProtocolSupportEncoder for ChatComponent<'a> {
     fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
         <String as ProtocolSupportEncoder>::calculate_len(
@@ -152,7 +189,7 @@ impl<'a> ProtocolSupportEncoder for ChatComponent<'a> {
 
 impl<'a> ProtocolSupportDecoder for ChatComponent<'a> {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut protocol_internal::DecodeContext<R>,
         version: &protocol_internal::ProtocolVersion,
     ) -> std::io::Result<Self> {
         serde_json::from_str(&<String as ProtocolSupportDecoder>::decode(src, version)?)
@@ -161,7 +198,7 @@ impl<'a> ProtocolSupportDecoder for ChatComponent<'a> {
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ChatColor {
     Black = '0' as u8,
@@ -249,7 +286,7 @@ impl protocol_internal::ProtocolSupportEncoder for ChatColor {
 
 impl protocol_internal::ProtocolSupportDecoder for ChatColor {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut protocol_internal::DecodeContext<R>,
         version: &protocol_internal::ProtocolVersion,
     ) -> std::io::Result<Self> {
         Ok(Self::from(
@@ -258,7 +295,7 @@ impl protocol_internal::ProtocolSupportDecoder for ChatColor {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ChatEvent<T: Sized> {
     action: T,
     value: String,
@@ -273,7 +310,7 @@ impl<T: Sized> ChatEvent<T> {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ClickEvent {
     OpenUrl,
@@ -281,7 +318,7 @@ pub enum ClickEvent {
     SuggestCommand,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum HoverEvent {
     ShowText,
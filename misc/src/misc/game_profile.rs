@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::misc::property::Property;
+
+/// A player's identity, as known once the login finishes.
+///
+/// Deserializes from the profiles returned by the session server.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GameProfile {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub properties: Vec<Property>,
+}
+
+impl GameProfile {
+    pub fn new<N: Into<String>>(id: Uuid, name: N) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            properties: Vec::new(),
+        }
+    }
+
+    /// The profile an offline-mode server gives to `name`.
+    pub fn offline<N: Into<String>>(name: N) -> Self {
+        let name = name.into();
+        Self::new(offline_uuid(&name), name)
+    }
+}
+
+/// The uuid vanilla servers give players in offline mode, a version 3 uuid
+/// of `OfflinePlayer:<name>`.
+pub fn offline_uuid(name: &str) -> Uuid {
+    let mut bytes = md5::compute(format!("OfflinePlayer:{}", name)).0;
+    bytes[6] = (bytes[6] & 0x0f) | 0x30;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    Uuid::from_bytes(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_offline_uuid() {
+        assert_eq!(
+            offline_uuid("Notch").to_string(),
+            "b50ad385-829d-3141-a216-7e7d7539ba7f"
+        );
+
+        let profile = GameProfile::offline("SaiintBrisson");
+        assert_eq!(
+            profile.id.to_string(),
+            "ed2c0592-1bc1-3236-881e-688101ec539d"
+        );
+        assert_eq!(profile.id.get_version_num(), 3);
+        assert!(profile.properties.is_empty());
+    }
+
+    #[test]
+    fn test_session_profile() {
+        let profile: GameProfile = serde_json::from_str(
+            r#"{"id":"069a79f444e94726a5befca90e38aaf5","name":"Notch","properties":[{"name":"textures","value":"e30=","signature":"c2ln"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            profile.id.to_string(),
+            "069a79f4-44e9-4726-a5be-fca90e38aaf5"
+        );
+        assert_eq!(profile.properties[0].name, "textures");
+        assert_eq!(profile.properties[0].signature.as_deref(), Some("c2ln"));
+    }
+}
@@ -1,6 +1,11 @@
-#[derive(Clone, Debug, protocol_derive::ProtocolSupport)]
+#[derive(Clone, Debug, PartialEq, Eq, protocol_derive::ProtocolSupport)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct Property {
-    name: String,
-    value: String,
-    signature: Option<String>,
+    pub name: String,
+    pub value: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub signature: Option<String>,
 }
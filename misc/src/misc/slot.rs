@@ -0,0 +1,502 @@
+use std::io::{self, Error, ErrorKind, Read};
+
+use protocol_internal::{DecodeContext, ProtocolSupportDecoder, ProtocolSupportEncoder};
+use quartz_nbt::io::{read_nbt, write_nbt, Flavor};
+pub use quartz_nbt::NbtCompound;
+use quartz_nbt::NbtTag;
+
+/// What an inventory slot holds, as sent before 1.13: `-1` as the item id
+/// for an empty slot, any negative one being read as such, then the count,
+/// damage and an optional nbt compound.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Slot(pub Option<ItemStack>);
+
+impl Slot {
+    pub const EMPTY: Self = Self(None);
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    pub fn item(&self) -> Option<&ItemStack> {
+        self.0.as_ref()
+    }
+}
+
+impl From<ItemStack> for Slot {
+    fn from(item: ItemStack) -> Self {
+        Self(Some(item))
+    }
+}
+
+impl From<Option<ItemStack>> for Slot {
+    fn from(item: Option<ItemStack>) -> Self {
+        Self(item)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ItemStack {
+    pub item_id: i16,
+    pub count: i8,
+    /// The durability used, or which variant of the item it is, like the
+    /// color of wool.
+    pub damage: i16,
+    /// Enchantments, names, lore and anything else items carry.
+    pub nbt: Option<NbtCompound>,
+}
+
+impl ItemStack {
+    pub fn new(item_id: i16, count: i8) -> Self {
+        Self {
+            item_id,
+            count,
+            damage: 0,
+            nbt: None,
+        }
+    }
+
+    pub fn with_damage(mut self, damage: i16) -> Self {
+        self.damage = damage;
+        self
+    }
+
+    pub fn with_nbt(mut self, nbt: NbtCompound) -> Self {
+        self.nbt = Some(nbt);
+        self
+    }
+}
+
+impl ProtocolSupportEncoder for Slot {
+    fn calculate_len(&self, version: &protocol_internal::ProtocolVersion) -> usize {
+        match &self.0 {
+            Some(item) => {
+                item.item_id.calculate_len(version)
+                    + item.count.calculate_len(version)
+                    + item.damage.calculate_len(version)
+                    + nbt_len(&item.nbt)
+            }
+            None => 2,
+        }
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        let item = match &self.0 {
+            Some(item) => item,
+            None => return (-1i16).encode(dst, version),
+        };
+
+        item.item_id.encode(dst, version)?;
+        item.count.encode(dst, version)?;
+        item.damage.encode(dst, version)?;
+        encode_optional_nbt(&item.nbt, dst, version)
+    }
+}
+
+impl ProtocolSupportDecoder for Slot {
+    fn decode<R: std::io::Read>(
+        src: &mut DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let item_id = i16::decode(src, version)?;
+        // vanilla takes any negative id for an empty slot, not only -1
+        if item_id < 0 {
+            return Ok(Self::EMPTY);
+        }
+
+        let count = i8::decode(src, version)?;
+        let damage = i16::decode(src, version)?;
+        let nbt = decode_optional_nbt(src, version)?;
+
+        Ok(Self(Some(ItemStack {
+            item_id,
+            count,
+            damage,
+            nbt,
+        })))
+    }
+}
+
+/// An nbt compound sent on its own, like the data of a block entity, with
+/// a single `TAG_End` standing for no compound.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OptionalNbt(pub Option<NbtCompound>);
+
+impl From<NbtCompound> for OptionalNbt {
+    fn from(nbt: NbtCompound) -> Self {
+        Self(Some(nbt))
+    }
+}
+
+impl ProtocolSupportEncoder for OptionalNbt {
+    fn calculate_len(&self, _: &protocol_internal::ProtocolVersion) -> usize {
+        nbt_len(&self.0)
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        encode_optional_nbt(&self.0, dst, version)
+    }
+}
+
+impl ProtocolSupportDecoder for OptionalNbt {
+    fn decode<R: std::io::Read>(
+        src: &mut DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        decode_optional_nbt(src, version).map(Self)
+    }
+}
+
+/// The len of the compound as written by `write_nbt`, worked out from the
+/// tags rather than by writing them.
+fn nbt_len(nbt: &Option<NbtCompound>) -> usize {
+    match nbt {
+        // the tag, an empty root name and the payload
+        Some(nbt) => 1 + 2 + compound_len(nbt),
+        None => 1,
+    }
+}
+
+fn compound_len(nbt: &NbtCompound) -> usize {
+    nbt.inner()
+        .iter()
+        .map(|(name, tag)| 1 + string_len(name) + tag_len(tag))
+        .sum::<usize>()
+        // TAG_End
+        + 1
+}
+
+fn tag_len(tag: &NbtTag) -> usize {
+    match tag {
+        NbtTag::Byte(_) => 1,
+        NbtTag::Short(_) => 2,
+        NbtTag::Int(_) | NbtTag::Float(_) => 4,
+        NbtTag::Long(_) | NbtTag::Double(_) => 8,
+        NbtTag::ByteArray(array) => 4 + array.len(),
+        NbtTag::String(string) => string_len(string),
+        NbtTag::List(list) => {
+            let list: &[NbtTag] = list.as_ref();
+            5 + list.iter().map(tag_len).sum::<usize>()
+        }
+        NbtTag::Compound(nbt) => compound_len(nbt),
+        NbtTag::IntArray(array) => 4 + array.len() * 4,
+        NbtTag::LongArray(array) => 4 + array.len() * 8,
+    }
+}
+
+/// A string in the modified UTF-8 of Java, its nul and supplementary
+/// characters taking 2 and 6 bytes.
+fn string_len(string: &str) -> usize {
+    2 + string
+        .chars()
+        .map(|c| match c as u32 {
+            0 => 2,
+            0x01..=0x7F => 1,
+            0x80..=0x7FF => 2,
+            0x800..=0xFFFF => 3,
+            _ => 6,
+        })
+        .sum::<usize>()
+}
+
+fn encode_optional_nbt<W: std::io::Write>(
+    nbt: &Option<NbtCompound>,
+    dst: &mut W,
+    version: &protocol_internal::ProtocolVersion,
+) -> io::Result<()> {
+    match nbt {
+        Some(nbt) => write_nbt(dst, Some(""), nbt, Flavor::Uncompressed)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err)),
+        // TAG_End in place of the compound
+        None => 0u8.encode(dst, version),
+    }
+}
+
+fn decode_optional_nbt<R: std::io::Read>(
+    src: &mut DecodeContext<R>,
+    version: &protocol_internal::ProtocolVersion,
+) -> io::Result<Option<NbtCompound>> {
+    match u8::decode(src, version)? {
+        0 => Ok(None),
+        tag => {
+            let buf = NbtReader::read_compound(src, tag)?;
+            let (nbt, _) = read_nbt(&mut &buf[..], Flavor::Uncompressed)
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+            Ok(Some(nbt))
+        }
+    }
+}
+
+/// How deep lists and compounds nest at most. Lower than the 512 of vanilla,
+/// as `read_nbt` recurses for every level and may run on a small task stack.
+const MAX_NBT_DEPTH: usize = 128;
+
+/// Copies an nbt compound out of the frame, checking each length on the way.
+///
+/// `read_nbt` allocates whatever the lengths of arrays, lists and strings
+/// say before reading them, so they are only handed to it once they are
+/// known to fit in the bytes left.
+struct NbtReader<'a, R> {
+    src: &'a mut DecodeContext<R>,
+    buf: Vec<u8>,
+}
+
+impl<'a, R: std::io::Read> NbtReader<'a, R> {
+    /// Reads the rest of a compound whose tag was already read.
+    fn read_compound(src: &'a mut DecodeContext<R>, tag: u8) -> io::Result<Vec<u8>> {
+        if tag != 0x0A {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("expected an nbt compound, got tag {}", tag),
+            ));
+        }
+
+        let mut reader = Self {
+            src,
+            buf: vec![tag],
+        };
+        reader.string()?;
+        reader.payload(tag, 0)?;
+
+        Ok(reader.buf)
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&[u8]> {
+        if len > self.src.remaining() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "nbt of {} bytes over the {} left",
+                    len,
+                    self.src.remaining()
+                ),
+            ));
+        }
+
+        let start = self.buf.len();
+        self.buf.resize(start + len, 0);
+        self.src.read_exact(&mut self.buf[start..])?;
+        Ok(&self.buf[start..])
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn string(&mut self) -> io::Result<()> {
+        let len = self.take(2)?;
+        let len = u16::from_be_bytes([len[0], len[1]]);
+        self.take(len as usize).map(drop)
+    }
+
+    /// The count of an array or list of items of `item_len` bytes at least.
+    fn len(&mut self, item_len: usize) -> io::Result<usize> {
+        let len = self.take(4)?;
+        let len = i32::from_be_bytes([len[0], len[1], len[2], len[3]]);
+        if len < 0 || len as usize > self.src.remaining() / item_len {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} nbt items over the {} bytes left",
+                    len,
+                    self.src.remaining()
+                ),
+            ));
+        }
+
+        Ok(len as usize)
+    }
+
+    fn payload(&mut self, tag: u8, depth: usize) -> io::Result<()> {
+        if depth > MAX_NBT_DEPTH {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("nbt nested deeper than {}", MAX_NBT_DEPTH),
+            ));
+        }
+
+        match tag {
+            0x01..=0x06 => self.take(min_payload_len(tag)?).map(drop),
+            0x07 => {
+                let len = self.len(1)?;
+                self.take(len).map(drop)
+            }
+            0x08 => self.string(),
+            0x09 => {
+                let item = self.u8()?;
+                let len = self.len(min_payload_len(item)?.max(1))?;
+                if item == 0 && len > 0 {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "nbt list of TAG_End with items",
+                    ));
+                }
+
+                for _ in 0..len {
+                    self.payload(item, depth + 1)?;
+                }
+                Ok(())
+            }
+            0x0A => loop {
+                match self.u8()? {
+                    0 => break Ok(()),
+                    tag => {
+                        self.string()?;
+                        self.payload(tag, depth + 1)?;
+                    }
+                }
+            },
+            0x0B | 0x0C => {
+                let item_len = if tag == 0x0B { 4 } else { 8 };
+                let len = self.len(item_len)?;
+                self.take(len * item_len).map(drop)
+            }
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid nbt tag {}", tag),
+            )),
+        }
+    }
+}
+
+/// The fewest bytes the payload of `tag` takes.
+fn min_payload_len(tag: u8) -> io::Result<usize> {
+    Ok(match tag {
+        0x00 => 0,
+        0x01 => 1,
+        0x02 => 2,
+        0x03 | 0x05 => 4,
+        0x04 | 0x06 => 8,
+        0x07 | 0x0B | 0x0C => 4,
+        0x08 => 2,
+        0x09 => 5,
+        0x0A => 1,
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid nbt tag {}", tag),
+            ))
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use protocol_internal::{
+        DecodeContext, ProtocolSupportDecoder, ProtocolSupportEncoder, ProtocolVersion,
+    };
+
+    use super::*;
+
+    fn round_trip(slot: &Slot) -> Vec<u8> {
+        let version = ProtocolVersion::new(47);
+
+        let mut buf = Vec::new();
+        slot.encode(&mut buf, &version).unwrap();
+        assert_eq!(buf.len(), slot.calculate_len(&version));
+
+        let mut src = DecodeContext::from(&buf[..]);
+        assert_eq!(&Slot::decode(&mut src, &version).unwrap(), slot);
+        assert!(src.is_empty());
+
+        buf
+    }
+
+    #[test]
+    fn test_slot() {
+        assert_eq!(round_trip(&Slot::EMPTY), [0xFF, 0xFF]);
+        assert_eq!(
+            Slot::decode(
+                &mut DecodeContext::from(&[0x80, 0x00][..]),
+                &ProtocolVersion::new(47)
+            )
+            .unwrap(),
+            Slot::EMPTY
+        );
+        assert_eq!(
+            round_trip(&ItemStack::new(1, 64).with_damage(3).into()),
+            [0x00, 0x01, 0x40, 0x00, 0x03, 0x00]
+        );
+
+        let mut display = NbtCompound::new();
+        display.insert("Name", "Excalibur");
+        let mut nbt = NbtCompound::new();
+        nbt.insert("display", display);
+
+        round_trip(&ItemStack::new(276, 1).with_nbt(nbt.clone()).into());
+
+        // every tag, with characters of each modified UTF-8 width
+        let mut list = quartz_nbt::NbtList::new();
+        list.push(NbtCompound::new());
+        list.push(nbt);
+        let mut tags = NbtCompound::new();
+        tags.insert("byte", 1i8);
+        tags.insert("short", 1i16);
+        tags.insert("int", 1i32);
+        tags.insert("long", 1i64);
+        tags.insert("float", 1f32);
+        tags.insert("double", 1f64);
+        tags.insert("bytes", vec![1i8, 2]);
+        tags.insert("string", "a\0é€😀");
+        tags.insert("list", list);
+        tags.insert("empty", quartz_nbt::NbtList::new());
+        tags.insert("ints", vec![1i32, 2]);
+        tags.insert("longs", vec![1i64, 2]);
+        round_trip(&ItemStack::new(1, 1).with_nbt(tags).into());
+    }
+
+    #[test]
+    fn test_malformed_nbt() {
+        let version = ProtocolVersion::new(47);
+        let decode = |buf: &[u8]| Slot::decode(&mut DecodeContext::from(buf), &version);
+
+        // a byte array of -1 bytes
+        let slot = [
+            0x00, 0x01, 0x01, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x07, 0x00, 0x00, 0xFF, 0xFF, 0xFF,
+            0xFF,
+        ];
+        assert_eq!(decode(&slot).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        // lengths far over the frame
+        let int_array = [
+            0x00, 0x01, 0x01, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x0B, 0x00, 0x00, 0x7F, 0xFF, 0xFF,
+            0xFF,
+        ];
+        assert_eq!(
+            decode(&int_array).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        let list = [
+            0x00, 0x01, 0x01, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x09, 0x00, 0x00, 0x0A, 0x10, 0x00,
+            0x00, 0x00,
+        ];
+        assert_eq!(decode(&list).unwrap_err().kind(), ErrorKind::InvalidData);
+        let string = [0x00, 0x01, 0x01, 0x00, 0x00, 0x0A, 0xFF, 0xFF];
+        assert_eq!(decode(&string).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        // lists nested up to the depth limit and past it
+        let nested = |depth: usize| {
+            let mut slot = vec![
+                0x00, 0x01, 0x01, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x09, 0x00, 0x00,
+            ];
+            for _ in 1..depth {
+                slot.extend_from_slice(&[0x09, 0x00, 0x00, 0x00, 0x01]);
+            }
+            slot.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+            slot
+        };
+        assert!(decode(&nested(MAX_NBT_DEPTH)).is_ok());
+        assert_eq!(
+            decode(&nested(MAX_NBT_DEPTH + 1)).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
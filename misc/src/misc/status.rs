@@ -0,0 +1,238 @@
+use std::io::{self, Error, ErrorKind};
+
+use protocol_internal::{ProtocolSupportDecoder, ProtocolSupportEncoder, VarNum};
+use serde::{Deserialize, Deserializer, Serialize};
+use uuid::Uuid;
+
+use crate::misc::chat::ChatComponent;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const FAVICON_PREFIX: &str = "data:image/png;base64,";
+
+/// The server list entry sent in response to a status request.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusResponse {
+    pub version: StatusVersion,
+    pub players: StatusPlayers,
+    #[serde(deserialize_with = "deserialize_description")]
+    pub description: ChatComponent<'static>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<Favicon>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enforces_secure_chat: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previews_chat: Option<bool>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StatusVersion {
+    pub name: String,
+    pub protocol: i32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct StatusPlayers {
+    pub max: i32,
+    pub online: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample: Vec<PlayerSample>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlayerSample {
+    pub name: String,
+    pub id: Uuid,
+}
+
+/// A server icon, as a `data:image/png;base64,` uri.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Favicon(String);
+
+impl StatusResponse {
+    pub fn new<N: Into<String>>(
+        name: N,
+        protocol: i32,
+        description: ChatComponent<'static>,
+    ) -> Self {
+        Self {
+            version: StatusVersion {
+                name: name.into(),
+                protocol,
+            },
+            players: StatusPlayers::default(),
+            description,
+            favicon: None,
+            enforces_secure_chat: None,
+            previews_chat: None,
+        }
+    }
+}
+
+impl Favicon {
+    /// Builds the favicon from a png file, which must be 64x64 like the
+    /// client expects.
+    pub fn from_png(png: &[u8]) -> io::Result<Self> {
+        // the signature is followed by the IHDR chunk, whose first fields
+        // are the image width and height
+        if png.len() < 24 || &png[..8] != PNG_SIGNATURE || &png[12..16] != b"IHDR" {
+            return Err(Error::new(ErrorKind::InvalidData, "favicon is not a png"));
+        }
+
+        let width = u32::from_be_bytes([png[16], png[17], png[18], png[19]]);
+        let height = u32::from_be_bytes([png[20], png[21], png[22], png[23]]);
+        if width != 64 || height != 64 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("favicon must be 64x64, got {}x{}", width, height),
+            ));
+        }
+
+        Ok(Self(format!("{}{}", FAVICON_PREFIX, base64::encode(png))))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<Favicon> for String {
+    fn from(favicon: Favicon) -> Self {
+        favicon.0
+    }
+}
+
+/// Older servers send the description as a plain string.
+fn deserialize_description<'de, D>(deserializer: D) -> Result<ChatComponent<'static>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Description {
+        Text(String),
+        Component(ChatComponent<'static>),
+    }
+
+    Ok(match Description::deserialize(deserializer)? {
+        Description::Text(text) => ChatComponent::new(text),
+        Description::Component(component) => component,
+    })
+}
+
+impl ProtocolSupportEncoder for StatusResponse {
+    fn calculate_len(&self, _: &::protocol_internal::ProtocolVersion) -> usize {
+        // counted without building the string; a response serde can't write
+        // counts as nothing here, `encode` reporting the error
+        let mut counter = ByteCounter(0);
+        match serde_json::to_writer(&mut counter, self) {
+            Ok(()) => VarNum::<i32>::calculate_len(&(counter.0 as i32)) + counter.0,
+            Err(_) => 0,
+        }
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        <String as ProtocolSupportEncoder>::encode(
+            &serde_json::to_string(self).map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+            dst,
+            version,
+        )
+    }
+}
+
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ProtocolSupportDecoder for StatusResponse {
+    fn decode<R: std::io::Read>(
+        src: &mut protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        serde_json::from_str(&<String as ProtocolSupportDecoder>::decode(src, version)?)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use protocol_internal::{DecodeContext, ProtocolVersion};
+
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(&13u32.to_be_bytes());
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&width.to_be_bytes());
+        png.extend_from_slice(&height.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        png
+    }
+
+    #[test]
+    fn test_favicon_from_png() {
+        let favicon = Favicon::from_png(&png(64, 64)).unwrap();
+        assert!(favicon
+            .as_str()
+            .starts_with("data:image/png;base64,iVBORw0KGgo"));
+
+        let err = Favicon::from_png(&png(32, 64)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        assert!(Favicon::from_png(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_status_response_roundtrip() {
+        let mut status = StatusResponse::new("1.8.9", 47, ChatComponent::new("A server"));
+        status.players.max = 20;
+        status.players.online = 1;
+        status.players.sample.push(PlayerSample {
+            name: "SaiintBrisson".into(),
+            id: Uuid::nil(),
+        });
+        status.enforces_secure_chat = Some(true);
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["version"]["protocol"], 47);
+        assert_eq!(json["players"]["sample"][0]["id"], Uuid::nil().to_string());
+        assert_eq!(json["enforcesSecureChat"], true);
+        assert!(json.get("favicon").is_none());
+
+        let version = ProtocolVersion::new(47);
+        let mut buf = vec![0; status.calculate_len(&version)];
+        status.encode(&mut &mut buf[..], &version).unwrap();
+
+        let decoded = StatusResponse::decode(&mut DecodeContext::from(&buf[..]), &version).unwrap();
+        assert_eq!(decoded.players.sample[0].name, "SaiintBrisson");
+        assert_eq!(decoded.description.text.as_deref(), Some("A server"));
+    }
+
+    #[test]
+    fn test_status_response_plain_description() {
+        let status: StatusResponse = serde_json::from_str(
+            r#"{"version":{"name":"1.8.9","protocol":47},"players":{"max":20,"online":0},"description":"hello"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(status.description.text.as_deref(), Some("hello"));
+        assert!(status.players.sample.is_empty());
+    }
+}
@@ -31,15 +31,18 @@ impl EntityLocation {
     }
 }
 
+/// Sent as the block position holding the entity, see the `Vec3D<i32>`
+/// implementation.
 impl ProtocolPosition for EntityLocation {
     fn to_position(&self) -> i64 {
-        ((self.x as i64) << 38) | ((self.z as i64 & 0x3FFFFFF) << 12) | (self.y as i64 & 0xFFF)
+        self.block_position().to_position()
     }
     fn from_position(position: i64) -> Self {
+        let Vec3D { x, y, z } = Vec3D::<i32>::from_position(position);
         Self {
-            x: (position >> 38) as f64,
-            y: (position & 0xFFF) as f64,
-            z: (position << 26 >> 38) as f64,
+            x: x as f64,
+            y: y as f64,
+            z: z as f64,
             ..Default::default()
         }
     }
@@ -96,15 +96,18 @@ where
     }
 }
 
+/// Packed as 26 bits of x, 12 of y and 26 of z, the layout used until 1.14.
 impl ProtocolPosition for Vec3D<i32> {
     fn to_position(&self) -> i64 {
-        (((self.x as i64) << 12) | (self.y as i64 & 0xFFF) << 26) | (self.z as i64 & 0x3FFFFFF)
+        ((self.x as i64 & 0x3FFFFFF) << 38)
+            | ((self.y as i64 & 0xFFF) << 26)
+            | (self.z as i64 & 0x3FFFFFF)
     }
     fn from_position(position: i64) -> Self {
         Self {
             x: (position >> 38) as i32,
-            y: (position & 0xFFF) as i32,
-            z: (position << 26 >> 38) as i32,
+            y: (position << 26 >> 52) as i32,
+            z: (position << 38 >> 38) as i32,
         }
     }
 }
@@ -166,3 +169,23 @@ impl<E> From<E> for Error<E> {
 }
 
 impl<T: std::error::Error> std::error::Error for Error<T> {}
+
+#[cfg(test)]
+mod test {
+    use protocol_internal::ProtocolPosition;
+
+    use super::Vec3D;
+
+    #[test]
+    fn test_block_position() {
+        let position = Vec3D::new(-30_000_000, 255, 1234);
+        assert_eq!(Vec3D::from_position(position.to_position()), position);
+
+        assert_eq!(Vec3D::new(1, 2, 3).to_position(), 1 << 38 | 2 << 26 | 3);
+        assert_eq!(
+            Vec3D::from_position(-1),
+            Vec3D::new(-1, -1, -1),
+            "every coordinate is sign extended"
+        );
+    }
+}
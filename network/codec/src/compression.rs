@@ -0,0 +1,243 @@
+use std::io::{self, Error, ErrorKind};
+
+use protocol::{VarNum, VarNumExt};
+
+/// The biggest uncompressed packet a vanilla 1.8 client accepts.
+pub const DEFAULT_MAX_UNCOMPRESSED_LEN: usize = 2_097_152;
+
+/// Zlib compression level, from `0` (no compression) up to `9`.
+///
+/// When the `libdeflate` backend is enabled, levels up to `12` are accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompressionLevel(u32);
+
+impl CompressionLevel {
+    pub const fn new(level: u32) -> Self {
+        Self(level)
+    }
+
+    pub const fn none() -> Self {
+        Self(0)
+    }
+
+    pub const fn fast() -> Self {
+        Self(1)
+    }
+
+    pub const fn best() -> Self {
+        Self(9)
+    }
+
+    pub const fn level(&self) -> u32 {
+        self.0
+    }
+}
+
+impl Default for CompressionLevel {
+    fn default() -> Self {
+        Self::best()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CompressionSettings {
+    /// Packets at least this big get compressed, `None` disables compression.
+    pub threshold: Option<usize>,
+    pub level: CompressionLevel,
+    /// Frames declaring a bigger uncompressed len are rejected before inflating.
+    pub max_uncompressed_len: usize,
+}
+
+impl CompressionSettings {
+    /// Builds the frame body for an already encoded packet, compressing it
+    /// if it is bigger than the threshold.
+    ///
+    /// This does not touch any codec state, so it can be moved to a blocking
+    /// thread and the result written with [`crate::Codec`] later on.
+    pub fn prepare(&self, payload: Vec<u8>) -> io::Result<PreparedPacket> {
+        let threshold = match self.threshold {
+            Some(threshold) => threshold,
+            None => {
+                return Ok(PreparedPacket {
+                    body: payload,
+                    compressed: false,
+                })
+            }
+        };
+
+        let mut body = Vec::with_capacity(payload.len() + 5);
+        if payload.len() >= threshold {
+            VarNum::<i32>::encode(&(payload.len() as i32), &mut body)?;
+            compress(&payload, self.level, &mut body)?;
+        } else {
+            body.push(0);
+            body.extend_from_slice(&payload);
+        }
+
+        Ok(PreparedPacket {
+            body,
+            compressed: true,
+        })
+    }
+
+    /// Same as [`CompressionSettings::prepare`], but runs on tokio's blocking
+    /// pool so big packets don't stall the reactor.
+    #[cfg(feature = "blocking")]
+    pub async fn prepare_blocking(&self, payload: Vec<u8>) -> io::Result<PreparedPacket> {
+        let settings = self.clone();
+        tokio::task::spawn_blocking(move || settings.prepare(payload))
+            .await
+            .map_err(Error::other)?
+    }
+
+    /// Inflates `src` into `dst`, making sure the result is exactly as big as
+    /// the declared `uncompressed_len`.
+    pub(crate) fn decompress(
+        &self,
+        src: &[u8],
+        uncompressed_len: usize,
+        dst: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        if uncompressed_len > self.max_uncompressed_len {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "uncompressed len {} is bigger than max {}",
+                    uncompressed_len, self.max_uncompressed_len
+                ),
+            ));
+        }
+
+        decompress(src, uncompressed_len, dst)
+    }
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            threshold: None,
+            level: CompressionLevel::default(),
+            max_uncompressed_len: DEFAULT_MAX_UNCOMPRESSED_LEN,
+        }
+    }
+}
+
+/// A packet whose frame body was built ahead of time, see
+/// [`CompressionSettings::prepare`].
+#[derive(Debug)]
+pub struct PreparedPacket {
+    body: Vec<u8>,
+    compressed: bool,
+}
+
+impl PreparedPacket {
+    pub fn len(&self) -> usize {
+        self.body.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.body.is_empty()
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    pub(crate) fn body(&self) -> &[u8] {
+        &self.body[..]
+    }
+
+    pub(crate) fn frame_len(&self) -> usize {
+        self.body.len() + (self.body.len() as i32).varnum_len()
+    }
+}
+
+#[cfg(not(feature = "libdeflate"))]
+pub(crate) fn compress(src: &[u8], level: CompressionLevel, dst: &mut Vec<u8>) -> io::Result<()> {
+    use std::io::Write;
+
+    use flate2::{write::ZlibEncoder, Compression};
+
+    let mut encoder = ZlibEncoder::new(dst, Compression::new(level.level().min(9)));
+    encoder.write_all(src)?;
+    encoder.finish().map(|_| ())
+}
+
+#[cfg(not(feature = "libdeflate"))]
+fn decompress(src: &[u8], len: usize, dst: &mut Vec<u8>) -> io::Result<()> {
+    use std::io::Read;
+
+    use flate2::read::ZlibDecoder;
+
+    let start = dst.len();
+    dst.reserve(len);
+
+    // reads at most one extra byte, enough to tell the declared len was a lie
+    ZlibDecoder::new(src)
+        .take(len as u64 + 1)
+        .read_to_end(dst)?;
+
+    match dst.len() - start {
+        read if read == len => Ok(()),
+        read if read > len => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("decompressed len is bigger than declared len {}", len),
+        )),
+        read => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "decompressed len {} does not match declared len {}",
+                read, len
+            ),
+        )),
+    }
+}
+
+#[cfg(feature = "libdeflate")]
+pub(crate) fn compress(src: &[u8], level: CompressionLevel, dst: &mut Vec<u8>) -> io::Result<()> {
+    use libdeflater::{CompressionLvl, Compressor};
+
+    let level = CompressionLvl::new(level.level().min(12) as i32)
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid compression level"))?;
+    let mut compressor = Compressor::new(level);
+
+    let start = dst.len();
+    dst.resize(start + compressor.zlib_compress_bound(src.len()), 0);
+
+    let written = compressor
+        .zlib_compress(src, &mut dst[start..])
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+    dst.truncate(start + written);
+
+    Ok(())
+}
+
+#[cfg(feature = "libdeflate")]
+fn decompress(src: &[u8], len: usize, dst: &mut Vec<u8>) -> io::Result<()> {
+    use libdeflater::{DecompressionError, Decompressor};
+
+    let start = dst.len();
+    dst.resize(start + len, 0);
+
+    let read = Decompressor::new()
+        .zlib_decompress(src, &mut dst[start..])
+        .map_err(|err| match err {
+            DecompressionError::InsufficientSpace => Error::new(
+                ErrorKind::InvalidData,
+                format!("decompressed len is bigger than declared len {}", len),
+            ),
+            err => Error::new(ErrorKind::InvalidData, err),
+        })?;
+
+    if read != len {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "decompressed len {} does not match declared len {}",
+                read, len
+            ),
+        ));
+    }
+
+    Ok(())
+}
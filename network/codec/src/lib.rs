@@ -1,28 +1,40 @@
-use std::io::{self, Error, ErrorKind, Read, Write};
+#[cfg(feature = "compression")]
+pub mod compression;
+
+#[cfg(feature = "compression")]
+use std::io::Write;
+use std::io::{self, Error, ErrorKind};
 
 #[cfg(feature = "encryption")]
 use aes::cipher::{AsyncStreamCipher, NewCipher};
 use bytes::{Buf, BytesMut};
 #[cfg(feature = "encryption")]
 use cfb8::Cfb8;
-#[cfg(feature = "compression")]
-use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
-use protocol::{PacketDecoder, PacketEncoder, ProtocolVersion, VarNum, VarNumExt};
+use protocol::{
+    DecodeContext, DecodeMode, PacketDecoder, PacketEncoder, ProtocolVersion, VarNum, VarNumExt,
+};
+#[cfg(feature = "tokio-util")]
 use tokio_util::codec::{Decoder, Encoder};
 
+#[cfg(feature = "compression")]
+use compression::{CompressionLevel, CompressionSettings, PreparedPacket};
+
 #[cfg(feature = "encryption")]
 type AesCfb8 = Cfb8<aes::Aes128>;
 
 pub struct Codec<T> {
     version: ProtocolVersion,
+    decode_mode: DecodeMode,
 
     staging_buf: BytesMut,
     payload_len: Option<usize>,
 
     #[cfg(feature = "compression")]
-    compression_threshold: Option<usize>,
+    compression: CompressionSettings,
     #[cfg(feature = "encryption")]
-    cipher: Option<AesCfb8>,
+    encryptor: Option<AesCfb8>,
+    #[cfg(feature = "encryption")]
+    decryptor: Option<AesCfb8>,
 
     _data: std::marker::PhantomData<T>,
 }
@@ -33,31 +45,96 @@ impl<T> Codec<T> {
         &self.version
     }
 
+    /// Get the codec's decode mode.
+    pub fn decode_mode(&self) -> DecodeMode {
+        self.decode_mode
+    }
+
+    /// Sets how bytes left unread by a packet decoder are handled.
+    pub fn set_decode_mode(&mut self, mode: DecodeMode) {
+        self.decode_mode = mode;
+    }
+
     /// Enables zlib compression for this codec.
     #[cfg(feature = "compression")]
     pub fn enable_compression(&mut self, threshold: i32) {
         tracing::debug!(%threshold, "enabled compression");
-        self.compression_threshold = Some(threshold as usize);
+        self.compression.threshold = Some(threshold as usize);
+    }
+
+    /// Sets the zlib level used for outgoing packets.
+    #[cfg(feature = "compression")]
+    pub fn set_compression_level(&mut self, level: CompressionLevel) {
+        self.compression.level = level;
+    }
+
+    /// Sets the biggest uncompressed len accepted from the peer.
+    #[cfg(feature = "compression")]
+    pub fn set_max_uncompressed_len(&mut self, max: usize) {
+        self.compression.max_uncompressed_len = max;
+    }
+
+    /// Get a reference to the codec's compression settings.
+    ///
+    /// Cloning these allows compressing packets away from the codec,
+    /// see [`CompressionSettings::prepare`].
+    #[cfg(feature = "compression")]
+    pub fn compression(&self) -> &CompressionSettings {
+        &self.compression
     }
 
     /// Enables aes-cfb8 encryption for this codec.
     #[cfg(feature = "encryption")]
     pub fn enable_encryption(&mut self, secret: &[u8; 16]) {
         tracing::debug!("enabled encryption");
-        self.cipher = Some(AesCfb8::new_from_slices(&secret[..], &secret[..]).unwrap())
+        self.encryptor = Some(AesCfb8::new_from_slices(&secret[..], &secret[..]).unwrap());
+        self.decryptor = Some(AesCfb8::new_from_slices(&secret[..], &secret[..]).unwrap());
     }
 
     /// Adapts this codec to a new packet decoder.
     pub fn adapt<N>(self) -> Codec<N> {
         Codec {
             version: self.version,
+            decode_mode: self.decode_mode,
             staging_buf: self.staging_buf,
             payload_len: self.payload_len,
-            compression_threshold: self.compression_threshold,
-            cipher: self.cipher,
+            #[cfg(feature = "compression")]
+            compression: self.compression,
+            #[cfg(feature = "encryption")]
+            encryptor: self.encryptor,
+            #[cfg(feature = "encryption")]
+            decryptor: self.decryptor,
             _data: Default::default(),
         }
     }
+
+    /// Splits this codec into a decoding and an encoding half.
+    ///
+    /// Both halves share the current settings, but each only keeps the
+    /// cipher for its own direction.
+    pub fn split(self) -> (Codec<T>, Codec<T>) {
+        let encoder = Codec {
+            version: self.version,
+            decode_mode: self.decode_mode,
+            staging_buf: BytesMut::new(),
+            payload_len: None,
+            #[cfg(feature = "compression")]
+            compression: self.compression.clone(),
+            #[cfg(feature = "encryption")]
+            encryptor: self.encryptor,
+            #[cfg(feature = "encryption")]
+            decryptor: None,
+            _data: Default::default(),
+        };
+
+        let decoder = Codec {
+            #[cfg(feature = "encryption")]
+            encryptor: None,
+            ..self
+        };
+
+        (decoder, encoder)
+    }
 }
 
 impl<I, T> From<I> for Codec<T>
@@ -67,35 +144,35 @@ where
     fn from(version: I) -> Self {
         Self {
             version: version.into(),
+            decode_mode: DecodeMode::default(),
             staging_buf: BytesMut::with_capacity(512),
             payload_len: None,
 
             #[cfg(feature = "encryption")]
-            cipher: None,
+            encryptor: None,
+            #[cfg(feature = "encryption")]
+            decryptor: None,
             #[cfg(feature = "compression")]
-            compression_threshold: None,
+            compression: Default::default(),
 
             _data: Default::default(),
         }
     }
 }
 
-impl<T> Decoder for Codec<T>
-where
-    T: PacketDecoder,
-{
-    type Item = T;
-
-    type Error = Error;
-
-    fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+impl<T> Codec<T> {
+    /// Decodes the next frame as `P`, regardless of this codec's packet type.
+    ///
+    /// Useful when a single packet of another state has to be read without
+    /// adapting the whole codec.
+    pub fn decode_packet<P: PacketDecoder>(&mut self, src: &mut BytesMut) -> io::Result<Option<P>> {
         match self.payload_len {
             Some(len) if src.len() + self.staging_buf.len() < len => return Ok(None),
             _ => {}
         };
 
         #[cfg(feature = "encryption")]
-        if let Some(cipher) = self.cipher.as_mut() {
+        if let Some(cipher) = self.decryptor.as_mut() {
             cipher.decrypt(&mut src[..]);
         }
 
@@ -114,60 +191,85 @@ where
             }
         };
 
-        let mut buf = &self.staging_buf[..];
+        let len = self.payload_len.unwrap_or_default();
+        let packet = {
+            let frame = &self.staging_buf[..len];
 
-        #[cfg(feature = "compression")]
-        let mut src = match self.compression_threshold {
-            Some(threshold) => {
-                let uncompressed_len = VarNum::<i32>::decode(&mut buf)? as usize;
-                if uncompressed_len == 0 {
-                    Reader::RawReader(buf)
-                } else {
-                    if uncompressed_len < threshold {
-                        return Err(Error::new(
-                            ErrorKind::InvalidInput,
-                            format!(
-                                "uncompressed len {} is smaller than threshold {}",
-                                uncompressed_len, threshold
-                            ),
-                        ));
+            #[cfg(feature = "compression")]
+            let mut decompressed = Vec::new();
+            #[cfg(feature = "compression")]
+            let src = match self.compression.threshold {
+                Some(threshold) => {
+                    let mut buf = frame;
+                    let uncompressed_len = VarNum::<i32>::decode(&mut buf)? as usize;
+                    if uncompressed_len == 0 {
+                        buf
+                    } else {
+                        if uncompressed_len < threshold {
+                            return Err(Error::new(
+                                ErrorKind::InvalidInput,
+                                format!(
+                                    "uncompressed len {} is smaller than threshold {}",
+                                    uncompressed_len, threshold
+                                ),
+                            ));
+                        }
+
+                        self.compression
+                            .decompress(buf, uncompressed_len, &mut decompressed)?;
+                        &decompressed[..]
                     }
-                    Reader::CompressedReader(ZlibDecoder::new(buf))
                 }
-            }
-            None => Reader::RawReader(buf),
-        };
-        #[cfg(not(feature = "compression"))]
-        let mut src = Reader::RawReader(buf);
+                None => frame,
+            };
+            #[cfg(not(feature = "compression"))]
+            let src = frame;
 
-        let packet = PacketDecoder::decode(&mut src, &self.version)?;
+            let mut ctx = DecodeContext::from(src);
+            PacketDecoder::decode(&mut ctx, &self.version).and_then(|packet| {
+                check_trailing_bytes(packet, src, ctx.remaining(), self.decode_mode)
+            })
+        };
 
         self.payload_len = None;
-        self.staging_buf.truncate(0);
+        self.staging_buf.advance(len);
 
-        Ok(Some(packet))
+        packet.map(Some)
     }
 }
 
-impl<P, T> Encoder<P> for Codec<T>
+#[cfg(feature = "tokio-util")]
+impl<T> Decoder for Codec<T>
 where
-    P: PacketEncoder,
+    T: PacketDecoder,
 {
+    type Item = T;
+
     type Error = Error;
 
-    fn encode(&mut self, item: P, dst: &mut BytesMut) -> Result<(), Self::Error> {
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.decode_packet(src)
+    }
+}
+
+impl<T> Codec<T> {
+    /// Encodes a packet into a frame, compressed and encrypted as set up.
+    pub fn encode_packet<P: PacketEncoder>(
+        &mut self,
+        item: &P,
+        dst: &mut BytesMut,
+    ) -> io::Result<()> {
         let pos = dst.len();
-        let len = PacketEncoder::calculate_len(&item, &self.version);
+        let len = PacketEncoder::calculate_len(item, &self.version);
 
         #[cfg(feature = "compression")]
-        match self.compression_threshold {
+        match self.compression.threshold {
             Some(threshold) if len >= threshold => {
-                let mut buf = Vec::with_capacity(len);
+                let mut payload = vec![0; len];
+                PacketEncoder::encode(item, &mut &mut payload[..], &self.version)?;
 
-                {
-                    let mut encoder = ZlibEncoder::new(&mut buf, Compression::best());
-                    PacketEncoder::encode(&item, &mut encoder, &self.version)?;
-                }
+                let mut buf = Vec::with_capacity(len);
+                compression::compress(&payload, self.compression.level, &mut buf)?;
 
                 let compressed_len = buf.len() + (len as i32).varnum_len();
                 dst.resize(
@@ -181,19 +283,19 @@ where
                 dst.write_all(&buf[..])?;
             }
             Some(_) => {
-                dst.resize(dst.len() + len + (len as i32).varnum_len() + 1, 0);
+                dst.resize(dst.len() + len + (len as i32 + 1).varnum_len() + 1, 0);
 
                 let dst = &mut &mut dst[pos..];
-                VarNum::<i32>::encode(&(len as i32), dst)?;
+                VarNum::<i32>::encode(&(len as i32 + 1), dst)?;
                 *dst = &mut dst[1..];
-                PacketEncoder::encode(&item, dst, &self.version)?;
+                PacketEncoder::encode(item, dst, &self.version)?;
             }
             None => {
                 dst.resize(dst.len() + len + (len as i32).varnum_len(), 0);
 
                 let dst = &mut &mut dst[pos..];
                 VarNum::<i32>::encode(&(len as i32), dst)?;
-                PacketEncoder::encode(&item, dst, &self.version)?;
+                PacketEncoder::encode(item, dst, &self.version)?;
             }
         }
 
@@ -203,11 +305,11 @@ where
 
             let dst = &mut &mut dst[pos..];
             VarNum::<i32>::encode(&(len as i32), dst)?;
-            PacketEncoder::encode(&item, dst, &self.version)?;
+            PacketEncoder::encode(item, dst, &self.version)?;
         }
 
         #[cfg(feature = "encryption")]
-        if let Some(cipher) = self.cipher.as_mut() {
+        if let Some(cipher) = self.encryptor.as_mut() {
             cipher.encrypt(&mut dst[pos..]);
         }
 
@@ -215,22 +317,81 @@ where
     }
 }
 
-enum Reader<T> {
-    #[cfg(feature = "compression")]
-    CompressedReader(ZlibDecoder<T>),
-    RawReader(T),
-}
+#[cfg(feature = "compression")]
+impl<T> Codec<T> {
+    /// Writes a frame built by [`CompressionSettings::prepare`].
+    pub fn encode_prepared(&mut self, item: &PreparedPacket, dst: &mut BytesMut) -> io::Result<()> {
+        if item.is_compressed() != self.compression.threshold.is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "prepared packet does not match the codec compression state",
+            ));
+        }
 
-impl<T: Read> Read for Reader<T> {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        match self {
-            #[cfg(feature = "compression")]
-            Reader::CompressedReader(src) => src.read(buf),
-            Reader::RawReader(src) => src.read(buf),
+        let pos = dst.len();
+        dst.resize(pos + item.frame_len(), 0);
+
+        {
+            let dst = &mut &mut dst[pos..];
+            VarNum::<i32>::encode(&(item.len() as i32), dst)?;
+            dst.write_all(item.body())?;
+        }
+
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = self.encryptor.as_mut() {
+            cipher.encrypt(&mut dst[pos..]);
         }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio-util")]
+impl<P, T> Encoder<P> for Codec<T>
+where
+    P: PacketEncoder,
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: P, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode_packet(&item, dst)
+    }
+}
+
+#[cfg(all(feature = "compression", feature = "tokio-util"))]
+impl<T> Encoder<PreparedPacket> for Codec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, item: PreparedPacket, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode_prepared(&item, dst)
     }
 }
 
+fn check_trailing_bytes<T: std::fmt::Debug>(
+    packet: T,
+    payload: &[u8],
+    remaining: usize,
+    mode: DecodeMode,
+) -> io::Result<T> {
+    if remaining == 0 {
+        return Ok(packet);
+    }
+
+    let id = VarNum::<i32>::decode(&mut &payload[..]).unwrap_or(-1);
+    if mode.is_strict() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "packet {:#04x} left {} trailing bytes unread",
+                id, remaining
+            ),
+        ));
+    }
+
+    tracing::warn!(id, remaining, ?packet, "packet left trailing bytes unread");
+    Ok(packet)
+}
+
 fn validate_varint(arr: &[u8]) -> io::Result<Option<(usize, usize)>> {
     let mut len = 0usize;
 
@@ -239,9 +400,8 @@ fn validate_varint(arr: &[u8]) -> io::Result<Option<(usize, usize)>> {
             return Err(Error::new(ErrorKind::InvalidInput, "frame len too big"));
         }
 
-        if b & 0x80 != 0 {
-            len |= usize::from(*b & 0x7f) << (7 * i);
-        } else {
+        len |= usize::from(*b & 0x7f) << (7 * i);
+        if b & 0x80 == 0 {
             return Ok(Some((i + 1, len)));
         }
     }
@@ -252,7 +412,10 @@ fn validate_varint(arr: &[u8]) -> io::Result<Option<(usize, usize)>> {
 #[cfg(test)]
 mod test {
     use protocol::{
-        packets::play::{server_bound::PluginMessage, ServerBound},
+        packets::play::{
+            server_bound::{KeepAlive, PluginMessage},
+            ServerBound,
+        },
         ProtocolVersionEnum,
     };
 
@@ -263,7 +426,7 @@ mod test {
 
         let packet = ServerBound::PluginMessage(PluginMessage {
             channel: "plain".into(),
-            data: vec![0; 128],
+            data: vec![0; 128].into(),
         });
 
         assert!(matches!(codec.encode(packet, &mut buf), Ok(_)));
@@ -279,13 +442,16 @@ mod test {
     fn test_codec_roundtrip_uncompressed() {
         test_codec(Codec {
             version: ProtocolVersionEnum::V1_8.into(),
+            decode_mode: DecodeMode::Strict,
             staging_buf: BytesMut::with_capacity(128),
             payload_len: None,
 
             #[cfg(feature = "compression")]
-            compression_threshold: None,
+            compression: Default::default(),
             #[cfg(feature = "encryption")]
-            cipher: None,
+            encryptor: None,
+            #[cfg(feature = "encryption")]
+            decryptor: None,
 
             _data: Default::default(),
         });
@@ -295,13 +461,19 @@ mod test {
     fn test_codec_roundtrip_under_compression_threshold() {
         test_codec(Codec {
             version: ProtocolVersionEnum::V1_8.into(),
+            decode_mode: DecodeMode::Strict,
             staging_buf: BytesMut::with_capacity(128),
             payload_len: None,
 
             #[cfg(feature = "compression")]
-            compression_threshold: Some(256),
+            compression: CompressionSettings {
+                threshold: Some(256),
+                ..Default::default()
+            },
+            #[cfg(feature = "encryption")]
+            encryptor: None,
             #[cfg(feature = "encryption")]
-            cipher: None,
+            decryptor: None,
 
             _data: Default::default(),
         });
@@ -311,18 +483,144 @@ mod test {
     fn test_codec_roundtrip_over_compression_threshold() {
         test_codec(Codec {
             version: ProtocolVersionEnum::V1_8.into(),
+            decode_mode: DecodeMode::Strict,
             staging_buf: BytesMut::with_capacity(128),
             payload_len: None,
 
             #[cfg(feature = "compression")]
-            compression_threshold: Some(128),
+            compression: CompressionSettings {
+                threshold: Some(128),
+                ..Default::default()
+            },
             #[cfg(feature = "encryption")]
-            cipher: None,
+            encryptor: None,
+            #[cfg(feature = "encryption")]
+            decryptor: None,
 
             _data: Default::default(),
         });
     }
 
+    fn keep_alive_with_trailing_bytes() -> BytesMut {
+        // len, packet id, keep alive id, 3 extra bytes
+        BytesMut::from(&[0x05, 0x00, 0x2A, 0x01, 0x02, 0x03][..])
+    }
+
+    #[test]
+    fn test_codec_strict_rejects_trailing_bytes() {
+        let mut codec = Codec::<ServerBound>::from(ProtocolVersionEnum::V1_8);
+        codec.set_decode_mode(DecodeMode::Strict);
+
+        let mut buf = keep_alive_with_trailing_bytes();
+        match codec.decode(&mut buf) {
+            Err(err) => {
+                assert_eq!(err.kind(), ErrorKind::InvalidData);
+                assert!(err.to_string().contains("left 3 trailing bytes"));
+            }
+            other => panic!("expected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_codec_lenient_accepts_trailing_bytes() {
+        let mut codec = Codec::<ServerBound>::from(ProtocolVersionEnum::V1_8);
+        codec.set_decode_mode(DecodeMode::Lenient);
+
+        let mut buf = keep_alive_with_trailing_bytes();
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Ok(Some(ServerBound::KeepAlive(KeepAlive {
+                keep_alive_id: 42
+            })))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_codec_roundtrip_fast_compression() {
+        let mut codec = Codec::from(ProtocolVersionEnum::V1_8);
+        codec.enable_compression(128);
+        codec.set_compression_level(CompressionLevel::fast());
+
+        test_codec(codec);
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_codec_roundtrip_prepared() {
+        let mut codec = Codec::<ServerBound>::from(ProtocolVersionEnum::V1_8);
+        codec.enable_compression(128);
+
+        let packet = ServerBound::PluginMessage(PluginMessage {
+            channel: "prepared".into(),
+            data: vec![0; 128].into(),
+        });
+
+        let version = ProtocolVersionEnum::V1_8.into();
+        let mut payload = vec![0; PacketEncoder::calculate_len(&packet, &version)];
+        PacketEncoder::encode(&packet, &mut &mut payload[..], &version).unwrap();
+
+        let prepared = codec.compression().prepare(payload).unwrap();
+        assert!(prepared.is_compressed());
+
+        let mut buf = BytesMut::new();
+        assert!(codec.encode(prepared, &mut buf).is_ok());
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Ok(Some(ServerBound::PluginMessage { .. }))
+        ));
+    }
+
+    #[cfg(feature = "compression")]
+    fn compressed_frame(declared_len: usize, data: &[u8]) -> BytesMut {
+        let mut body = vec![];
+        VarNum::<i32>::encode(&(declared_len as i32), &mut body).unwrap();
+        compression::compress(data, CompressionLevel::default(), &mut body).unwrap();
+
+        let mut frame = vec![];
+        VarNum::<i32>::encode(&(body.len() as i32), &mut frame).unwrap();
+        frame.extend_from_slice(&body);
+
+        BytesMut::from(&frame[..])
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_codec_rejects_decompressed_len_mismatch() {
+        let mut codec = Codec::<ServerBound>::from(ProtocolVersionEnum::V1_8);
+        codec.enable_compression(128);
+
+        let mut buf = compressed_frame(256, &[0; 512]);
+        assert!(matches!(codec.decode(&mut buf), Err(err) if err.kind() == ErrorKind::InvalidData));
+
+        let mut buf = compressed_frame(256, &[0; 200]);
+        assert!(matches!(codec.decode(&mut buf), Err(err) if err.kind() == ErrorKind::InvalidData));
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_codec_rejects_uncompressed_len_over_max() {
+        let mut codec = Codec::<ServerBound>::from(ProtocolVersionEnum::V1_8);
+        codec.enable_compression(128);
+        codec.set_max_uncompressed_len(1024);
+
+        let mut buf = compressed_frame(4096, &[0; 4096]);
+        assert!(matches!(codec.decode(&mut buf), Err(err) if err.kind() == ErrorKind::InvalidData));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "blocking")]
+    async fn test_prepare_blocking() {
+        let settings = CompressionSettings {
+            threshold: Some(128),
+            ..Default::default()
+        };
+
+        let prepared = settings.prepare_blocking(vec![0; 256]).await.unwrap();
+        assert!(prepared.is_compressed());
+        assert!(prepared.len() < 256);
+    }
+
     #[cfg(feature = "encryption")]
     fn test_codec_cipher(mut codec: Codec<ServerBound>) {
         #[rustfmt::skip]
@@ -337,7 +635,7 @@ mod test {
 
         let packet = ServerBound::PluginMessage(PluginMessage {
             channel: "encrypted".into(),
-            data: vec![0; 128],
+            data: vec![0; 128].into(),
         });
 
         codec.enable_encryption(&SECRET);
@@ -357,11 +655,13 @@ mod test {
     fn test_codec_roundtrip_encrypted() {
         test_codec_cipher(Codec {
             version: ProtocolVersionEnum::V1_8.into(),
+            decode_mode: DecodeMode::Strict,
             staging_buf: BytesMut::with_capacity(128),
             payload_len: None,
 
-            compression_threshold: None,
-            cipher: None,
+            compression: Default::default(),
+            encryptor: None,
+            decryptor: None,
 
             _data: Default::default(),
         });
@@ -372,13 +672,41 @@ mod test {
     fn test_codec_roundtrip_encrypted_compressed() {
         test_codec_cipher(Codec {
             version: ProtocolVersionEnum::V1_8.into(),
+            decode_mode: DecodeMode::Strict,
             staging_buf: BytesMut::with_capacity(128),
             payload_len: None,
 
-            compression_threshold: Some(128),
-            cipher: None,
+            compression: CompressionSettings {
+                threshold: Some(128),
+                ..Default::default()
+            },
+            encryptor: None,
+            decryptor: None,
 
             _data: Default::default(),
         });
     }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn test_codec_split_keeps_cipher_streams() {
+        let secret = [0x2A; 16];
+
+        let mut codec = Codec::<ServerBound>::from(ProtocolVersionEnum::V1_8);
+        codec.enable_encryption(&secret);
+        let (mut decoder, mut encoder) = codec.split();
+
+        let mut buf = BytesMut::new();
+        for keep_alive_id in 0..3 {
+            let packet = ServerBound::KeepAlive(KeepAlive { keep_alive_id });
+            assert!(encoder.encode(packet, &mut buf).is_ok());
+        }
+
+        for expected in 0..3 {
+            assert!(matches!(
+                decoder.decode(&mut buf),
+                Ok(Some(ServerBound::KeepAlive(KeepAlive { keep_alive_id }))) if keep_alive_id == expected
+            ));
+        }
+    }
 }
@@ -2,7 +2,7 @@ use std::io;
 
 const NUM_SHIFT: [u8; 10] = [0, 7, 14, 21, 28, 35, 42, 49, 56, 63];
 
-use protocol::{PacketDecoder, PacketEncoder, ProtocolVersion};
+use protocol::{DecodeContext, DecodeMode, PacketDecoder, PacketEncoder, ProtocolVersion};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 pub async fn read_varint<R>(src: &mut R) -> io::Result<i32>
@@ -50,6 +50,19 @@ where
 }
 
 pub async fn read_packet<P, R>(src: &mut R, version: &ProtocolVersion) -> io::Result<P>
+where
+    P: PacketDecoder,
+    R: tokio::io::AsyncRead + Unpin,
+{
+    read_packet_with_mode(src, version, DecodeMode::default()).await
+}
+
+/// Reads a packet, handling bytes left unread by its decoder according to `mode`.
+pub async fn read_packet_with_mode<P, R>(
+    src: &mut R,
+    version: &ProtocolVersion,
+    mode: DecodeMode,
+) -> io::Result<P>
 where
     P: PacketDecoder,
     R: tokio::io::AsyncRead + Unpin,
@@ -59,7 +72,20 @@ where
     let mut buf = vec![0; len];
     src.read_exact(&mut buf[..]).await?;
 
-    PacketDecoder::decode(&mut &buf[..], version)
+    let mut ctx = DecodeContext::from(&buf[..]);
+    let packet = PacketDecoder::decode(&mut ctx, version)?;
+
+    match ctx.remaining() {
+        0 => Ok(packet),
+        remaining if mode.is_strict() => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("packet left {} trailing bytes unread", remaining),
+        )),
+        remaining => {
+            tracing::warn!(remaining, ?packet, "packet left trailing bytes unread");
+            Ok(packet)
+        }
+    }
 }
 
 pub async fn write_packet<P, W>(
@@ -114,4 +140,36 @@ mod test {
             assert!(matches!(packet, Ok(Handshake { .. })))
         });
     }
+
+    #[test]
+    fn test_packet_trailing_bytes() {
+        futures::executor::block_on(async {
+            let packet = Handshake {
+                protocol_version: 47,
+                server_address: "127.0.0.1".into(),
+                server_port: 25565,
+                next_state: NextState::Status,
+            };
+
+            let version = ProtocolVersion::new(47);
+
+            let mut payload = vec![0; PacketEncoder::calculate_len(&packet, &version)];
+            PacketEncoder::encode(&packet, &mut &mut payload[..], &version).unwrap();
+            payload.extend_from_slice(&[0xCA, 0xFE]);
+
+            let mut buf = vec![];
+            write_varint(&mut buf, payload.len() as i32).await.unwrap();
+            buf.extend_from_slice(&payload);
+
+            let strict =
+                read_packet_with_mode::<Handshake, _>(&mut &buf[..], &version, DecodeMode::Strict)
+                    .await;
+            assert!(matches!(strict, Err(err) if err.kind() == io::ErrorKind::InvalidData));
+
+            let lenient =
+                read_packet_with_mode::<Handshake, _>(&mut &buf[..], &version, DecodeMode::Lenient)
+                    .await;
+            assert!(matches!(lenient, Ok(Handshake { .. })));
+        });
+    }
 }
@@ -0,0 +1,273 @@
+//! Primitives of the online-mode login: the server's RSA key, the server
+//! hash and the session server lookup.
+
+use std::{
+    future::Future,
+    io::{self, Error, ErrorKind},
+};
+
+use protocol::misc::prelude::GameProfile;
+use rsa::{
+    pkcs8::{DecodePublicKey, EncodePublicKey},
+    Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey,
+};
+use sha1::{Digest, Sha1};
+
+/// Size of the key vanilla servers generate, the one clients expect.
+pub const KEY_BITS: usize = 1024;
+
+/// The keypair a server encrypts the login with.
+#[derive(Clone, Debug)]
+pub struct ServerKey {
+    private: RsaPrivateKey,
+    public_der: Vec<u8>,
+}
+
+impl ServerKey {
+    /// Generates a new 1024 bit keypair, which takes a while.
+    pub fn generate() -> io::Result<Self> {
+        let private =
+            RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS).map_err(Error::other)?;
+        Self::from_private_key(private)
+    }
+
+    pub fn from_private_key(private: RsaPrivateKey) -> io::Result<Self> {
+        let public_der = RsaPublicKey::from(&private)
+            .to_public_key_der()
+            .map_err(Error::other)?
+            .into_vec();
+
+        Ok(Self {
+            private,
+            public_der,
+        })
+    }
+
+    /// The public key as sent in `EncryptionRequest`, in DER.
+    pub fn public_key_der(&self) -> &[u8] {
+        &self.public_der
+    }
+
+    /// Decrypts the shared secret or verify token of an
+    /// `EncryptionResponse`.
+    pub fn decrypt(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        self.private
+            .decrypt(Pkcs1v15Encrypt, data)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+/// Encrypts `data` with the DER public key sent by the server, the way the
+/// client answers an `EncryptionRequest`.
+pub fn encrypt(public_key_der: &[u8], data: &[u8]) -> io::Result<Vec<u8>> {
+    RsaPublicKey::from_public_key_der(public_key_der)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err))?
+        .encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, data)
+        .map_err(Error::other)
+}
+
+/// Computes the hash both sides send to the session server.
+///
+/// It is the SHA-1 of the server id, shared secret and public key, printed
+/// as a signed number in hex like Java's `BigInteger::toString(16)`.
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key_der: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(server_id.as_bytes());
+    hasher.update(shared_secret);
+    hasher.update(public_key_der);
+
+    signed_hex(hasher.finalize().into())
+}
+
+fn signed_hex(mut digest: [u8; 20]) -> String {
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        // two's complement, to print the magnitude
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            *byte = !*byte;
+            if carry {
+                let (sum, overflow) = byte.overflowing_add(1);
+                *byte = sum;
+                carry = overflow;
+            }
+        }
+    }
+
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let hex = hex.trim_start_matches('0');
+
+    match (negative, hex.is_empty()) {
+        (_, true) => "0".into(),
+        (true, false) => format!("-{}", hex),
+        (false, false) => hex.into(),
+    }
+}
+
+/// Checks that a player joined the server through the session server,
+/// before an online-mode login completes.
+pub trait SessionVerifier: Send + Sync {
+    /// Returns the player's profile, or `None` when they did not join with
+    /// this `server_hash`.
+    fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+    ) -> impl Future<Output = io::Result<Option<GameProfile>>> + Send;
+}
+
+/// Tells the session server a player is joining a server, what a client
+/// does before answering `EncryptionRequest`.
+pub trait Authenticator: Send + Sync {
+    fn join(&self, server_hash: &str) -> impl Future<Output = io::Result<()>> + Send;
+}
+
+/// An account without credentials, which can only join offline-mode
+/// servers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OfflineAuthenticator;
+
+impl Authenticator for OfflineAuthenticator {
+    async fn join(&self, _: &str) -> io::Result<()> {
+        Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "an offline account cannot join an online-mode server",
+        ))
+    }
+}
+
+/// Joins through Mojang's session server, or one speaking the same API.
+#[cfg(feature = "session")]
+#[derive(Clone, Debug)]
+pub struct MojangAuthenticator {
+    client: reqwest::Client,
+    base_url: String,
+    access_token: String,
+    profile_id: uuid::Uuid,
+}
+
+#[cfg(feature = "session")]
+impl MojangAuthenticator {
+    pub fn new<T: Into<String>>(access_token: T, profile_id: uuid::Uuid) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: MojangSessionVerifier::DEFAULT_BASE_URL.into(),
+            access_token: access_token.into(),
+            profile_id,
+        }
+    }
+
+    pub fn with_base_url<U: Into<String>>(mut self, base_url: U) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+}
+
+#[cfg(feature = "session")]
+impl Authenticator for MojangAuthenticator {
+    async fn join(&self, server_hash: &str) -> io::Result<()> {
+        self.client
+            .post(format!("{}/session/minecraft/join", self.base_url))
+            .json(&serde_json::json!({
+                "accessToken": self.access_token,
+                "selectedProfile": self.profile_id.to_simple().to_string(),
+                "serverId": server_hash,
+            }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| Error::new(ErrorKind::PermissionDenied, err))
+    }
+}
+
+/// Asks Mojang's session server, or one speaking the same API.
+#[cfg(feature = "session")]
+#[derive(Clone, Debug)]
+pub struct MojangSessionVerifier {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[cfg(feature = "session")]
+impl MojangSessionVerifier {
+    pub const DEFAULT_BASE_URL: &'static str = "https://sessionserver.mojang.com";
+
+    pub fn new() -> Self {
+        Self::with_base_url(Self::DEFAULT_BASE_URL)
+    }
+
+    pub fn with_base_url<U: Into<String>>(base_url: U) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.into(),
+        }
+    }
+}
+
+#[cfg(feature = "session")]
+impl Default for MojangSessionVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "session")]
+impl SessionVerifier for MojangSessionVerifier {
+    async fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+    ) -> io::Result<Option<GameProfile>> {
+        let response = self
+            .client
+            .get(format!("{}/session/minecraft/hasJoined", self.base_url))
+            .query(&[("username", username), ("serverId", server_hash)])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(Error::other)?;
+
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+
+        response
+            .json()
+            .await
+            .map(Some)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sha1_hex(name: &str) -> String {
+        signed_hex(Sha1::digest(name.as_bytes()).into())
+    }
+
+    #[test]
+    fn test_signed_hex() {
+        assert_eq!(
+            sha1_hex("Notch"),
+            "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"
+        );
+        assert_eq!(
+            sha1_hex("jeb_"),
+            "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
+        );
+        assert_eq!(sha1_hex("simon"), "88e16a1019277b15d58faf0541e11910eb756f6");
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = ServerKey::generate().unwrap();
+        let secret = [9; 16];
+
+        let encrypted = encrypt(key.public_key_der(), &secret).unwrap();
+        assert_eq!(encrypted.len(), KEY_BITS / 8);
+        assert_eq!(key.decrypt(&encrypted).unwrap(), secret);
+    }
+}
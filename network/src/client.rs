@@ -0,0 +1,285 @@
+//! Logging in as a player, for bots and test clients.
+
+use std::{
+    error,
+    fmt::{self, Display},
+    io::{self, Error, ErrorKind},
+};
+
+use protocol::{
+    misc::prelude::{ChatComponent, GameProfile},
+    packets::{
+        handshake::{Handshake, NextState},
+        login::{
+            ClientBound, Disconnect, EncryptionRequest, EncryptionResponse, LoginPluginResponse,
+            LoginStart, LoginSuccess, SetCompression,
+        },
+        play,
+    },
+    ProtocolState, ProtocolVersion,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+
+use crate::{
+    auth::{self, server_hash, Authenticator},
+    connection::Connection,
+};
+
+/// The server disconnected the client while it was logging in.
+///
+/// Returned inside an [`io::Error`] of kind `ConnectionRefused`, see
+/// [`Disconnected::from_io`].
+#[derive(Clone, Debug)]
+pub struct Disconnected {
+    pub reason: ChatComponent<'static>,
+}
+
+impl Disconnected {
+    /// Gets the disconnect out of an error returned by [`client_login`].
+    pub fn from_io(err: &io::Error) -> Option<&Self> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
+impl Display for Disconnected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "disconnected while logging in: {}",
+            self.reason.to_legacy_string()
+        )
+    }
+}
+
+impl error::Error for Disconnected {}
+
+/// Connects to `host:port` and logs in as `username`, returning the
+/// connection ready for the play state.
+pub async fn login<V, A>(
+    host: &str,
+    port: u16,
+    version: V,
+    username: &str,
+    authenticator: &A,
+) -> io::Result<(
+    Connection<TcpStream, play::ClientBound<'static>>,
+    GameProfile,
+)>
+where
+    V: Into<ProtocolVersion>,
+    A: Authenticator,
+{
+    let stream = TcpStream::connect((host, port)).await?;
+    stream.set_nodelay(true)?;
+
+    let mut conn = Connection::new(stream, version);
+    let profile = client_login(&mut conn, host, port, username, authenticator).await?;
+
+    Ok((conn.adapt(ProtocolState::Play), profile))
+}
+
+/// Logs in over an already open connection, still in the handshake state.
+///
+/// `EncryptionRequest` is answered through `authenticator`, `SetCompression`
+/// applied as it comes and login plugin requests answered as not
+/// understood. The profile is the one sent in `LoginSuccess`.
+pub async fn client_login<T, A>(
+    conn: &mut Connection<T, ()>,
+    server_address: &str,
+    server_port: u16,
+    username: &str,
+    authenticator: &A,
+) -> io::Result<GameProfile>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+    A: Authenticator,
+{
+    conn.feed(Handshake {
+        protocol_version: **conn.version(),
+        server_address: server_address.into(),
+        server_port,
+        next_state: NextState::Login,
+    })
+    .await?;
+    conn.send(LoginStart {
+        username: username.into(),
+    })
+    .await?;
+
+    loop {
+        match conn.recv_as::<ClientBound>().await?.ok_or_else(closed)? {
+            ClientBound::Disconnect(Disconnect { reason }) => {
+                return Err(Error::new(
+                    ErrorKind::ConnectionRefused,
+                    Disconnected { reason },
+                ))
+            }
+            ClientBound::EncryptionRequest(request) => {
+                let (response, secret) = encryption_response(&request, authenticator).await?;
+                conn.send(response).await?;
+                conn.enable_encryption(&secret);
+            }
+            ClientBound::SetCompression(SetCompression { threshold }) => {
+                if threshold >= 0 {
+                    conn.enable_compression(threshold);
+                }
+            }
+            ClientBound::LoginPluginRequest(request) => {
+                conn.send(LoginPluginResponse {
+                    message_id: request.message_id,
+                    data: None,
+                })
+                .await?;
+            }
+            ClientBound::LoginSuccess(LoginSuccess { uuid, username }) => {
+                return Ok(GameProfile::new(uuid, username))
+            }
+        }
+    }
+}
+
+async fn encryption_response<A: Authenticator>(
+    request: &EncryptionRequest,
+    authenticator: &A,
+) -> io::Result<(EncryptionResponse, [u8; 16])> {
+    let secret = rand::random::<[u8; 16]>();
+
+    authenticator
+        .join(&server_hash(
+            &request.server_id,
+            &secret,
+            &request.public_key,
+        ))
+        .await?;
+
+    let response = EncryptionResponse {
+        shared_secret: auth::encrypt(&request.public_key, &secret)?,
+        verify_token: auth::encrypt(&request.public_key, &request.verify_token)?,
+    };
+
+    Ok((response, secret))
+}
+
+fn closed() -> Error {
+    Error::new(
+        ErrorKind::UnexpectedEof,
+        "server closed the connection before the login finished",
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use protocol::packets::login::LoginPluginRequest;
+    use tokio::io::DuplexStream;
+
+    use super::*;
+    use crate::{
+        auth::{OfflineAuthenticator, ServerKey},
+        login,
+        testing::{duplex, MockAuthenticator, MockSessionVerifier},
+    };
+
+    fn pair() -> (
+        Connection<DuplexStream, ()>,
+        Connection<DuplexStream, LoginStart>,
+    ) {
+        duplex(ProtocolVersion::new(47))
+    }
+
+    async fn skip_handshake(server: &mut Connection<DuplexStream, LoginStart>) -> io::Result<()> {
+        server.recv_as::<Handshake>().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_login_offline() {
+        let (mut client, mut server) = pair();
+
+        let peer = tokio::spawn(async move {
+            skip_handshake(&mut server).await?;
+            server
+                .send(LoginPluginRequest {
+                    message_id: 3,
+                    channel: "test:channel".into(),
+                    data: vec![1].into(),
+                })
+                .await?;
+
+            let username = login::recv_login_start(&mut server).await?;
+            let LoginPluginResponse { message_id, data } = server.recv_as().await?.unwrap();
+            assert_eq!((message_id, data), (3, None));
+
+            login::finish_login(&mut server, GameProfile::offline(username), Some(16)).await
+        });
+
+        let profile = client_login(
+            &mut client,
+            "localhost",
+            25565,
+            "Notch",
+            &OfflineAuthenticator,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(profile.id, peer.await.unwrap().unwrap().id);
+        assert_eq!(profile.name, "Notch");
+    }
+
+    #[tokio::test]
+    async fn test_client_login_online() {
+        let key = Arc::new(ServerKey::generate().unwrap());
+        let verifier = Arc::new(MockSessionVerifier::new());
+        let (mut client, mut server) = pair();
+
+        let peer = {
+            let (key, verifier) = (key.clone(), verifier.clone());
+            tokio::spawn(async move {
+                skip_handshake(&mut server).await?;
+                login::online_login(&mut server, &key, &*verifier, Some(64)).await
+            })
+        };
+
+        let account = GameProfile::new(uuid::Uuid::from_u128(7), "Notch");
+        let authenticator = MockAuthenticator::new(verifier, account.clone());
+        let profile = client_login(&mut client, "localhost", 25565, "Notch", &authenticator)
+            .await
+            .unwrap();
+
+        assert_eq!(profile.id, account.id);
+        peer.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_login_disconnected() {
+        let (mut client, mut server) = pair();
+
+        tokio::spawn(async move {
+            skip_handshake(&mut server).await?;
+            server
+                .send(Disconnect {
+                    reason: ChatComponent::new("Server is full"),
+                })
+                .await
+        });
+
+        let err = client_login(
+            &mut client,
+            "localhost",
+            25565,
+            "Notch",
+            &OfflineAuthenticator,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+        let disconnected = Disconnected::from_io(&err).unwrap();
+        assert_eq!(disconnected.reason.text.as_deref(), Some("Server is full"));
+    }
+}
@@ -0,0 +1,718 @@
+use std::{io, net::SocketAddr};
+
+use bytes::{Bytes, BytesMut};
+use codec::Codec;
+use futures_util::SinkExt;
+use protocol::{
+    misc::prelude::ChatComponent,
+    packets::{login, play::client_bound},
+    DecodeContext, PacketDecoder, PacketEncoder, PacketSizer, ProtocolState,
+    ProtocolSupportDecoder, ProtocolSupportEncoder, ProtocolVersion,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf},
+    net::{TcpStream, ToSocketAddrs},
+    sync::mpsc,
+    task::JoinHandle,
+};
+use tokio_util::codec::{Framed, FramedParts, FramedRead, FramedWrite};
+
+const READ_CHUNK_LEN: usize = 8 * 1024;
+
+/// A framed connection to a peer, owning its transport and codec state.
+///
+/// `D` is the packet type received from the peer, it is usually changed
+/// alongside the protocol state with [`Connection::adapt`].
+pub struct Connection<T, D> {
+    framed: Framed<T, Codec<D>>,
+    state: ProtocolState,
+    remote_addr: Option<SocketAddr>,
+}
+
+impl<D> Connection<TcpStream, D> {
+    /// Connects to a server, starting at the handshake state.
+    pub async fn connect<A, V>(addr: A, version: V) -> io::Result<Self>
+    where
+        A: ToSocketAddrs,
+        V: Into<ProtocolVersion>,
+    {
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self::new(stream, version))
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.framed.get_ref().peer_addr()
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.framed.get_ref().local_addr()
+    }
+}
+
+impl<T, D> Connection<T, D> {
+    pub fn new<V: Into<ProtocolVersion>>(transport: T, version: V) -> Self
+    where
+        T: AsyncRead + AsyncWrite,
+    {
+        Self::with_codec(transport, Codec::from(version))
+    }
+
+    pub fn with_codec(transport: T, codec: Codec<D>) -> Self
+    where
+        T: AsyncRead + AsyncWrite,
+    {
+        Self {
+            framed: Framed::new(transport, codec),
+            state: ProtocolState::Handshake,
+            remote_addr: None,
+        }
+    }
+
+    /// Get a reference to the connection's version.
+    pub fn version(&self) -> &ProtocolVersion {
+        self.framed.codec().version()
+    }
+
+    pub fn state(&self) -> ProtocolState {
+        self.state
+    }
+
+    /// Changes the protocol state without changing the packet type, see
+    /// [`Connection::adapt`] to change both.
+    pub fn set_state(&mut self, state: ProtocolState) {
+        self.state = state;
+    }
+
+    /// The address of the client as reported by a proxy in front of the
+    /// server, `None` when nothing reported it.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    pub fn set_remote_addr(&mut self, addr: Option<SocketAddr>) {
+        self.remote_addr = addr;
+    }
+
+    pub fn codec(&self) -> &Codec<D> {
+        self.framed.codec()
+    }
+
+    pub fn codec_mut(&mut self) -> &mut Codec<D> {
+        self.framed.codec_mut()
+    }
+
+    pub fn get_ref(&self) -> &T {
+        self.framed.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.framed.get_mut()
+    }
+
+    /// Bytes read from the transport but not decoded yet.
+    #[cfg(all(
+        any(feature = "legacy", feature = "proxy-protocol"),
+        feature = "server"
+    ))]
+    pub(crate) fn read_buffer_mut(&mut self) -> &mut BytesMut {
+        self.framed.read_buffer_mut()
+    }
+
+    /// Enables zlib compression for both directions.
+    #[cfg(feature = "compression")]
+    pub fn enable_compression(&mut self, threshold: i32) {
+        self.framed.codec_mut().enable_compression(threshold);
+    }
+
+    /// Enables aes-cfb8 encryption for both directions.
+    #[cfg(feature = "encryption")]
+    pub fn enable_encryption(&mut self, secret: &[u8; 16]) {
+        self.framed.codec_mut().enable_encryption(secret);
+    }
+
+    /// Moves the connection to a new protocol state, changing the type of
+    /// packets received from the peer.
+    pub fn adapt<N>(self, state: ProtocolState) -> Connection<T, N>
+    where
+        T: AsyncRead + AsyncWrite,
+    {
+        let parts = self.framed.into_parts();
+
+        let mut new_parts = FramedParts::new::<RawPacket>(parts.io, parts.codec.adapt());
+        new_parts.read_buf = parts.read_buf;
+        new_parts.write_buf = parts.write_buf;
+
+        Connection {
+            framed: Framed::from_parts(new_parts),
+            state,
+            remote_addr: self.remote_addr,
+        }
+    }
+
+    /// Splits the connection into halves that can be driven by different tasks.
+    ///
+    /// Codec settings are copied into both halves, changing them afterwards
+    /// only affects the half they were changed on.
+    pub fn split(self) -> (ConnectionReader<T, D>, ConnectionWriter<T>)
+    where
+        T: AsyncRead + AsyncWrite,
+        D: PacketDecoder,
+    {
+        let parts = self.framed.into_parts();
+        let (read_half, write_half) = tokio::io::split(parts.io);
+        let (decoder, encoder) = parts.codec.split();
+
+        let mut reader = FramedRead::new(read_half, decoder);
+        *reader.read_buffer_mut() = parts.read_buf;
+
+        let mut writer = FramedWrite::new(write_half, encoder.adapt());
+        *writer.write_buffer_mut() = parts.write_buf;
+
+        (
+            ConnectionReader {
+                framed: reader,
+                state: self.state,
+            },
+            ConnectionWriter {
+                framed: writer,
+                state: self.state,
+            },
+        )
+    }
+}
+
+impl<T, D> Connection<T, D>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Buffers a packet without flushing the transport.
+    pub async fn feed<P: PacketEncoder>(&mut self, packet: P) -> io::Result<()> {
+        self.framed.feed(packet).await
+    }
+
+    /// Writes every buffered packet to the transport.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        SinkExt::<RawPacket>::flush(&mut self.framed).await
+    }
+
+    /// Writes a packet and flushes the transport.
+    pub async fn send<P: PacketEncoder>(&mut self, packet: P) -> io::Result<()> {
+        self.framed.send(packet).await
+    }
+
+    /// Receives the next packet, `None` meaning the peer closed the connection.
+    pub async fn recv(&mut self) -> io::Result<Option<D>>
+    where
+        D: PacketDecoder,
+    {
+        // not `Framed::next`, which waits for the transport before decoding
+        // frames `recv_as` left in the buffer
+        self.recv_as().await
+    }
+
+    /// Receives the next packet as `P`, regardless of the connection's
+    /// packet type. `None` means the peer closed the connection.
+    pub async fn recv_as<P: PacketDecoder>(&mut self) -> io::Result<Option<P>> {
+        loop {
+            let mut buf = std::mem::take(self.framed.read_buffer_mut());
+            let packet = self.framed.codec_mut().decode_packet(&mut buf);
+            *self.framed.read_buffer_mut() = buf;
+
+            if let Some(packet) = packet? {
+                return Ok(Some(packet));
+            }
+
+            // read into a fresh buffer so nothing buffered is lost if this
+            // future gets dropped while waiting for the peer
+            let mut chunk = BytesMut::with_capacity(READ_CHUNK_LEN);
+            if self.framed.get_mut().read_buf(&mut chunk).await? == 0 {
+                return Ok(None);
+            }
+            self.framed.read_buffer_mut().unsplit(chunk);
+        }
+    }
+
+    /// Sends the disconnect packet for the current state, if there is one,
+    /// and then shuts the transport down.
+    pub async fn disconnect(mut self, reason: ChatComponent<'static>) -> io::Result<()> {
+        send_disconnect(&mut self.framed, self.state, reason).await?;
+        self.shutdown().await
+    }
+
+    /// Flushes pending packets and shuts the transport down.
+    pub async fn shutdown(mut self) -> io::Result<()> {
+        self.flush().await?;
+        self.framed.get_mut().shutdown().await
+    }
+}
+
+/// The receiving half of a [`Connection`].
+pub struct ConnectionReader<T, D> {
+    framed: FramedRead<ReadHalf<T>, Codec<D>>,
+    state: ProtocolState,
+}
+
+impl<T, D> ConnectionReader<T, D>
+where
+    T: AsyncRead,
+{
+    pub fn state(&self) -> ProtocolState {
+        self.state
+    }
+
+    pub fn codec(&self) -> &Codec<D> {
+        self.framed.decoder()
+    }
+
+    pub fn codec_mut(&mut self) -> &mut Codec<D> {
+        self.framed.decoder_mut()
+    }
+
+    /// Receives the next packet, `None` meaning the peer closed the connection.
+    pub async fn recv(&mut self) -> io::Result<Option<D>>
+    where
+        D: PacketDecoder,
+    {
+        // not `FramedRead::next`, a new one waits for the transport before
+        // decoding frames handed over by `split` or `adapt`
+        self.recv_as().await
+    }
+
+    /// Receives the next packet as `P`, regardless of the reader's packet
+    /// type. `None` means the peer closed the connection.
+    pub async fn recv_as<P: PacketDecoder>(&mut self) -> io::Result<Option<P>> {
+        loop {
+            let mut buf = std::mem::take(self.framed.read_buffer_mut());
+            let packet = self.framed.decoder_mut().decode_packet(&mut buf);
+            *self.framed.read_buffer_mut() = buf;
+
+            if let Some(packet) = packet? {
+                return Ok(Some(packet));
+            }
+
+            let mut chunk = BytesMut::with_capacity(READ_CHUNK_LEN);
+            if self.framed.get_mut().read_buf(&mut chunk).await? == 0 {
+                return Ok(None);
+            }
+            self.framed.read_buffer_mut().unsplit(chunk);
+        }
+    }
+
+    /// Moves the reader to a new protocol state, keeping whatever was
+    /// already buffered.
+    pub fn adapt<N>(mut self, state: ProtocolState) -> ConnectionReader<T, N>
+    where
+        N: PacketDecoder,
+    {
+        let read_buf = std::mem::take(self.framed.read_buffer_mut());
+        let version = *self.framed.decoder().version();
+        let codec = std::mem::replace(self.framed.decoder_mut(), Codec::from(version));
+
+        let mut framed = FramedRead::new(self.framed.into_inner(), codec.adapt());
+        *framed.read_buffer_mut() = read_buf;
+
+        ConnectionReader { framed, state }
+    }
+}
+
+/// The sending half of a [`Connection`].
+pub struct ConnectionWriter<T> {
+    framed: FramedWrite<WriteHalf<T>, Codec<()>>,
+    state: ProtocolState,
+}
+
+impl<T> ConnectionWriter<T>
+where
+    T: AsyncWrite,
+{
+    pub fn state(&self) -> ProtocolState {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: ProtocolState) {
+        self.state = state;
+    }
+
+    pub fn codec(&self) -> &Codec<()> {
+        self.framed.encoder()
+    }
+
+    pub fn codec_mut(&mut self) -> &mut Codec<()> {
+        self.framed.encoder_mut()
+    }
+
+    /// Buffers a packet without flushing the transport.
+    pub async fn feed<P: PacketEncoder>(&mut self, packet: P) -> io::Result<()> {
+        self.framed.feed(packet).await
+    }
+
+    /// Writes every buffered packet to the transport.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        SinkExt::<RawPacket>::flush(&mut self.framed).await
+    }
+
+    /// Writes a packet and flushes the transport.
+    pub async fn send<P: PacketEncoder>(&mut self, packet: P) -> io::Result<()> {
+        self.framed.send(packet).await
+    }
+
+    /// Sends the disconnect packet for the current state, if there is one,
+    /// and then shuts the transport down.
+    pub async fn disconnect(mut self, reason: ChatComponent<'static>) -> io::Result<()> {
+        send_disconnect(&mut self.framed, self.state, reason).await?;
+        self.shutdown().await
+    }
+
+    /// Flushes pending packets and shuts the transport down.
+    pub async fn shutdown(mut self) -> io::Result<()> {
+        self.flush().await?;
+        self.framed.get_mut().shutdown().await
+    }
+
+    /// Moves the writer to a task draining a bounded queue of packets.
+    ///
+    /// Packets are written as they arrive and the transport is flushed once
+    /// the queue runs empty. The task ends when every [`Outbound`] handle is
+    /// dropped, handing the writer back.
+    pub fn spawn_queue(mut self, capacity: usize) -> (Outbound, JoinHandle<io::Result<Self>>)
+    where
+        T: Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::channel::<RawPacket>(capacity);
+        let outbound = Outbound {
+            tx,
+            version: *self.framed.encoder().version(),
+        };
+
+        let handle = tokio::spawn(async move {
+            while let Some(packet) = rx.recv().await {
+                self.feed(packet).await?;
+
+                while let Ok(packet) = rx.try_recv() {
+                    self.feed(packet).await?;
+                }
+
+                self.flush().await?;
+            }
+
+            Ok(self)
+        });
+
+        (outbound, handle)
+    }
+}
+
+/// A handle pushing packets to a writer spawned with
+/// [`ConnectionWriter::spawn_queue`].
+///
+/// Packets are encoded when pushed, so the queue never borrows from them.
+#[derive(Clone)]
+pub struct Outbound {
+    tx: mpsc::Sender<RawPacket>,
+    version: ProtocolVersion,
+}
+
+impl Outbound {
+    /// Queues a packet, waiting for room if the queue is full.
+    pub async fn send<P: PacketEncoder>(&self, packet: &P) -> io::Result<()> {
+        let packet = RawPacket::encode(packet, &self.version)?;
+        self.tx
+            .send(packet)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "connection writer is gone"))
+    }
+
+    /// Queues a packet, failing with [`io::ErrorKind::WouldBlock`] if the
+    /// queue is full.
+    pub fn try_send<P: PacketEncoder>(&self, packet: &P) -> io::Result<()> {
+        let packet = RawPacket::encode(packet, &self.version)?;
+        self.tx.try_send(packet).map_err(|err| match err {
+            mpsc::error::TrySendError::Full(_) => {
+                io::Error::new(io::ErrorKind::WouldBlock, "outbound queue is full")
+            }
+            mpsc::error::TrySendError::Closed(_) => {
+                io::Error::new(io::ErrorKind::BrokenPipe, "connection writer is gone")
+            }
+        })
+    }
+
+    /// Whether the writer task is still running.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+/// A packet already encoded to its id and body, or received as such
+/// whatever its id.
+#[derive(Clone, Debug)]
+pub struct RawPacket(Bytes);
+
+impl RawPacket {
+    pub fn encode<P: PacketEncoder>(packet: &P, version: &ProtocolVersion) -> io::Result<Self> {
+        let mut buf = vec![0; PacketEncoder::calculate_len(packet, version)];
+        PacketEncoder::encode(packet, &mut &mut buf[..], version)?;
+
+        Ok(Self(buf.into()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+impl ProtocolSupportEncoder for RawPacket {
+    fn calculate_len(&self, _: &ProtocolVersion) -> usize {
+        self.0.len()
+    }
+
+    fn encode<W: io::Write>(&self, dst: &mut W, _: &ProtocolVersion) -> io::Result<()> {
+        dst.write_all(&self.0[..])
+    }
+}
+
+impl PacketEncoder for RawPacket {
+    fn calculate_len(&self, _: &ProtocolVersion) -> usize {
+        self.0.len()
+    }
+
+    fn encode<W: io::Write>(&self, dst: &mut W, _: &ProtocolVersion) -> io::Result<()> {
+        dst.write_all(&self.0[..])
+    }
+}
+
+impl ProtocolSupportDecoder for RawPacket {
+    fn decode<R: io::Read>(src: &mut DecodeContext<R>, _: &ProtocolVersion) -> io::Result<Self> {
+        let mut buf = vec![0; src.remaining()];
+        io::Read::read_exact(src, &mut buf)?;
+
+        Ok(Self(buf.into()))
+    }
+}
+
+impl PacketDecoder for RawPacket {
+    fn decode<R: io::Read>(
+        src: &mut DecodeContext<R>,
+        version: &ProtocolVersion,
+    ) -> io::Result<Self> {
+        ProtocolSupportDecoder::decode(src, version)
+    }
+}
+
+impl PacketSizer for RawPacket {}
+
+async fn send_disconnect<S>(
+    sink: &mut S,
+    state: ProtocolState,
+    reason: ChatComponent<'static>,
+) -> io::Result<()>
+where
+    S: futures_util::Sink<login::Disconnect, Error = io::Error>
+        + futures_util::Sink<client_bound::Disconnect<'static>, Error = io::Error>
+        + Unpin,
+{
+    match state {
+        ProtocolState::Login => sink.send(login::Disconnect { reason }).await,
+        ProtocolState::Play => sink.send(client_bound::Disconnect { reason }).await,
+        ProtocolState::Handshake | ProtocolState::Status => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use protocol::packets::{
+        handshake::{Handshake, NextState},
+        play::{client_bound::ClientBound, server_bound},
+    };
+
+    use super::*;
+
+    fn pair<D, N>() -> (
+        Connection<tokio::io::DuplexStream, D>,
+        Connection<tokio::io::DuplexStream, N>,
+    ) {
+        let (client, server) = tokio::io::duplex(4096);
+        let version = ProtocolVersion::new(47);
+        (
+            Connection::new(client, version),
+            Connection::new(server, version),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_connection_roundtrip() {
+        let (mut client, mut server) = pair::<(), Handshake>();
+
+        client
+            .send(Handshake {
+                protocol_version: 47,
+                server_address: "localhost".into(),
+                server_port: 25565,
+                next_state: NextState::Login,
+            })
+            .await
+            .unwrap();
+
+        let packet = server.recv().await.unwrap();
+        assert!(matches!(
+            packet,
+            Some(Handshake {
+                next_state: NextState::Login,
+                ..
+            })
+        ));
+
+        client.shutdown().await.unwrap();
+        assert!(server.recv().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recv_after_recv_as() {
+        use protocol::packets::status::Ping;
+
+        let (mut client, mut server) = pair::<(), Ping>();
+
+        client
+            .feed(Handshake {
+                protocol_version: 47,
+                server_address: "localhost".into(),
+                server_port: 25565,
+                next_state: NextState::Status,
+            })
+            .await
+            .unwrap();
+        client.send(Ping { payload: 1 }).await.unwrap();
+
+        server.recv_as::<Handshake>().await.unwrap().unwrap();
+
+        // the ping was read along with the handshake, nothing else is coming
+        let ping = tokio::time::timeout(std::time::Duration::from_secs(1), server.recv())
+            .await
+            .expect("buffered packet was not decoded");
+        assert!(matches!(ping.unwrap(), Some(Ping { payload: 1 })));
+    }
+
+    #[tokio::test]
+    async fn test_split_and_adapt_with_buffered_frames() {
+        use protocol::packets::status::{Ping, Request};
+
+        let (mut client, mut server) = pair::<(), Handshake>();
+
+        client
+            .feed(Handshake {
+                protocol_version: 47,
+                server_address: "localhost".into(),
+                server_port: 25565,
+                next_state: NextState::Status,
+            })
+            .await
+            .unwrap();
+        client.feed(Request).await.unwrap();
+        client.send(Ping { payload: 1 }).await.unwrap();
+
+        // everything is read along with the handshake, nothing else is coming
+        server.recv().await.unwrap().unwrap();
+        let (reader, _writer) = server.split();
+        let mut reader = reader.adapt::<Request>(ProtocolState::Status);
+
+        let timeout = std::time::Duration::from_secs(1);
+        let request = tokio::time::timeout(timeout, reader.recv())
+            .await
+            .expect("buffered packet was not decoded after split");
+        assert!(request.unwrap().is_some());
+
+        let mut reader = reader.adapt::<Ping>(ProtocolState::Status);
+        let ping = tokio::time::timeout(timeout, reader.recv())
+            .await
+            .expect("buffered packet was not decoded after adapt");
+        assert!(matches!(ping.unwrap(), Some(Ping { payload: 1 })));
+    }
+
+    #[tokio::test]
+    async fn test_connection_compression_and_encryption() {
+        let (client, server) = pair::<(), ()>();
+        let mut client = client.adapt::<ClientBound>(ProtocolState::Play);
+        let mut server = server.adapt::<server_bound::ServerBound>(ProtocolState::Play);
+
+        let secret = [7; 16];
+        client.enable_compression(16);
+        client.enable_encryption(&secret);
+        server.enable_compression(16);
+        server.enable_encryption(&secret);
+
+        for keep_alive_id in 0..3 {
+            server
+                .send(client_bound::KeepAlive { keep_alive_id })
+                .await
+                .unwrap();
+            client
+                .send(server_bound::KeepAlive { keep_alive_id })
+                .await
+                .unwrap();
+        }
+
+        for id in 0..3 {
+            assert!(matches!(
+                client.recv().await.unwrap(),
+                Some(ClientBound::KeepAlive(client_bound::KeepAlive { keep_alive_id })) if keep_alive_id == id
+            ));
+            assert!(matches!(
+                server.recv().await.unwrap(),
+                Some(server_bound::ServerBound::KeepAlive(server_bound::KeepAlive { keep_alive_id })) if keep_alive_id == id
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_outbound_queue() {
+        let (client, server) = pair::<(), ()>();
+        let mut client = client.adapt::<ClientBound>(ProtocolState::Play);
+        let server = server.adapt::<server_bound::ServerBound>(ProtocolState::Play);
+
+        let (_reader, writer) = server.split();
+        let (outbound, handle) = writer.spawn_queue(2);
+
+        let sender = tokio::spawn(async move {
+            for keep_alive_id in 0..8 {
+                outbound
+                    .send(&client_bound::KeepAlive { keep_alive_id })
+                    .await
+                    .unwrap();
+            }
+        });
+
+        for id in 0..8 {
+            assert!(matches!(
+                client.recv().await.unwrap(),
+                Some(ClientBound::KeepAlive(client_bound::KeepAlive { keep_alive_id })) if keep_alive_id == id
+            ));
+        }
+
+        sender.await.unwrap();
+        let writer = handle.await.unwrap().unwrap();
+        writer.disconnect(ChatComponent::new("bye")).await.unwrap();
+
+        assert!(matches!(
+            client.recv().await.unwrap(),
+            Some(ClientBound::Disconnect(client_bound::Disconnect { reason })) if reason.text.as_deref() == Some("bye")
+        ));
+        assert!(client.recv().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_outbound_closed() {
+        let (_client, server) = pair::<(), Handshake>();
+        let (_reader, writer) = server.split();
+
+        let (outbound, handle) = writer.spawn_queue(1);
+        handle.abort();
+        let _ = handle.await;
+
+        let err = outbound
+            .try_send(&client_bound::KeepAlive { keep_alive_id: 0 })
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert!(outbound.is_closed());
+    }
+}
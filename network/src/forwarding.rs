@@ -0,0 +1,520 @@
+//! Player info forwarded by a proxy in front of the server.
+//!
+//! BungeeCord appends it to [`Handshake::server_address`], Velocity sends it
+//! signed in answer to a login plugin request.
+
+use std::{
+    io::{self, Error, ErrorKind},
+    net::IpAddr,
+    sync::{Arc, Mutex},
+};
+
+use hmac::{Hmac, Mac};
+use protocol::{
+    misc::prelude::{GameProfile, Property},
+    packets::{
+        handshake::{Handshake, NextState},
+        login::LoginStart,
+    },
+    DecodeContext, PacketDecoder, PacketSizer, ProtocolSupportDecoder, ProtocolSupportEncoder,
+    ProtocolVersion, VarNum,
+};
+use sha2::Sha256;
+use tokio::io::{AsyncRead, AsyncWrite};
+use uuid::Uuid;
+
+use crate::{
+    connection::Connection,
+    login::{self, LoginPluginDispatcher},
+};
+
+/// Channel of Velocity's modern forwarding.
+pub const VELOCITY_CHANNEL: &str = "velocity:player_info";
+/// The modern forwarding version without chat signing keys.
+pub const VELOCITY_FORWARDING_VERSION: i32 = 1;
+
+const SIGNATURE_LEN: usize = 32;
+/// The most a string can hold, BungeeCord's forwarded info does not fit in
+/// the 255 characters of a vanilla handshake.
+const FORWARDED_ADDRESS_MAX_LEN: usize = 32767;
+
+/// Who the proxy says is connecting.
+#[derive(Clone, Debug)]
+pub struct ForwardedPlayer {
+    /// The address of the player's client, not the proxy's.
+    pub address: IpAddr,
+    pub profile: GameProfile,
+}
+
+/// What BungeeCord forwards in the handshake, as
+/// `host\0address\0uuid\0properties`.
+///
+/// The player's name is not part of it, it comes with `LoginStart`.
+#[derive(Clone, Debug)]
+pub struct BungeeCordForwarding {
+    /// The host the player connected to.
+    pub host: String,
+    pub address: IpAddr,
+    pub id: Uuid,
+    pub properties: Vec<Property>,
+}
+
+impl BungeeCordForwarding {
+    pub fn parse(server_address: &str) -> io::Result<Self> {
+        let mut fields = server_address.splitn(4, '\0');
+
+        let (host, address, id) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(host), Some(address), Some(id)) => (host, address, id),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::PermissionDenied,
+                    "handshake was not forwarded by BungeeCord",
+                ))
+            }
+        };
+
+        let properties = match fields.next() {
+            Some(properties) => serde_json::from_str(properties)
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            host: host.into(),
+            address: address
+                .parse()
+                .map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+            id: Uuid::parse_str(id).map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+            properties,
+        })
+    }
+
+    pub fn from_handshake(handshake: &Handshake) -> io::Result<Self> {
+        Self::parse(&handshake.server_address)
+    }
+
+    /// Formats the forwarded info the way BungeeCord sends it.
+    pub fn to_server_address(&self) -> io::Result<String> {
+        let properties = serde_json::to_string(&self.properties)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+
+        Ok(format!(
+            "{}\0{}\0{}\0{}",
+            self.host,
+            self.address,
+            self.id.to_simple(),
+            properties
+        ))
+    }
+
+    /// Replaces the handshake's server address with the forwarded info.
+    pub fn apply(&self, handshake: &mut Handshake) -> io::Result<()> {
+        handshake.server_address = self.to_server_address()?;
+        Ok(())
+    }
+
+    pub fn into_player<N: Into<String>>(self, username: N) -> ForwardedPlayer {
+        ForwardedPlayer {
+            address: self.address,
+            profile: GameProfile {
+                id: self.id,
+                name: username.into(),
+                properties: self.properties,
+            },
+        }
+    }
+}
+
+/// A [`Handshake`] read with room for BungeeCord's forwarded info.
+///
+/// Only meant for servers behind BungeeCord, every other handshake keeps
+/// the vanilla limit.
+#[derive(Debug)]
+pub struct BungeeCordHandshake(pub Handshake);
+
+impl ProtocolSupportDecoder for BungeeCordHandshake {
+    fn decode<R: io::Read>(
+        src: &mut DecodeContext<R>,
+        version: &ProtocolVersion,
+    ) -> io::Result<Self> {
+        Ok(Self(Handshake {
+            protocol_version: VarNum::<i32>::decode(src)?,
+            server_address: <String as protocol::RangeValidatedSupport>::decode(
+                src,
+                version,
+                0,
+                FORWARDED_ADDRESS_MAX_LEN,
+            )?,
+            server_port: u16::decode(src, version)?,
+            next_state: NextState::decode(src, version)?,
+        }))
+    }
+}
+
+impl PacketDecoder for BungeeCordHandshake {
+    fn decode<R: io::Read>(
+        src: &mut DecodeContext<R>,
+        version: &ProtocolVersion,
+    ) -> io::Result<Self> {
+        let id = VarNum::<i32>::decode(src)?;
+        if id != 0x00 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("expected id 0, got {}", id),
+            ));
+        }
+
+        ProtocolSupportDecoder::decode(src, version)
+    }
+}
+
+impl PacketSizer for BungeeCordHandshake {}
+
+/// Signs and checks Velocity's modern forwarding with the secret shared
+/// with the proxy.
+#[derive(Clone)]
+pub struct VelocityForwarding {
+    secret: Vec<u8>,
+}
+
+impl VelocityForwarding {
+    pub fn new<S: Into<Vec<u8>>>(secret: S) -> Self {
+        Self {
+            secret: secret.into(),
+        }
+    }
+
+    /// Builds the answer to a forwarding request, the HMAC-SHA256 signature
+    /// followed by the player info.
+    pub fn sign(&self, player: &ForwardedPlayer) -> io::Result<Vec<u8>> {
+        let version = ProtocolVersion::new(0);
+        let profile = &player.profile;
+
+        let mut payload = Vec::new();
+        VarNum::<i32>::encode(&VELOCITY_FORWARDING_VERSION, &mut payload)?;
+        player.address.to_string().encode(&mut payload, &version)?;
+        profile.id.encode(&mut payload, &version)?;
+        profile.name.encode(&mut payload, &version)?;
+        profile.properties.encode(&mut payload, &version)?;
+
+        let mut data = self
+            .mac()
+            .chain_update(&payload)
+            .finalize()
+            .into_bytes()
+            .to_vec();
+        data.extend_from_slice(&payload);
+
+        Ok(data)
+    }
+
+    /// Checks the signature of a forwarding answer and reads the player info
+    /// out of it.
+    pub fn verify(&self, data: &[u8]) -> io::Result<ForwardedPlayer> {
+        if data.len() < SIGNATURE_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "forwarding data is too short",
+            ));
+        }
+
+        let (signature, payload) = data.split_at(SIGNATURE_LEN);
+        self.mac()
+            .chain_update(payload)
+            .verify_slice(signature)
+            .map_err(|_| Error::new(ErrorKind::PermissionDenied, "invalid forwarding signature"))?;
+
+        let version = ProtocolVersion::new(0);
+        let src = &mut DecodeContext::from(payload);
+
+        let forwarding_version = VarNum::<i32>::decode(src)?;
+        if forwarding_version != VELOCITY_FORWARDING_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported forwarding version {}", forwarding_version),
+            ));
+        }
+
+        let address = String::decode(src, &version)?
+            .parse()
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        let profile = GameProfile {
+            id: Uuid::decode(src, &version)?,
+            name: String::decode(src, &version)?,
+            properties: Vec::decode(src, &version)?,
+        };
+
+        Ok(ForwardedPlayer { address, profile })
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::new_from_slice(&self.secret).expect("hmac accepts keys of any len")
+    }
+}
+
+/// Logs in a player forwarded by BungeeCord, without authenticating them
+/// since the proxy already did.
+pub async fn bungeecord_login<T>(
+    conn: &mut Connection<T, LoginStart>,
+    handshake: &Handshake,
+    compression_threshold: Option<i32>,
+) -> io::Result<ForwardedPlayer>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let forwarding = match BungeeCordForwarding::from_handshake(handshake) {
+        Ok(forwarding) => forwarding,
+        Err(err) => {
+            login::disconnect(conn, "Please connect through the proxy").await?;
+            return Err(err);
+        }
+    };
+
+    let username = login::recv_login_start(conn).await?;
+    let player = forwarding.into_player(username);
+    login::finish_login(conn, player.profile.clone(), compression_threshold).await?;
+
+    Ok(player)
+}
+
+/// Logs in a player forwarded by Velocity, asking the proxy for the player
+/// info and checking its signature.
+pub async fn velocity_login<T>(
+    conn: &mut Connection<T, LoginStart>,
+    forwarding: &VelocityForwarding,
+    compression_threshold: Option<i32>,
+) -> io::Result<ForwardedPlayer>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    login::recv_login_start(conn).await?;
+
+    let answer = Arc::new(Mutex::new(None));
+    let mut dispatcher = LoginPluginDispatcher::default();
+    {
+        let answer = answer.clone();
+        dispatcher.handler(VELOCITY_CHANNEL, move |data| {
+            *answer.lock().unwrap() = data;
+            Ok(())
+        });
+    }
+    dispatcher.request(VELOCITY_CHANNEL, vec![VELOCITY_FORWARDING_VERSION as u8])?;
+    dispatcher.run(conn).await?;
+
+    let data = answer.lock().unwrap().take();
+    let player = match data.map(|data| forwarding.verify(&data)) {
+        Some(Ok(player)) => player,
+        Some(Err(err)) => {
+            login::disconnect(conn, "Unable to verify player details").await?;
+            return Err(err);
+        }
+        None => {
+            login::disconnect(conn, "This server requires you to connect with Velocity").await?;
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "client was not forwarded by Velocity",
+            ));
+        }
+    };
+
+    login::finish_login(conn, player.profile.clone(), compression_threshold).await?;
+
+    Ok(player)
+}
+
+#[cfg(test)]
+mod test {
+    use protocol::packets::{
+        handshake::NextState,
+        login::{Disconnect, LoginPluginRequest, LoginPluginResponse, LoginSuccess},
+    };
+
+    use super::*;
+    use crate::testing::duplex;
+
+    fn player() -> ForwardedPlayer {
+        ForwardedPlayer {
+            address: "203.0.113.7".parse().unwrap(),
+            profile: GameProfile {
+                id: Uuid::parse_str("069a79f444e94726a5befca90e38aaf5").unwrap(),
+                name: "Notch".into(),
+                properties: vec![Property {
+                    name: "textures".into(),
+                    value: "e30=".into(),
+                    signature: Some("c2ln".into()),
+                }],
+            },
+        }
+    }
+
+    fn handshake(server_address: String) -> Handshake {
+        Handshake {
+            protocol_version: 47,
+            server_address,
+            server_port: 25565,
+            next_state: NextState::Login,
+        }
+    }
+
+    #[test]
+    fn test_bungeecord_roundtrip() {
+        let player = player();
+        let forwarding = BungeeCordForwarding {
+            host: "play.example.com".into(),
+            address: player.address,
+            id: player.profile.id,
+            properties: player.profile.properties,
+        };
+
+        let mut handshake = handshake("play.example.com".into());
+        forwarding.apply(&mut handshake).unwrap();
+        assert!(handshake
+            .server_address
+            .starts_with("play.example.com\0203.0.113.7\0069a79f444e94726a5befca90e38aaf5\0[{"));
+
+        let parsed = BungeeCordForwarding::from_handshake(&handshake).unwrap();
+        assert_eq!(parsed.host, "play.example.com");
+        assert_eq!(parsed.id, forwarding.id);
+        assert_eq!(parsed.properties[0].signature.as_deref(), Some("c2ln"));
+
+        let parsed =
+            BungeeCordForwarding::parse("host\0::1\0069a79f444e94726a5befca90e38aaf5").unwrap();
+        assert!(parsed.properties.is_empty());
+
+        let err = BungeeCordForwarding::parse("play.example.com").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_bungeecord_login() {
+        let (mut client, mut server) = duplex::<(), Handshake>(ProtocolVersion::new(47));
+
+        let mut forwarded = player();
+        forwarded.profile.properties[0].value = "e30=".repeat(200);
+        let forwarding = BungeeCordForwarding {
+            host: "localhost".into(),
+            address: forwarded.address,
+            id: forwarded.profile.id,
+            properties: forwarded.profile.properties,
+        };
+
+        let mut sent = handshake("localhost".into());
+        forwarding.apply(&mut sent).unwrap();
+        assert!(sent.server_address.len() > 255);
+
+        client.feed(sent).await.unwrap();
+        client
+            .send(LoginStart {
+                username: "Notch".into(),
+            })
+            .await
+            .unwrap();
+
+        let received = server
+            .recv_as::<BungeeCordHandshake>()
+            .await
+            .unwrap()
+            .unwrap()
+            .0;
+        let mut server = server.adapt::<LoginStart>(protocol::ProtocolState::Login);
+        let player = bungeecord_login(&mut server, &received, None)
+            .await
+            .unwrap();
+
+        assert_eq!(player.profile.name, "Notch");
+        assert_eq!(player.profile.properties[0].value.len(), 800);
+        let success = client.recv_as::<LoginSuccess>().await.unwrap().unwrap();
+        assert_eq!(success.uuid, forwarding.id);
+    }
+
+    #[test]
+    fn test_velocity_signature() {
+        let forwarding = VelocityForwarding::new("secret");
+        let mut data = forwarding.sign(&player()).unwrap();
+
+        let verified = forwarding.verify(&data).unwrap();
+        assert_eq!(verified.address, player().address);
+        assert_eq!(verified.profile.name, "Notch");
+        assert_eq!(verified.profile.properties[0].value, "e30=");
+
+        let err = VelocityForwarding::new("other").verify(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+
+        *data.last_mut().unwrap() ^= 1;
+        let err = forwarding.verify(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_velocity_login() {
+        let forwarding = VelocityForwarding::new("secret");
+        let (mut client, mut server) = duplex::<(), LoginStart>(ProtocolVersion::new(393));
+
+        let peer = {
+            let forwarding = forwarding.clone();
+            tokio::spawn(async move { velocity_login(&mut server, &forwarding, None).await })
+        };
+
+        client
+            .send(LoginStart {
+                username: "Notch".into(),
+            })
+            .await
+            .unwrap();
+
+        let request = client
+            .recv_as::<LoginPluginRequest>()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.channel, VELOCITY_CHANNEL);
+        client
+            .send(LoginPluginResponse {
+                message_id: request.message_id,
+                data: Some(forwarding.sign(&player()).unwrap().into()),
+            })
+            .await
+            .unwrap();
+
+        let success = client.recv_as::<LoginSuccess>().await.unwrap().unwrap();
+        assert_eq!(success.uuid, player().profile.id);
+
+        let forwarded = peer.await.unwrap().unwrap();
+        assert_eq!(forwarded.address, player().address);
+    }
+
+    #[tokio::test]
+    async fn test_velocity_login_without_proxy() {
+        let (mut client, mut server) = duplex::<(), LoginStart>(ProtocolVersion::new(393));
+
+        let peer = tokio::spawn(async move {
+            velocity_login(&mut server, &VelocityForwarding::new("secret"), None).await
+        });
+
+        client
+            .send(LoginStart {
+                username: "Notch".into(),
+            })
+            .await
+            .unwrap();
+
+        let request = client
+            .recv_as::<LoginPluginRequest>()
+            .await
+            .unwrap()
+            .unwrap();
+        client
+            .send(LoginPluginResponse {
+                message_id: request.message_id,
+                data: None,
+            })
+            .await
+            .unwrap();
+
+        client.recv_as::<Disconnect>().await.unwrap().unwrap();
+        assert_eq!(
+            peer.await.unwrap().unwrap_err().kind(),
+            ErrorKind::PermissionDenied
+        );
+    }
+}
@@ -0,0 +1,382 @@
+//! The server list ping used before 1.7, which is not VarInt framed.
+//!
+//! Clients start it with `0xFE`. Since 1.4 a `0x01` follows, and since 1.6 an
+//! `MC|PingHost` plugin message carrying the protocol version, host and port.
+//! Servers answer with a `0xFF` kick packet holding the status as UTF-16BE.
+
+use std::{
+    io::{self, Error, ErrorKind},
+    time::Duration,
+};
+
+use protocol::misc::prelude::StatusResponse;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::ping::PingOptions;
+
+/// First byte of a legacy ping, and of the VarInt length of any frame of
+/// 254, 382, 510... bytes; see [`is_legacy_ping`] to tell them apart.
+pub const LEGACY_PING_ID: u8 = 0xFE;
+const LEGACY_KICK_ID: u8 = 0xFF;
+const PING_HOST_CHANNEL: &str = "MC|PingHost";
+/// The last protocol version speaking the legacy ping, 1.6.4.
+const LEGACY_PROTOCOL_VERSION: u8 = 78;
+
+/// What the client sent along with its legacy ping, only filled in by 1.6
+/// clients.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LegacyPing {
+    pub protocol_version: Option<u8>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LegacyStatus {
+    /// `-1` when answered by a server older than 1.4, which leaves it out.
+    pub protocol: i32,
+    pub version: String,
+    pub motd: String,
+    pub online: i32,
+    pub max: i32,
+}
+
+impl LegacyStatus {
+    /// Formats the status as `§1\0protocol\0version\0motd\0online\0max`.
+    pub fn to_kick_message(&self) -> String {
+        format!(
+            "§1\0{}\0{}\0{}\0{}\0{}",
+            self.protocol, self.version, self.motd, self.online, self.max
+        )
+    }
+
+    /// Parses the kick message of a legacy ping response, in either the
+    /// `§1` format or the `motd§online§max` one of servers older than 1.4.
+    pub fn from_kick_message(message: &str) -> io::Result<Self> {
+        if let Some(fields) = message.strip_prefix("§1\0") {
+            let fields: Vec<_> = fields.split('\0').collect();
+            if let [protocol, version, motd, online, max] = fields[..] {
+                return Ok(Self {
+                    protocol: parse_int(protocol)?,
+                    version: version.into(),
+                    motd: motd.into(),
+                    online: parse_int(online)?,
+                    max: parse_int(max)?,
+                });
+            }
+        } else {
+            let mut fields = message.rsplitn(3, '§');
+            if let (Some(max), Some(online), Some(motd)) =
+                (fields.next(), fields.next(), fields.next())
+            {
+                return Ok(Self {
+                    protocol: -1,
+                    version: String::new(),
+                    motd: motd.into(),
+                    online: parse_int(online)?,
+                    max: parse_int(max)?,
+                });
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::InvalidData,
+            "malformed legacy ping response",
+        ))
+    }
+}
+
+impl From<&StatusResponse> for LegacyStatus {
+    fn from(status: &StatusResponse) -> Self {
+        Self {
+            protocol: status.version.protocol,
+            version: status.version.name.clone(),
+            motd: status.description.to_legacy_string(),
+            online: status.players.online,
+            max: status.players.max,
+        }
+    }
+}
+
+/// Whether the connection starting with `prefix` is a legacy ping rather
+/// than a frame whose length starts with `0xFE` too.
+///
+/// Like vanilla, `0xFE` is a legacy ping when the client sends nothing more
+/// within `wait`, or follows it with `0x01` and then nothing or `0xFA`. The
+/// bytes read to find out are added to `prefix`, to be handed back to the
+/// connection otherwise.
+pub async fn is_legacy_ping<R>(
+    src: &mut R,
+    prefix: &mut Vec<u8>,
+    wait: Duration,
+) -> io::Result<bool>
+where
+    R: AsyncRead + Unpin,
+{
+    if prefix.first() != Some(&LEGACY_PING_ID) {
+        return Ok(false);
+    }
+
+    for (i, expected) in [0x01, 0xFA].iter().enumerate() {
+        let byte = match prefix.get(i + 1) {
+            Some(byte) => *byte,
+            None => match tokio::time::timeout(wait, src.read_u8()).await {
+                Ok(Ok(byte)) => {
+                    prefix.push(byte);
+                    byte
+                }
+                Ok(Err(err)) if err.kind() == ErrorKind::UnexpectedEof => return Ok(true),
+                Ok(Err(err)) => return Err(err),
+                Err(_) => return Ok(true),
+            },
+        };
+
+        if byte != *expected {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Reads the rest of a legacy ping, the leading `0xFE` already consumed.
+///
+/// Clients older than 1.6 send nothing after it, so reading gives up once
+/// the client has been quiet for `wait`.
+pub async fn read_legacy_ping<R>(src: &mut R, wait: Duration) -> io::Result<LegacyPing>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut ping = LegacyPing::default();
+
+    for expected in [0x01, 0xFA].iter() {
+        match tokio::time::timeout(wait, src.read_u8()).await {
+            Ok(Ok(byte)) if byte == *expected => {}
+            Ok(Ok(byte)) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unexpected legacy ping byte {:#04x}", byte),
+                ))
+            }
+            Ok(Err(err)) if err.kind() == ErrorKind::UnexpectedEof => return Ok(ping),
+            Ok(Err(err)) => return Err(err),
+            Err(_) => return Ok(ping),
+        }
+    }
+
+    let channel = read_utf16(src).await?;
+    if channel != PING_HOST_CHANNEL {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("unexpected legacy ping channel {}", channel),
+        ));
+    }
+
+    let _len = src.read_u16().await?;
+    ping.protocol_version = Some(src.read_u8().await?);
+    ping.host = Some(read_utf16(src).await?);
+    ping.port = Some(src.read_i32().await? as u16);
+
+    Ok(ping)
+}
+
+/// Writes the `0xFF` kick packet answering a legacy ping.
+pub async fn write_legacy_status<W>(dst: &mut W, status: &LegacyStatus) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![LEGACY_KICK_ID];
+    write_utf16(&mut buf, &status.to_kick_message())?;
+
+    dst.write_all(&buf).await?;
+    dst.flush().await
+}
+
+/// Runs a 1.6 style legacy ping against `host:port`.
+pub async fn legacy_ping(host: &str, port: u16, options: &PingOptions) -> io::Result<LegacyStatus> {
+    let mut stream =
+        tokio::time::timeout(options.connect_timeout, TcpStream::connect((host, port)))
+            .await
+            .map_err(|_| Error::new(ErrorKind::TimedOut, "legacy ping timed out"))??;
+
+    tokio::time::timeout(options.timeout, legacy_ping_stream(&mut stream, host, port))
+        .await
+        .map_err(|_| Error::new(ErrorKind::TimedOut, "legacy ping timed out"))?
+}
+
+/// Runs a 1.6 style legacy ping over an already open stream.
+pub async fn legacy_ping_stream<S>(
+    stream: &mut S,
+    host: &str,
+    port: u16,
+) -> io::Result<LegacyStatus>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut payload = vec![LEGACY_PROTOCOL_VERSION];
+    write_utf16(&mut payload, host)?;
+    payload.extend_from_slice(&(port as i32).to_be_bytes());
+
+    let mut buf = vec![LEGACY_PING_ID, 0x01, 0xFA];
+    write_utf16(&mut buf, PING_HOST_CHANNEL)?;
+    buf.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&payload);
+
+    stream.write_all(&buf).await?;
+    stream.flush().await?;
+
+    match stream.read_u8().await? {
+        LEGACY_KICK_ID => LegacyStatus::from_kick_message(&read_utf16(stream).await?),
+        id => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("expected legacy kick packet, got {:#04x}", id),
+        )),
+    }
+}
+
+async fn read_utf16<R>(src: &mut R) -> io::Result<String>
+where
+    R: AsyncRead + Unpin,
+{
+    let len = src.read_u16().await? as usize;
+
+    let mut buf = vec![0; len * 2];
+    src.read_exact(&mut buf).await?;
+
+    let units: Vec<_> = buf
+        .chunks_exact(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .collect();
+    String::from_utf16(&units).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
+
+fn write_utf16(dst: &mut Vec<u8>, value: &str) -> io::Result<()> {
+    let units: Vec<_> = value.encode_utf16().collect();
+    if units.len() > u16::MAX as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "legacy string is too long",
+        ));
+    }
+
+    dst.extend_from_slice(&(units.len() as u16).to_be_bytes());
+    for unit in units {
+        dst.extend_from_slice(&unit.to_be_bytes());
+    }
+
+    Ok(())
+}
+
+fn parse_int(value: &str) -> io::Result<i32> {
+    value
+        .parse()
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn status() -> LegacyStatus {
+        LegacyStatus {
+            protocol: 47,
+            version: "1.8.9".into(),
+            motd: "§aA server".into(),
+            online: 3,
+            max: 20,
+        }
+    }
+
+    #[test]
+    fn test_kick_message_roundtrip() {
+        let message = status().to_kick_message();
+        assert_eq!(message, "§1\u{0}47\u{0}1.8.9\u{0}§aA server\u{0}3\u{0}20");
+        assert_eq!(LegacyStatus::from_kick_message(&message).unwrap(), status());
+    }
+
+    #[test]
+    fn test_beta_kick_message() {
+        let status = LegacyStatus::from_kick_message("A § server§3§20").unwrap();
+        assert_eq!(status.motd, "A § server");
+        assert_eq!(status.protocol, -1);
+        assert_eq!((status.online, status.max), (3, 20));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_ping_roundtrip() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+
+        let peer = tokio::spawn(async move {
+            assert_eq!(server.read_u8().await?, LEGACY_PING_ID);
+            let ping = read_legacy_ping(&mut server, Duration::from_secs(1)).await?;
+            write_legacy_status(&mut server, &status()).await?;
+
+            Ok::<_, io::Error>(ping)
+        });
+
+        let received = legacy_ping_stream(&mut client, "localhost", 25565)
+            .await
+            .unwrap();
+        assert_eq!(received, status());
+
+        let ping = peer.await.unwrap().unwrap();
+        assert_eq!(
+            ping,
+            LegacyPing {
+                protocol_version: Some(LEGACY_PROTOCOL_VERSION),
+                host: Some("localhost".into()),
+                port: Some(25565),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_is_legacy_ping() {
+        let wait = Duration::from_millis(20);
+        let detect = |bytes: &'static [u8]| async move {
+            let (mut client, mut server) = tokio::io::duplex(1024);
+            client.write_all(&bytes[1..]).await?;
+
+            // the first byte as the server reads it
+            let mut prefix = vec![bytes[0]];
+            let legacy = is_legacy_ping(&mut server, &mut prefix, wait).await?;
+            Ok::<_, io::Error>((legacy, prefix))
+        };
+
+        // pings from before 1.4, before 1.6 and from 1.6
+        assert_eq!(detect(&[0xFE]).await.unwrap(), (true, vec![0xFE]));
+        assert_eq!(
+            detect(&[0xFE, 0x01]).await.unwrap(),
+            (true, vec![0xFE, 0x01])
+        );
+        assert_eq!(
+            detect(&[0xFE, 0x01, 0xFA, 0x00]).await.unwrap(),
+            (true, vec![0xFE, 0x01, 0xFA])
+        );
+
+        // frames of 254 and 382 bytes, and one of 127 bytes
+        assert_eq!(
+            detect(&[0xFE, 0x01, 0x00, 0x2F]).await.unwrap(),
+            (false, vec![0xFE, 0x01, 0x00])
+        );
+        assert_eq!(
+            detect(&[0xFE, 0x02, 0x00]).await.unwrap(),
+            (false, vec![0xFE, 0x02])
+        );
+        assert_eq!(detect(&[0x7F, 0x00]).await.unwrap(), (false, vec![0x7F]));
+    }
+
+    #[tokio::test]
+    async fn test_read_pre_1_6_ping() {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client.write_all(&[0x01]).await.unwrap();
+
+        let ping = read_legacy_ping(&mut server, Duration::from_millis(20))
+            .await
+            .unwrap();
+        assert_eq!(ping, LegacyPing::default());
+    }
+}
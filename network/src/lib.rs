@@ -1,5 +1,38 @@
 #[cfg(feature = "aio")]
 pub mod aio;
 
+#[cfg(feature = "auth")]
+pub mod auth;
+
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(feature = "connection")]
+pub mod connection;
+
+#[cfg(feature = "forwarding")]
+pub mod forwarding;
+
+#[cfg(feature = "legacy")]
+pub mod legacy;
+
+#[cfg(feature = "login")]
+pub mod login;
+
+#[cfg(feature = "ping")]
+pub mod ping;
+
+#[cfg(feature = "proxy-protocol")]
+pub mod proxy_protocol;
+
+#[cfg(feature = "server")]
+pub mod server;
+
+#[cfg(feature = "sync")]
+pub mod sync;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
 #[cfg(feature = "codec")]
 pub extern crate codec;
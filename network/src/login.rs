@@ -0,0 +1,484 @@
+//! Server side drivers for the login state, run right after the handshake.
+
+#[cfg(feature = "auth")]
+use std::convert::TryInto;
+use std::{
+    collections::HashMap,
+    io::{self, Error, ErrorKind},
+    time::Duration,
+};
+
+#[cfg(feature = "auth")]
+use protocol::packets::login::{EncryptionRequest, EncryptionResponse};
+use protocol::{
+    misc::prelude::{ChatComponent, GameProfile},
+    packets::login::{
+        Disconnect, LoginPluginRequest, LoginPluginResponse, LoginStart, LoginSuccess,
+        SetCompression,
+    },
+    RemainingBytes,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    time::{timeout_at, Instant},
+};
+
+#[cfg(feature = "auth")]
+use crate::auth::{server_hash, ServerKey, SessionVerifier};
+use crate::connection::Connection;
+
+/// Logs a player in without authenticating them, the way offline-mode
+/// servers do.
+///
+/// The username is checked against the regex of [`LoginStart`], the player
+/// being disconnected when it does not match. With a `compression_threshold`,
+/// `SetCompression` is sent and the connection compresses from then on.
+pub async fn offline_login<T>(
+    conn: &mut Connection<T, LoginStart>,
+    compression_threshold: Option<i32>,
+) -> io::Result<GameProfile>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let username = recv_login_start(conn).await?;
+    finish_login(conn, GameProfile::offline(username), compression_threshold).await
+}
+
+/// Logs a player in the way online-mode servers do.
+///
+/// After [`LoginStart`], the connection is encrypted with the secret the
+/// client sends back for `key`, and `verifier` checks that the player joined
+/// through the session server. The profile it returns is the one logged in.
+#[cfg(feature = "auth")]
+pub async fn online_login<T, V>(
+    conn: &mut Connection<T, LoginStart>,
+    key: &ServerKey,
+    verifier: &V,
+    compression_threshold: Option<i32>,
+) -> io::Result<GameProfile>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+    V: SessionVerifier,
+{
+    let username = recv_login_start(conn).await?;
+
+    let verify_token = rand::random::<[u8; 4]>();
+    conn.send(EncryptionRequest {
+        server_id: String::new(),
+        public_key: key.public_key_der().to_vec(),
+        verify_token: verify_token.to_vec(),
+    })
+    .await?;
+
+    let response = conn
+        .recv_as::<EncryptionResponse>()
+        .await?
+        .ok_or_else(closed)?;
+
+    let secret = match decrypt_response(key, &response, &verify_token) {
+        Ok(secret) => secret,
+        Err(err) => {
+            disconnect(conn, "Invalid encryption response").await?;
+            return Err(err);
+        }
+    };
+    conn.enable_encryption(&secret);
+
+    let hash = server_hash("", &secret, key.public_key_der());
+    match verifier.has_joined(&username, &hash).await? {
+        Some(profile) => finish_login(conn, profile, compression_threshold).await,
+        None => {
+            disconnect(conn, "Failed to verify username!").await?;
+            Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("{} has not joined through the session server", username),
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "auth")]
+fn decrypt_response(
+    key: &ServerKey,
+    response: &EncryptionResponse,
+    verify_token: &[u8],
+) -> io::Result<[u8; 16]> {
+    if key.decrypt(&response.verify_token)? != verify_token {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "verify token does not match",
+        ));
+    }
+
+    let secret = key.decrypt(&response.shared_secret)?;
+    secret
+        .as_slice()
+        .try_into()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "shared secret is not 16 bytes"))
+}
+
+/// Receives `LoginStart`, disconnecting the client when its username does
+/// not match the regex of [`LoginStart`].
+pub async fn recv_login_start<T>(conn: &mut Connection<T, LoginStart>) -> io::Result<String>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    match conn.recv().await {
+        Ok(Some(LoginStart { username })) => Ok(username),
+        Ok(None) => Err(closed()),
+        Err(err) if err.kind() == ErrorKind::InvalidData => {
+            disconnect(conn, "Invalid username").await?;
+            Err(err)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Turns compression on if asked to and sends `LoginSuccess`, ending the
+/// login state.
+pub async fn finish_login<T>(
+    conn: &mut Connection<T, LoginStart>,
+    profile: GameProfile,
+    compression_threshold: Option<i32>,
+) -> io::Result<GameProfile>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    if let Some(threshold) = compression_threshold {
+        conn.send(SetCompression { threshold }).await?;
+        conn.enable_compression(threshold);
+    }
+
+    conn.send(LoginSuccess {
+        uuid: profile.id,
+        username: profile.name.clone(),
+    })
+    .await?;
+
+    Ok(profile)
+}
+
+type PluginHandler = Box<dyn FnMut(Option<Vec<u8>>) -> io::Result<()> + Send>;
+
+/// Sends `LoginPluginRequest`s and hands each answer to the handler of its
+/// channel.
+///
+/// Run it between `LoginStart` and [`finish_login`]. Requests the client has
+/// not answered when the timeout runs out are handled as if it did not
+/// understand their channel, with `None`.
+pub struct LoginPluginDispatcher {
+    handlers: HashMap<String, PluginHandler>,
+    queued: Vec<LoginPluginRequest>,
+    next_message_id: i32,
+    timeout: Duration,
+}
+
+impl LoginPluginDispatcher {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            handlers: HashMap::new(),
+            queued: Vec::new(),
+            next_message_id: 0,
+            timeout,
+        }
+    }
+
+    /// Sets what handles the answers to requests on `channel`. An error
+    /// returned by the handler stops [`run`](Self::run).
+    pub fn handler<C, F>(&mut self, channel: C, handler: F) -> &mut Self
+    where
+        C: Into<String>,
+        F: FnMut(Option<Vec<u8>>) -> io::Result<()> + Send + 'static,
+    {
+        self.handlers.insert(channel.into(), Box::new(handler));
+        self
+    }
+
+    /// Queues a request on `channel`, which needs a handler, returning its
+    /// message id.
+    pub fn request<D: Into<Vec<u8>>>(&mut self, channel: &str, data: D) -> io::Result<i32> {
+        if !self.handlers.contains_key(channel) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("no handler for login plugin channel {}", channel),
+            ));
+        }
+
+        let message_id = self.next_message_id;
+        self.next_message_id += 1;
+        self.queued.push(LoginPluginRequest {
+            message_id,
+            channel: channel.into(),
+            data: RemainingBytes(data.into()),
+        });
+
+        Ok(message_id)
+    }
+
+    /// Sends the queued requests and waits for their answers.
+    pub async fn run<T>(&mut self, conn: &mut Connection<T, LoginStart>) -> io::Result<()>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut pending = HashMap::new();
+        for request in self.queued.drain(..) {
+            pending.insert(request.message_id, request.channel.clone());
+            conn.feed(request).await?;
+        }
+        conn.flush().await?;
+
+        let deadline = Instant::now() + self.timeout;
+        while !pending.is_empty() {
+            let response = match timeout_at(deadline, conn.recv_as::<LoginPluginResponse>()).await {
+                Ok(response) => response?.ok_or_else(closed)?,
+                Err(_) => break,
+            };
+
+            let channel = pending.remove(&response.message_id).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unexpected login plugin message id {}", response.message_id),
+                )
+            })?;
+            self.dispatch(&channel, response.data.map(RemainingBytes::into_inner))?;
+        }
+
+        for channel in pending.into_values() {
+            self.dispatch(&channel, None)?;
+        }
+
+        Ok(())
+    }
+
+    fn dispatch(&mut self, channel: &str, data: Option<Vec<u8>>) -> io::Result<()> {
+        match self.handlers.get_mut(channel) {
+            Some(handler) => handler(data),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for LoginPluginDispatcher {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_TIMEOUT)
+    }
+}
+
+pub(crate) async fn disconnect<T>(
+    conn: &mut Connection<T, LoginStart>,
+    reason: &'static str,
+) -> io::Result<()>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    conn.send(Disconnect {
+        reason: ChatComponent::new(reason),
+    })
+    .await
+}
+
+fn closed() -> Error {
+    Error::new(
+        ErrorKind::UnexpectedEof,
+        "client closed the connection before logging in",
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use protocol::ProtocolVersion;
+
+    use super::*;
+    use crate::testing::duplex;
+
+    #[tokio::test]
+    async fn test_offline_login() {
+        let (mut client, mut server) = duplex::<(), LoginStart>(ProtocolVersion::new(47));
+
+        let peer = tokio::spawn(async move { offline_login(&mut server, Some(64)).await });
+
+        client
+            .send(LoginStart {
+                username: "Notch".into(),
+            })
+            .await
+            .unwrap();
+
+        let SetCompression { threshold } = client.recv_as().await.unwrap().unwrap();
+        assert_eq!(threshold, 64);
+        client.enable_compression(threshold);
+
+        let success = client.recv_as::<LoginSuccess>().await.unwrap().unwrap();
+        assert_eq!(success.username, "Notch");
+        assert_eq!(
+            success.uuid.to_string(),
+            "b50ad385-829d-3141-a216-7e7d7539ba7f"
+        );
+
+        let profile = peer.await.unwrap().unwrap();
+        assert_eq!(profile.id, success.uuid);
+        assert_eq!(profile.name, "Notch");
+    }
+
+    #[tokio::test]
+    async fn test_offline_login_invalid_username() {
+        let (mut client, mut server) = duplex::<(), LoginStart>(ProtocolVersion::new(47));
+
+        let peer = tokio::spawn(async move { offline_login(&mut server, None).await });
+
+        client
+            .send(LoginStart {
+                username: "not a name".into(),
+            })
+            .await
+            .unwrap();
+
+        let Disconnect { reason } = client.recv_as().await.unwrap().unwrap();
+        assert_eq!(reason.text.as_deref(), Some("Invalid username"));
+        assert_eq!(
+            peer.await.unwrap().unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[cfg(feature = "auth")]
+    async fn online_client(
+        client: &mut Connection<tokio::io::DuplexStream, ()>,
+        verifier: &crate::testing::MockSessionVerifier,
+        joined_hash: Option<&str>,
+    ) -> io::Result<()> {
+        client
+            .send(LoginStart {
+                username: "Notch".into(),
+            })
+            .await?;
+
+        let request = client.recv_as::<EncryptionRequest>().await?.unwrap();
+        let secret = [5; 16];
+        let hash = server_hash(&request.server_id, &secret, &request.public_key);
+        verifier.join(
+            GameProfile::new(uuid::Uuid::from_u128(1), "Notch"),
+            joined_hash.unwrap_or(&hash),
+        );
+
+        client
+            .send(EncryptionResponse {
+                shared_secret: crate::auth::encrypt(&request.public_key, &secret)?,
+                verify_token: crate::auth::encrypt(&request.public_key, &request.verify_token)?,
+            })
+            .await?;
+        client.enable_encryption(&secret);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "auth")]
+    #[tokio::test]
+    async fn test_online_login() {
+        use std::sync::Arc;
+
+        use crate::testing::MockSessionVerifier;
+
+        let key = Arc::new(ServerKey::generate().unwrap());
+        let verifier = Arc::new(MockSessionVerifier::new());
+        let (mut client, mut server) = duplex::<(), LoginStart>(ProtocolVersion::new(47));
+
+        let peer = {
+            let (key, verifier) = (key.clone(), verifier.clone());
+            tokio::spawn(async move { online_login(&mut server, &key, &*verifier, None).await })
+        };
+
+        online_client(&mut client, &verifier, None).await.unwrap();
+
+        let success = client.recv_as::<LoginSuccess>().await.unwrap().unwrap();
+        assert_eq!(success.uuid, uuid::Uuid::from_u128(1));
+
+        let profile = peer.await.unwrap().unwrap();
+        assert_eq!(profile.name, "Notch");
+    }
+
+    #[cfg(feature = "auth")]
+    #[tokio::test]
+    async fn test_online_login_not_joined() {
+        use std::sync::Arc;
+
+        use crate::testing::MockSessionVerifier;
+
+        let key = Arc::new(ServerKey::generate().unwrap());
+        let verifier = Arc::new(MockSessionVerifier::new());
+        let (mut client, mut server) = duplex::<(), LoginStart>(ProtocolVersion::new(47));
+
+        let peer = {
+            let (key, verifier) = (key.clone(), verifier.clone());
+            tokio::spawn(async move { online_login(&mut server, &key, &*verifier, None).await })
+        };
+
+        online_client(&mut client, &verifier, Some("-1"))
+            .await
+            .unwrap();
+
+        let Disconnect { reason } = client.recv_as().await.unwrap().unwrap();
+        assert_eq!(reason.text.as_deref(), Some("Failed to verify username!"));
+        assert_eq!(
+            peer.await.unwrap().unwrap_err().kind(),
+            ErrorKind::PermissionDenied
+        );
+    }
+
+    #[tokio::test]
+    async fn test_login_plugin_dispatcher() {
+        use std::sync::{Arc, Mutex};
+
+        let (mut client, mut server) =
+            duplex::<LoginPluginRequest, LoginStart>(ProtocolVersion::new(47));
+
+        let answers = Arc::new(Mutex::new(Vec::new()));
+        let mut dispatcher = LoginPluginDispatcher::new(Duration::from_millis(50));
+        for channel in ["test:answered", "test:ignored"].iter() {
+            let answers = answers.clone();
+            dispatcher.handler(*channel, move |data| {
+                answers.lock().unwrap().push((channel.to_string(), data));
+                Ok(())
+            });
+        }
+
+        let answered = dispatcher.request("test:answered", vec![1]).unwrap();
+        dispatcher.request("test:ignored", vec![2]).unwrap();
+        assert_eq!(
+            dispatcher
+                .request("test:unknown", vec![])
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+
+        let peer = tokio::spawn(async move {
+            dispatcher.run(&mut server).await?;
+            Ok::<_, io::Error>(server)
+        });
+
+        let first = client.recv().await.unwrap().unwrap();
+        let second = client.recv().await.unwrap().unwrap();
+        assert_eq!(first.message_id, answered);
+        assert_eq!((&*first.data, &*second.data), (&vec![1], &vec![2]));
+
+        client
+            .send(LoginPluginResponse {
+                message_id: answered,
+                data: Some(RemainingBytes(vec![3])),
+            })
+            .await
+            .unwrap();
+
+        peer.await.unwrap().unwrap();
+        assert_eq!(
+            *answers.lock().unwrap(),
+            vec![
+                ("test:answered".to_string(), Some(vec![3])),
+                ("test:ignored".to_string(), None),
+            ]
+        );
+    }
+}
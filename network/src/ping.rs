@@ -0,0 +1,130 @@
+use std::{
+    io,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use protocol::{
+    misc::prelude::StatusResponse,
+    packets::{
+        handshake::{Handshake, NextState},
+        status::{Ping, Pong, Request, Response},
+    },
+    ProtocolVersion, ProtocolVersionEnum,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+
+use crate::connection::Connection;
+
+#[derive(Clone, Debug)]
+pub struct PingOptions {
+    /// Sent in the handshake and used to encode packets.
+    pub version: ProtocolVersion,
+    pub connect_timeout: Duration,
+    /// How long the whole exchange may take once connected.
+    pub timeout: Duration,
+}
+
+impl Default for PingOptions {
+    fn default() -> Self {
+        Self {
+            version: ProtocolVersionEnum::V1_8.into(),
+            connect_timeout: Duration::from_secs(5),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PingResult {
+    pub status: StatusResponse,
+    /// Time between sending `Ping` and receiving the matching `Pong`.
+    pub latency: Duration,
+}
+
+impl PingResult {
+    /// The protocol version the server reported running.
+    pub fn version(&self) -> ProtocolVersion {
+        ProtocolVersion::new(self.status.version.protocol)
+    }
+}
+
+/// Runs a server list ping against `host:port`.
+pub async fn ping(host: &str, port: u16, options: &PingOptions) -> io::Result<PingResult> {
+    let stream = timeout(options.connect_timeout, TcpStream::connect((host, port))).await?;
+    stream.set_nodelay(true)?;
+
+    let mut conn = Connection::new(stream, options.version);
+    timeout(options.timeout, ping_connection(&mut conn, host, port)).await
+}
+
+/// Pings the server to find out which protocol version it runs.
+pub async fn detect_version(
+    host: &str,
+    port: u16,
+    options: &PingOptions,
+) -> io::Result<ProtocolVersion> {
+    ping(host, port, options)
+        .await
+        .map(|result| result.version())
+}
+
+/// Runs a server list ping over an already open connection, still in the
+/// handshake state. `server_address` and `server_port` are only sent in the
+/// handshake.
+pub async fn ping_connection<T>(
+    conn: &mut Connection<T, ()>,
+    server_address: &str,
+    server_port: u16,
+) -> io::Result<PingResult>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    conn.send(Handshake {
+        protocol_version: **conn.version(),
+        server_address: server_address.into(),
+        server_port,
+        next_state: NextState::Status,
+    })
+    .await?;
+    conn.send(Request).await?;
+
+    let Response { status } = conn.recv_as().await?.ok_or_else(closed)?;
+
+    let payload = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as i64)
+        .unwrap_or_default();
+
+    let start = Instant::now();
+    conn.send(Ping { payload }).await?;
+    let pong = conn.recv_as::<Pong>().await?.ok_or_else(closed)?;
+    let latency = start.elapsed();
+
+    if pong.payload != payload {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "pong payload {} does not match ping payload {}",
+                pong.payload, payload
+            ),
+        ));
+    }
+
+    Ok(PingResult { status, latency })
+}
+
+async fn timeout<F, T>(duration: Duration, future: F) -> io::Result<T>
+where
+    F: std::future::Future<Output = io::Result<T>>,
+{
+    tokio::time::timeout(duration, future)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "server ping timed out"))?
+}
+
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "server closed the connection")
+}
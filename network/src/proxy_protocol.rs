@@ -0,0 +1,497 @@
+//! The PROXY protocol header load balancers send before the client's bytes,
+//! telling the client's real address.
+//!
+//! Both the text v1 and binary v2 formats are read, see
+//! <https://www.haproxy.org/download/2.8/doc/proxy-protocol.txt>.
+
+use std::{
+    io::{self, Error, ErrorKind},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::FromStr,
+};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// Longest v1 header, crlf included.
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+pub const PP2_TYPE_ALPN: u8 = 0x01;
+pub const PP2_TYPE_AUTHORITY: u8 = 0x02;
+pub const PP2_TYPE_CRC32C: u8 = 0x03;
+pub const PP2_TYPE_NOOP: u8 = 0x04;
+pub const PP2_TYPE_UNIQUE_ID: u8 = 0x05;
+pub const PP2_TYPE_SSL: u8 = 0x20;
+pub const PP2_TYPE_NETNS: u8 = 0x30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProxyCommand {
+    /// The connection was opened by the proxy itself, a health check for
+    /// example, the addresses are the proxy's own.
+    Local,
+    Proxy,
+}
+
+/// A type-length-value field of a v2 header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyTlv {
+    pub kind: u8,
+    pub value: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyHeader {
+    /// `1` or `2`.
+    pub version: u8,
+    pub command: ProxyCommand,
+    /// The client's address, `None` when the proxy did not know it or it is
+    /// not an ip address.
+    pub source: Option<SocketAddr>,
+    pub destination: Option<SocketAddr>,
+    /// Always empty for v1 headers.
+    pub tlvs: Vec<ProxyTlv>,
+}
+
+impl ProxyHeader {
+    /// A header for a proxied tcp connection.
+    pub fn new(version: u8, source: SocketAddr, destination: SocketAddr) -> Self {
+        Self {
+            version,
+            command: ProxyCommand::Proxy,
+            source: Some(source),
+            destination: Some(destination),
+            tlvs: Vec::new(),
+        }
+    }
+
+    /// The first TLV of `kind`.
+    pub fn tlv(&self, kind: u8) -> Option<&[u8]> {
+        self.tlvs
+            .iter()
+            .find(|tlv| tlv.kind == kind)
+            .map(|tlv| &tlv.value[..])
+    }
+
+    /// Writes the header in its version's format.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        match self.version {
+            1 => Ok(self.to_v1().into_bytes()),
+            2 => self.to_v2(),
+            version => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown proxy protocol version {}", version),
+            )),
+        }
+    }
+
+    fn to_v1(&self) -> String {
+        match (self.command, self.source, self.destination) {
+            (ProxyCommand::Proxy, Some(src), Some(dst)) if src.is_ipv4() == dst.is_ipv4() => {
+                format!(
+                    "PROXY {} {} {} {} {}\r\n",
+                    if src.is_ipv4() { "TCP4" } else { "TCP6" },
+                    src.ip(),
+                    dst.ip(),
+                    src.port(),
+                    dst.port()
+                )
+            }
+            _ => "PROXY UNKNOWN\r\n".into(),
+        }
+    }
+
+    fn to_v2(&self) -> io::Result<Vec<u8>> {
+        let command = match self.command {
+            ProxyCommand::Local => 0x20,
+            ProxyCommand::Proxy => 0x21,
+        };
+
+        let mut body = Vec::new();
+        let family = match (self.source, self.destination) {
+            (Some(SocketAddr::V4(src)), Some(SocketAddr::V4(dst))) => {
+                body.extend_from_slice(&src.ip().octets());
+                body.extend_from_slice(&dst.ip().octets());
+                body.extend_from_slice(&src.port().to_be_bytes());
+                body.extend_from_slice(&dst.port().to_be_bytes());
+                0x11
+            }
+            (Some(SocketAddr::V6(src)), Some(SocketAddr::V6(dst))) => {
+                body.extend_from_slice(&src.ip().octets());
+                body.extend_from_slice(&dst.ip().octets());
+                body.extend_from_slice(&src.port().to_be_bytes());
+                body.extend_from_slice(&dst.port().to_be_bytes());
+                0x21
+            }
+            _ => 0x00,
+        };
+
+        for tlv in &self.tlvs {
+            if tlv.value.len() > u16::MAX as usize {
+                return Err(Error::new(ErrorKind::InvalidInput, "tlv is too long"));
+            }
+
+            body.push(tlv.kind);
+            body.extend_from_slice(&(tlv.value.len() as u16).to_be_bytes());
+            body.extend_from_slice(&tlv.value);
+        }
+
+        if body.len() > u16::MAX as usize {
+            return Err(Error::new(ErrorKind::InvalidInput, "header is too long"));
+        }
+
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.push(command);
+        buf.push(family);
+        buf.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&body);
+
+        Ok(buf)
+    }
+}
+
+/// Reads a PROXY header if the stream starts with one.
+///
+/// Whatever was read while telling the header apart from the client's own
+/// bytes is returned along with it, and has to be handled as if it was
+/// never read.
+pub async fn read_proxy_header<R>(src: &mut R) -> io::Result<(Option<ProxyHeader>, Vec<u8>)>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut read = Vec::new();
+
+    loop {
+        let v1 = V1_PREFIX.starts_with(&read);
+        let v2 = V2_SIGNATURE.starts_with(&read);
+
+        if read == V1_PREFIX {
+            return Ok((Some(read_v1(src).await?), Vec::new()));
+        }
+        if read == V2_SIGNATURE {
+            return Ok((Some(read_v2(src).await?), Vec::new()));
+        }
+        if !v1 && !v2 {
+            return Ok((None, read));
+        }
+
+        match src.read_u8().await {
+            Ok(byte) => read.push(byte),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok((None, read)),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+async fn read_v1<R>(src: &mut R) -> io::Result<ProxyHeader>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut line = Vec::new();
+    while !line.ends_with(b"\r\n") {
+        if line.len() + V1_PREFIX.len() >= V1_MAX_LEN {
+            return Err(invalid("proxy header is too long"));
+        }
+        line.push(src.read_u8().await?);
+    }
+    line.truncate(line.len() - 2);
+
+    let line = std::str::from_utf8(&line).map_err(|_| invalid("proxy header is not ascii"))?;
+    let fields: Vec<_> = line.split(' ').collect();
+
+    let (source, destination) = match fields[..] {
+        ["UNKNOWN", ..] => (None, None),
+        [protocol @ ("TCP4" | "TCP6"), src, dst, sport, dport] => {
+            let src: IpAddr = parse(src)?;
+            let dst: IpAddr = parse(dst)?;
+            if src.is_ipv4() != (protocol == "TCP4") || dst.is_ipv4() != (protocol == "TCP4") {
+                return Err(invalid("proxy header address does not match its protocol"));
+            }
+
+            (
+                Some(SocketAddr::new(src, parse(sport)?)),
+                Some(SocketAddr::new(dst, parse(dport)?)),
+            )
+        }
+        _ => return Err(invalid("malformed proxy header")),
+    };
+
+    Ok(ProxyHeader {
+        version: 1,
+        command: ProxyCommand::Proxy,
+        source,
+        destination,
+        tlvs: Vec::new(),
+    })
+}
+
+async fn read_v2<R>(src: &mut R) -> io::Result<ProxyHeader>
+where
+    R: AsyncRead + Unpin,
+{
+    let version_command = src.read_u8().await?;
+    let family = src.read_u8().await?;
+    let len = src.read_u16().await? as usize;
+
+    if version_command >> 4 != 2 {
+        return Err(invalid("unknown proxy protocol version"));
+    }
+    let command = match version_command & 0x0F {
+        0x0 => ProxyCommand::Local,
+        0x1 => ProxyCommand::Proxy,
+        _ => return Err(invalid("unknown proxy protocol command")),
+    };
+
+    let mut body = vec![0; len];
+    src.read_exact(&mut body).await?;
+
+    let (addresses_len, source, destination) = match family >> 4 {
+        0x1 if body.len() >= 12 => {
+            let ip = |at: usize| Ipv4Addr::new(body[at], body[at + 1], body[at + 2], body[at + 3]);
+            let (src, dst) = (IpAddr::V4(ip(0)), IpAddr::V4(ip(4)));
+            let (sport, dport) = ports(&body[8..12]);
+            (
+                12,
+                Some(SocketAddr::new(src, sport)),
+                Some(SocketAddr::new(dst, dport)),
+            )
+        }
+        0x2 if body.len() >= 36 => {
+            let ip = |at: usize| {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(&body[at..at + 16]);
+                Ipv6Addr::from(octets)
+            };
+            let (src, dst) = (IpAddr::V6(ip(0)), IpAddr::V6(ip(16)));
+            let (sport, dport) = ports(&body[32..36]);
+            (
+                36,
+                Some(SocketAddr::new(src, sport)),
+                Some(SocketAddr::new(dst, dport)),
+            )
+        }
+        // unix sockets, which have no ip address
+        0x3 if body.len() >= 216 => (216, None, None),
+        0x0 => (0, None, None),
+        _ => return Err(invalid("malformed proxy header addresses")),
+    };
+
+    // LOCAL connections must ignore the addresses
+    let (source, destination) = match command {
+        ProxyCommand::Local => (None, None),
+        ProxyCommand::Proxy => (source, destination),
+    };
+
+    Ok(ProxyHeader {
+        version: 2,
+        command,
+        source,
+        destination,
+        tlvs: read_tlvs(&body[addresses_len..])?,
+    })
+}
+
+fn read_tlvs(mut buf: &[u8]) -> io::Result<Vec<ProxyTlv>> {
+    let mut tlvs = Vec::new();
+
+    while !buf.is_empty() {
+        if buf.len() < 3 {
+            return Err(invalid("truncated proxy header tlv"));
+        }
+
+        let len = u16::from_be_bytes([buf[1], buf[2]]) as usize;
+        if buf.len() < 3 + len {
+            return Err(invalid("truncated proxy header tlv"));
+        }
+
+        tlvs.push(ProxyTlv {
+            kind: buf[0],
+            value: buf[3..3 + len].to_vec(),
+        });
+        buf = &buf[3 + len..];
+    }
+
+    Ok(tlvs)
+}
+
+fn ports(buf: &[u8]) -> (u16, u16) {
+    (
+        u16::from_be_bytes([buf[0], buf[1]]),
+        u16::from_be_bytes([buf[2], buf[3]]),
+    )
+}
+
+fn parse<T: FromStr>(value: &str) -> io::Result<T> {
+    value.parse().map_err(|_| invalid("malformed proxy header"))
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/// The upstream addresses allowed to send a PROXY header.
+///
+/// Anyone else could claim any address by sending one, so headers are only
+/// looked for on connections from these.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts an address, or a whole network in cidr notation like
+    /// `10.0.0.0/8`.
+    pub fn allow(mut self, network: &str) -> io::Result<Self> {
+        let invalid_network = || {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid network {}", network),
+            )
+        };
+
+        let (addr, prefix_len) = match network.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (network, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| invalid_network())?;
+
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.parse().map_err(|_| invalid_network())?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(invalid_network());
+        }
+
+        self.networks.push((addr, prefix_len));
+        Ok(self)
+    }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        let addr = addr.to_canonical();
+
+        self.networks
+            .iter()
+            .any(|(network, prefix_len)| match (network, addr) {
+                (IpAddr::V4(network), IpAddr::V4(addr)) => same_prefix(
+                    u32::from(*network).into(),
+                    u32::from(addr).into(),
+                    32,
+                    *prefix_len,
+                ),
+                (IpAddr::V6(network), IpAddr::V6(addr)) => {
+                    same_prefix(u128::from(*network), u128::from(addr), 128, *prefix_len)
+                }
+                _ => false,
+            })
+    }
+}
+
+fn same_prefix(network: u128, addr: u128, bits: u8, prefix_len: u8) -> bool {
+    let shift = bits - prefix_len;
+    shift == bits || (network >> shift) == (addr >> shift)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn source() -> SocketAddr {
+        "203.0.113.7:51234".parse().unwrap()
+    }
+
+    fn destination() -> SocketAddr {
+        "10.0.0.2:25565".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_read_v1() {
+        let mut src = &b"PROXY TCP4 203.0.113.7 10.0.0.2 51234 25565\r\n\x10\x00"[..];
+        let (header, rest) = read_proxy_header(&mut src).await.unwrap();
+
+        assert_eq!(header, Some(ProxyHeader::new(1, source(), destination())));
+        assert!(rest.is_empty());
+        assert_eq!(src, b"\x10\x00");
+
+        let mut src = &b"PROXY UNKNOWN\r\n"[..];
+        let (header, _) = read_proxy_header(&mut src).await.unwrap();
+        assert_eq!(header.unwrap().source, None);
+
+        let mut src = &b"PROXY TCP6 203.0.113.7 10.0.0.2 1 2\r\n"[..];
+        assert!(read_proxy_header(&mut src).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_v2_roundtrip() {
+        let mut header = ProxyHeader::new(
+            2,
+            "[2001:db8::7]:51234".parse().unwrap(),
+            "[2001:db8::2]:25565".parse().unwrap(),
+        );
+        header.tlvs.push(ProxyTlv {
+            kind: PP2_TYPE_AUTHORITY,
+            value: b"play.example.com".to_vec(),
+        });
+
+        let bytes = header.to_bytes().unwrap();
+        let (read, rest) = read_proxy_header(&mut &bytes[..]).await.unwrap();
+        assert!(rest.is_empty());
+
+        let read = read.unwrap();
+        assert_eq!(read, header);
+        assert_eq!(read.tlv(PP2_TYPE_AUTHORITY), Some(&b"play.example.com"[..]));
+
+        let v4 = ProxyHeader::new(2, source(), destination());
+        let bytes = v4.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 16 + 12);
+        let (read, _) = read_proxy_header(&mut &bytes[..]).await.unwrap();
+        assert_eq!(read, Some(v4));
+    }
+
+    #[tokio::test]
+    async fn test_v2_local() {
+        let mut header = ProxyHeader::new(2, source(), destination());
+        header.command = ProxyCommand::Local;
+
+        let bytes = header.to_bytes().unwrap();
+        let (read, _) = read_proxy_header(&mut &bytes[..]).await.unwrap();
+        assert_eq!(read.unwrap().source, None);
+    }
+
+    #[tokio::test]
+    async fn test_no_header() {
+        // a handshake frame of len 0x50, the ascii of `P`
+        let mut src = &b"\x50\x00\x2f"[..];
+        let (header, rest) = read_proxy_header(&mut src).await.unwrap();
+
+        assert_eq!(header, None);
+        assert_eq!(rest, b"\x50\x00");
+        assert_eq!(src, b"\x2f");
+    }
+
+    #[test]
+    fn test_trusted_proxies() {
+        let trusted = TrustedProxies::new()
+            .allow("10.0.0.0/8")
+            .unwrap()
+            .allow("2001:db8::1")
+            .unwrap();
+
+        assert!(trusted.contains("10.1.2.3".parse().unwrap()));
+        assert!(trusted.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(trusted.contains("2001:db8::1".parse().unwrap()));
+        assert!(!trusted.contains("2001:db8::2".parse().unwrap()));
+        assert!(!trusted.contains("192.168.0.1".parse().unwrap()));
+
+        assert!(TrustedProxies::new()
+            .allow("0.0.0.0/0")
+            .unwrap()
+            .contains("192.168.0.1".parse().unwrap()));
+        assert!(TrustedProxies::new().allow("10.0.0.0/33").is_err());
+    }
+}
@@ -0,0 +1,481 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use protocol::{
+    misc::prelude::{ChatComponent, StatusResponse},
+    packets::{
+        handshake::{Handshake, NextState},
+        login::LoginStart,
+        play::ServerBound,
+        status::{Ping, Pong, Request, Response},
+    },
+    ProtocolState, ProtocolVersion, ProtocolVersionEnum,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{mpsc, watch, OwnedSemaphorePermit, Semaphore},
+};
+
+use crate::connection::Connection;
+#[cfg(feature = "forwarding")]
+use crate::forwarding::BungeeCordHandshake;
+#[cfg(feature = "legacy")]
+use crate::legacy;
+#[cfg(feature = "proxy-protocol")]
+use crate::proxy_protocol::{self, TrustedProxies};
+
+/// How long a legacy ping may go quiet before it is taken as complete.
+#[cfg(feature = "legacy")]
+const LEGACY_PING_WAIT: Duration = Duration::from_millis(100);
+
+/// A connection in the login state, as handed to [`Handler::login`].
+pub type LoginConnection<T = TcpStream> = Connection<T, LoginStart>;
+/// A connection in the play state, as handed to [`Handler::play`].
+pub type PlayConnection<T = TcpStream> = Connection<T, ServerBound>;
+
+/// Any stream a [`Handler`] can be served on, sockets accepted by a
+/// [`Server`] or in-memory streams alike.
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+
+impl<T> Transport for T where T: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+
+/// Callbacks driving every connection accepted by a [`Server`].
+///
+/// The server reads the handshake and routes the connection to `status` or
+/// `login`. Players accepted by `login` are moved to `play` until they leave
+/// or the server shuts down.
+pub trait Handler: Send + Sync + 'static {
+    /// Whatever `login` learned about the player, handed to `play`.
+    type Player: Send;
+
+    /// Builds the server list entry answering a status request.
+    fn status(
+        &self,
+        handshake: &Handshake,
+    ) -> impl Future<Output = io::Result<StatusResponse>> + Send;
+
+    /// Runs the login sequence, sending `LoginSuccess` included.
+    ///
+    /// Returning `None` closes the connection without entering play.
+    fn login<T: Transport>(
+        &self,
+        handshake: &Handshake,
+        conn: &mut LoginConnection<T>,
+    ) -> impl Future<Output = io::Result<Option<Self::Player>>> + Send;
+
+    /// Runs until the player leaves. The connection is closed afterwards.
+    fn play<T: Transport>(
+        &self,
+        player: Self::Player,
+        conn: &mut PlayConnection<T>,
+    ) -> impl Future<Output = io::Result<()>> + Send;
+}
+
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    pub version: ProtocolVersion,
+    /// Connections accepted over this limit are closed right away.
+    pub max_connections: usize,
+    /// Same as `max_connections`, but counted per remote address.
+    pub max_connections_per_ip: Option<usize>,
+    /// How long a new connection has to send its handshake.
+    pub handshake_timeout: Duration,
+    /// Sent to every player still connected when the server shuts down.
+    pub shutdown_reason: ChatComponent<'static>,
+    /// Answers the server list ping of clients older than 1.7 with the
+    /// [`Handler::status`] response.
+    #[cfg(feature = "legacy")]
+    pub legacy_ping: bool,
+    /// Reads a PROXY protocol header on connections from these addresses,
+    /// exposing the client's address through [`Connection::remote_addr`].
+    #[cfg(feature = "proxy-protocol")]
+    pub proxy_protocol: Option<TrustedProxies>,
+    /// Accepts handshakes carrying BungeeCord's forwarded info, longer than
+    /// vanilla allows. Only enable it behind BungeeCord.
+    #[cfg(feature = "forwarding")]
+    pub bungeecord_forwarding: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            version: ProtocolVersionEnum::V1_8.into(),
+            max_connections: 1024,
+            max_connections_per_ip: None,
+            handshake_timeout: Duration::from_secs(5),
+            shutdown_reason: ChatComponent::new("Server closed"),
+            #[cfg(feature = "legacy")]
+            legacy_ping: true,
+            #[cfg(feature = "proxy-protocol")]
+            proxy_protocol: None,
+            #[cfg(feature = "forwarding")]
+            bungeecord_forwarding: false,
+        }
+    }
+}
+
+/// Tells a running [`Server`] to stop accepting connections and disconnect
+/// everyone.
+#[derive(Clone)]
+pub struct ShutdownHandle(Arc<watch::Sender<bool>>);
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        self.0.send_replace(true);
+    }
+}
+
+pub struct Server<H> {
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+    handler: Arc<H>,
+    shutdown: ShutdownHandle,
+}
+
+impl<H: Handler> Server<H> {
+    pub async fn bind<A: ToSocketAddrs>(
+        addr: A,
+        config: ServerConfig,
+        handler: H,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let (shutdown, _) = watch::channel(false);
+
+        Ok(Self {
+            listener,
+            config: Arc::new(config),
+            handler: Arc::new(handler),
+            shutdown: ShutdownHandle(Arc::new(shutdown)),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Accepts connections until shut down, then waits for every connection
+    /// to be disconnected.
+    pub async fn run(self) -> io::Result<()> {
+        let limits = Limits::new(&self.config);
+        let mut shutdown = self.shutdown.0.subscribe();
+
+        // every connection task holds a sender, so once they are all gone
+        // the receiver yields `None`
+        let (done_tx, mut done_rx) = mpsc::channel::<()>(1);
+
+        loop {
+            let (stream, addr) = tokio::select! {
+                accepted = self.listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(err) => {
+                        tracing::warn!("failed to accept connection: {}", err);
+                        continue;
+                    }
+                },
+                _ = signaled(&mut shutdown) => break,
+            };
+
+            let permit = match limits.acquire(addr.ip()) {
+                Some(permit) => permit,
+                None => {
+                    tracing::debug!("refused connection from {}, limit reached", addr);
+                    continue;
+                }
+            };
+
+            if let Err(err) = stream.set_nodelay(true) {
+                tracing::debug!("failed to set nodelay for {}: {}", addr, err);
+            }
+
+            let handler = self.handler.clone();
+            let config = self.config.clone();
+            let shutdown = self.shutdown.0.subscribe();
+            let done = done_tx.clone();
+
+            tokio::spawn(async move {
+                if let Err(err) =
+                    handle_connection(&*handler, &config, stream, Some(addr.ip()), shutdown).await
+                {
+                    tracing::debug!("connection from {} closed: {}", addr, err);
+                }
+
+                drop(permit);
+                drop(done);
+            });
+        }
+
+        drop(done_tx);
+        let _ = done_rx.recv().await;
+
+        Ok(())
+    }
+}
+
+/// Serves a single connection outside of a [`Server`], from the handshake
+/// until it is closed.
+pub async fn serve_connection<H, T>(
+    handler: &H,
+    config: &ServerConfig,
+    transport: T,
+) -> io::Result<()>
+where
+    H: Handler,
+    T: Transport,
+{
+    // nobody ever signals this one, it only has to outlive the connection
+    let (_shutdown, rx) = watch::channel(false);
+    handle_connection(handler, config, transport, None, rx).await
+}
+
+#[cfg_attr(not(feature = "proxy-protocol"), allow(unused_variables))]
+async fn handle_connection<H, T>(
+    handler: &H,
+    config: &ServerConfig,
+    transport: T,
+    peer: Option<IpAddr>,
+    mut shutdown: watch::Receiver<bool>,
+) -> io::Result<()>
+where
+    H: Handler,
+    T: Transport,
+{
+    #[cfg(any(feature = "legacy", feature = "proxy-protocol"))]
+    let mut transport = transport;
+    // bytes read ahead of the handshake, handed back to the connection
+    #[cfg(any(feature = "legacy", feature = "proxy-protocol"))]
+    let mut prefix = Vec::new();
+
+    // anyone could claim any address, so only trusted peers are asked
+    #[cfg(feature = "proxy-protocol")]
+    let remote_addr = match (&config.proxy_protocol, peer) {
+        (Some(trusted), Some(peer)) if trusted.contains(peer) => {
+            let read = proxy_protocol::read_proxy_header(&mut transport);
+            let (header, read) = tokio::time::timeout(config.handshake_timeout, read)
+                .await
+                .map_err(|_| handshake_timed_out())??;
+
+            prefix = read;
+            header.and_then(|header| header.source)
+        }
+        _ => None,
+    };
+
+    // a legacy ping has to be told apart before the first byte is taken as
+    // the start of a frame length
+    #[cfg(feature = "legacy")]
+    if config.legacy_ping {
+        use tokio::io::AsyncReadExt;
+
+        let first = match prefix.first() {
+            Some(first) => *first,
+            None => match tokio::time::timeout(config.handshake_timeout, transport.read_u8()).await
+            {
+                Ok(Ok(first)) => {
+                    prefix.push(first);
+                    first
+                }
+                Ok(Err(err)) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Ok(Err(err)) => return Err(err),
+                Err(_) => return Err(handshake_timed_out()),
+            },
+        };
+
+        if first == legacy::LEGACY_PING_ID
+            && legacy::is_legacy_ping(&mut transport, &mut prefix, LEGACY_PING_WAIT).await?
+        {
+            // a client can go quiet anywhere past the first bytes
+            let status = legacy_status(handler, &prefix[1..], transport);
+            return tokio::time::timeout(config.handshake_timeout, status)
+                .await
+                .map_err(|_| handshake_timed_out())?;
+        }
+    }
+
+    let mut conn = Connection::<_, Handshake>::new(transport, config.version);
+    #[cfg(any(feature = "legacy", feature = "proxy-protocol"))]
+    conn.read_buffer_mut().extend(prefix);
+    #[cfg(feature = "proxy-protocol")]
+    conn.set_remote_addr(remote_addr);
+
+    let recv_handshake = async {
+        #[cfg(feature = "forwarding")]
+        if config.bungeecord_forwarding {
+            let handshake = conn.recv_as::<BungeeCordHandshake>().await?;
+            return Ok(handshake.map(|handshake| handshake.0));
+        }
+
+        conn.recv().await
+    };
+
+    let handshake = match tokio::time::timeout(config.handshake_timeout, recv_handshake).await {
+        Ok(Ok(Some(handshake))) => handshake,
+        Ok(Ok(None)) => return Ok(()),
+        Ok(Err(err)) => return Err(err),
+        Err(_) => return Err(handshake_timed_out()),
+    };
+
+    match handshake.next_state {
+        NextState::Status => {
+            let mut conn = conn.adapt::<Request>(ProtocolState::Status);
+
+            tokio::select! {
+                res = status(handler, &handshake, &mut conn) => res,
+                _ = signaled(&mut shutdown) => Ok(()),
+            }
+        }
+        NextState::Login => {
+            let mut conn = conn.adapt::<LoginStart>(ProtocolState::Login);
+
+            let player = tokio::select! {
+                res = handler.login(&handshake, &mut conn) => Some(res),
+                _ = signaled(&mut shutdown) => None,
+            };
+
+            let player = match player {
+                Some(Ok(Some(player))) => player,
+                Some(Ok(None)) => return conn.shutdown().await,
+                Some(Err(err)) => return Err(err),
+                None => return conn.disconnect(config.shutdown_reason.clone()).await,
+            };
+
+            let mut conn = conn.adapt::<ServerBound>(ProtocolState::Play);
+
+            let res = tokio::select! {
+                res = handler.play(player, &mut conn) => Some(res),
+                _ = signaled(&mut shutdown) => None,
+            };
+
+            match res {
+                Some(res) => {
+                    res?;
+                    conn.shutdown().await
+                }
+                None => conn.disconnect(config.shutdown_reason.clone()).await,
+            }
+        }
+    }
+}
+
+async fn status<H: Handler, T: Transport>(
+    handler: &H,
+    handshake: &Handshake,
+    conn: &mut Connection<T, Request>,
+) -> io::Result<()> {
+    if conn.recv().await?.is_none() {
+        return Ok(());
+    }
+
+    let status = handler.status(handshake).await?;
+    conn.send(Response { status }).await?;
+
+    if let Some(Ping { payload }) = conn.recv_as::<Ping>().await? {
+        conn.send(Pong { payload }).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "legacy")]
+async fn legacy_status<H, T>(handler: &H, prefix: &[u8], mut transport: T) -> io::Result<()>
+where
+    H: Handler,
+    T: Transport,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // the bytes past `0xFE` read to tell the ping apart come first
+    let ping =
+        legacy::read_legacy_ping(&mut prefix.chain(&mut transport), LEGACY_PING_WAIT).await?;
+    let handshake = Handshake {
+        protocol_version: ping.protocol_version.map_or(-1, i32::from),
+        server_address: ping.host.unwrap_or_default(),
+        server_port: ping.port.unwrap_or_default(),
+        next_state: NextState::Status,
+    };
+
+    let status = handler.status(&handshake).await?;
+    legacy::write_legacy_status(&mut transport, &(&status).into()).await?;
+    transport.shutdown().await
+}
+
+fn handshake_timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "handshake timed out")
+}
+
+/// Resolves once shutdown was requested, or the server is gone.
+async fn signaled(shutdown: &mut watch::Receiver<bool>) {
+    while !*shutdown.borrow_and_update() {
+        if shutdown.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+struct Limits {
+    total: Arc<Semaphore>,
+    per_ip: Option<(usize, Arc<Mutex<HashMap<IpAddr, usize>>>)>,
+}
+
+impl Limits {
+    fn new(config: &ServerConfig) -> Self {
+        Self {
+            total: Arc::new(Semaphore::new(config.max_connections)),
+            per_ip: config
+                .max_connections_per_ip
+                .map(|max| (max, Default::default())),
+        }
+    }
+
+    fn acquire(&self, ip: IpAddr) -> Option<ConnectionPermit> {
+        let total = self.total.clone().try_acquire_owned().ok()?;
+
+        let per_ip = match &self.per_ip {
+            Some((max, counts)) => {
+                let mut guard = counts.lock().unwrap();
+                let count = guard.entry(ip).or_default();
+                if *count >= *max {
+                    return None;
+                }
+                *count += 1;
+
+                Some((ip, counts.clone()))
+            }
+            None => None,
+        };
+
+        Some(ConnectionPermit {
+            _total: total,
+            per_ip,
+        })
+    }
+}
+
+struct ConnectionPermit {
+    _total: OwnedSemaphorePermit,
+    per_ip: Option<(IpAddr, Arc<Mutex<HashMap<IpAddr, usize>>>)>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        if let Some((ip, counts)) = self.per_ip.take() {
+            let mut counts = counts.lock().unwrap();
+            if let Some(count) = counts.get_mut(&ip) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.remove(&ip);
+                }
+            }
+        }
+    }
+}
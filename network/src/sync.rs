@@ -0,0 +1,250 @@
+//! Blocking counterpart of [`crate::connection`], built on `std::io` only.
+
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use bytes::BytesMut;
+use codec::Codec;
+use protocol::{PacketDecoder, PacketEncoder, ProtocolVersion};
+
+const READ_CHUNK_LEN: usize = 8 * 1024;
+
+/// A framed, blocking connection, using the same framing, compression and
+/// encryption as [`Codec`].
+pub struct Connection<S = TcpStream> {
+    stream: S,
+    codec: Codec<()>,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+}
+
+impl Connection<TcpStream> {
+    pub fn connect<A, V>(addr: A, version: V) -> io::Result<Self>
+    where
+        A: ToSocketAddrs,
+        V: Into<ProtocolVersion>,
+    {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self::new(stream, version))
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.local_addr()
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_write_timeout(timeout)
+    }
+}
+
+impl<S> Connection<S> {
+    pub fn new<V: Into<ProtocolVersion>>(stream: S, version: V) -> Self {
+        Self::with_codec(stream, Codec::from(version))
+    }
+
+    pub fn with_codec(stream: S, codec: Codec<()>) -> Self {
+        Self {
+            stream,
+            codec,
+            read_buf: BytesMut::new(),
+            write_buf: BytesMut::new(),
+        }
+    }
+
+    /// Get a reference to the connection's version.
+    pub fn version(&self) -> &ProtocolVersion {
+        self.codec.version()
+    }
+
+    pub fn codec(&self) -> &Codec<()> {
+        &self.codec
+    }
+
+    pub fn codec_mut(&mut self) -> &mut Codec<()> {
+        &mut self.codec
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Returns the underlying stream. Anything buffered is lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Enables zlib compression for both directions.
+    #[cfg(feature = "compression")]
+    pub fn enable_compression(&mut self, threshold: i32) {
+        self.codec.enable_compression(threshold);
+    }
+
+    /// Enables aes-cfb8 encryption for both directions.
+    #[cfg(feature = "encryption")]
+    pub fn enable_encryption(&mut self, secret: &[u8; 16]) {
+        self.codec.enable_encryption(secret);
+    }
+}
+
+impl<S: Write> Connection<S> {
+    /// Buffers a packet without writing it to the stream.
+    pub fn feed<P: PacketEncoder>(&mut self, packet: &P) -> io::Result<()> {
+        self.codec.encode_packet(packet, &mut self.write_buf)
+    }
+
+    /// Writes every buffered packet to the stream.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.stream.write_all(&self.write_buf[..])?;
+        self.write_buf.clear();
+        self.stream.flush()
+    }
+
+    /// Writes a packet and flushes the stream.
+    pub fn send<P: PacketEncoder>(&mut self, packet: &P) -> io::Result<()> {
+        self.feed(packet)?;
+        self.flush()
+    }
+}
+
+impl<S: Read> Connection<S> {
+    /// Blocks until a whole packet is read, `None` meaning the peer closed
+    /// the connection.
+    pub fn recv<P: PacketDecoder>(&mut self) -> io::Result<Option<P>> {
+        loop {
+            if let Some(packet) = self.codec.decode_packet(&mut self.read_buf)? {
+                return Ok(Some(packet));
+            }
+
+            let pos = self.read_buf.len();
+            self.read_buf.resize(pos + READ_CHUNK_LEN, 0);
+
+            let len = match self.stream.read(&mut self.read_buf[pos..]) {
+                Ok(len) => len,
+                Err(err) => {
+                    self.read_buf.truncate(pos);
+                    match err.kind() {
+                        io::ErrorKind::Interrupted => continue,
+                        _ => return Err(err),
+                    }
+                }
+            };
+            self.read_buf.truncate(pos + len);
+
+            if len == 0 {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{net::TcpListener, thread};
+
+    use protocol::packets::{
+        handshake::{Handshake, NextState},
+        play::{client_bound::KeepAlive, ClientBound},
+        status::{Ping, Pong},
+    };
+
+    use super::*;
+
+    fn version() -> ProtocolVersion {
+        ProtocolVersion::new(47)
+    }
+
+    #[test]
+    fn test_sync_roundtrip() {
+        let mut writer = Connection::new(Vec::new(), version());
+        writer
+            .send(&Handshake {
+                protocol_version: 47,
+                server_address: "localhost".into(),
+                server_port: 25565,
+                next_state: NextState::Status,
+            })
+            .unwrap();
+        writer.send(&Ping { payload: 42 }).unwrap();
+
+        let buf = writer.into_inner();
+        let mut reader = Connection::new(&buf[..], version());
+
+        assert!(matches!(
+            reader.recv::<Handshake>().unwrap(),
+            Some(Handshake {
+                next_state: NextState::Status,
+                ..
+            })
+        ));
+        assert!(matches!(
+            reader.recv::<Ping>().unwrap(),
+            Some(Ping { payload: 42 })
+        ));
+        assert!(reader.recv::<Ping>().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_sync_compression_and_encryption() {
+        let secret = [3; 16];
+
+        let mut writer = Connection::new(Vec::new(), version());
+        writer.enable_compression(4);
+        writer.enable_encryption(&secret);
+        for keep_alive_id in 0..3 {
+            writer.feed(&KeepAlive { keep_alive_id }).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let buf = writer.into_inner();
+        let mut reader = Connection::new(&buf[..], version());
+        reader.enable_compression(4);
+        reader.enable_encryption(&secret);
+
+        for id in 0..3 {
+            assert!(matches!(
+                reader.recv::<ClientBound>().unwrap(),
+                Some(ClientBound::KeepAlive(KeepAlive { keep_alive_id })) if keep_alive_id == id
+            ));
+        }
+    }
+
+    #[test]
+    fn test_sync_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::new(stream, version());
+
+            let Ping { payload } = conn.recv().unwrap().unwrap();
+            conn.send(&Pong { payload }).unwrap();
+        });
+
+        let mut client = Connection::connect(addr, version()).unwrap();
+        client.send(&Ping { payload: 7 }).unwrap();
+        assert!(matches!(
+            client.recv::<Pong>().unwrap(),
+            Some(Pong { payload: 7 })
+        ));
+
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,373 @@
+//! In-memory connections and a scripted client for protocol level tests.
+//!
+//! ```ignore
+//! let (mut client, server) = testing::serve(handler, ServerConfig::default());
+//!
+//! Script::new()
+//!     .send(Handshake { next_state: NextState::Status, .. })
+//!     .send(Request)
+//!     .expect::<Response>()
+//!     .send(Ping { payload: 42 })
+//!     .expect_match(|pong: &Pong| pong.payload == 42)
+//!     .expect_close()
+//!     .run(&mut client)
+//!     .await?;
+//! ```
+
+use std::{any::type_name, fmt::Debug, future::Future, io, pin::Pin};
+#[cfg(feature = "auth")]
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(feature = "auth")]
+use protocol::misc::prelude::GameProfile;
+
+use protocol::{PacketDecoder, PacketEncoder, ProtocolVersion};
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream};
+#[cfg(feature = "server")]
+use tokio::task::JoinHandle;
+
+#[cfg(feature = "auth")]
+use crate::auth::{Authenticator, SessionVerifier};
+use crate::connection::{Connection, RawPacket};
+#[cfg(feature = "server")]
+use crate::server::{self, Handler, ServerConfig};
+
+/// Bytes either side can write before waiting for the other one to read.
+pub const DEFAULT_DUPLEX_CAPACITY: usize = 64 * 1024;
+
+/// Creates a connected client and server pair over an in-memory stream,
+/// both using the same codec settings.
+///
+/// `C` is the packet type received by the client, `S` the one received by
+/// the server.
+pub fn duplex<C, S>(
+    version: impl Into<ProtocolVersion>,
+) -> (Connection<DuplexStream, C>, Connection<DuplexStream, S>) {
+    duplex_with_capacity(version, DEFAULT_DUPLEX_CAPACITY)
+}
+
+pub fn duplex_with_capacity<C, S>(
+    version: impl Into<ProtocolVersion>,
+    capacity: usize,
+) -> (Connection<DuplexStream, C>, Connection<DuplexStream, S>) {
+    let version = version.into();
+    let (client, server) = tokio::io::duplex(capacity);
+
+    (
+        Connection::new(client, version),
+        Connection::new(server, version),
+    )
+}
+
+/// Serves a handler on one end of an in-memory stream, returning the other
+/// end as a client connection.
+#[cfg(feature = "server")]
+pub fn serve<H: Handler>(
+    handler: H,
+    config: ServerConfig,
+) -> (Connection<DuplexStream, ()>, JoinHandle<io::Result<()>>) {
+    let (client, stream) = tokio::io::duplex(DEFAULT_DUPLEX_CAPACITY);
+    let version = config.version;
+
+    let handle =
+        tokio::spawn(async move { server::serve_connection(&handler, &config, stream).await });
+
+    (Connection::new(client, version), handle)
+}
+
+/// A session server kept in memory, so online-mode logins can be tested
+/// without reaching Mojang.
+///
+/// Like the real one, it only knows about players who joined with the
+/// server hash the server later asks about.
+#[cfg(feature = "auth")]
+#[derive(Debug, Default)]
+pub struct MockSessionVerifier {
+    joined: Mutex<HashMap<String, (String, GameProfile)>>,
+}
+
+#[cfg(feature = "auth")]
+impl MockSessionVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `profile` joined a server, what clients do right before
+    /// answering `EncryptionRequest`.
+    pub fn join<H: Into<String>>(&self, profile: GameProfile, server_hash: H) {
+        self.joined
+            .lock()
+            .unwrap()
+            .insert(profile.name.clone(), (server_hash.into(), profile));
+    }
+}
+
+#[cfg(feature = "auth")]
+impl SessionVerifier for MockSessionVerifier {
+    async fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+    ) -> io::Result<Option<GameProfile>> {
+        Ok(match self.joined.lock().unwrap().get(username) {
+            Some((hash, profile)) if hash == server_hash => Some(profile.clone()),
+            _ => None,
+        })
+    }
+}
+
+/// Joins through a [`MockSessionVerifier`] as `profile`.
+#[cfg(feature = "auth")]
+#[derive(Clone, Debug)]
+pub struct MockAuthenticator {
+    verifier: Arc<MockSessionVerifier>,
+    profile: GameProfile,
+}
+
+#[cfg(feature = "auth")]
+impl MockAuthenticator {
+    pub fn new(verifier: Arc<MockSessionVerifier>, profile: GameProfile) -> Self {
+        Self { verifier, profile }
+    }
+}
+
+#[cfg(feature = "auth")]
+impl Authenticator for MockAuthenticator {
+    async fn join(&self, server_hash: &str) -> io::Result<()> {
+        self.verifier.join(self.profile.clone(), server_hash);
+        Ok(())
+    }
+}
+
+type StepFuture<'a> = Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'a>>;
+type Step<T> = Box<dyn for<'a> FnOnce(&'a mut Connection<T, ()>) -> StepFuture<'a> + Send>;
+
+/// A list of packets to send and expect, run in order against a connection.
+///
+/// The first step that fails stops the script, its error telling which step
+/// it was.
+pub struct Script<T = DuplexStream> {
+    steps: Vec<(String, Step<T>)>,
+}
+
+impl<T> Script<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    pub fn new() -> Self {
+        Self { steps: Vec::new() }
+    }
+
+    /// Sends a packet.
+    pub fn send<P>(mut self, packet: P) -> Self
+    where
+        P: PacketEncoder + Send + 'static,
+    {
+        self.steps.push((
+            format!("send {}", type_name::<P>()),
+            Box::new(move |conn| Box::pin(conn.send(packet))),
+        ));
+        self
+    }
+
+    /// Expects the next packet to decode as `P`.
+    pub fn expect<P>(self) -> Self
+    where
+        P: PacketDecoder + Send + 'static,
+    {
+        self.expect_match(|_: &P| true)
+    }
+
+    /// Expects the next packet to decode as `P` and pass `check`.
+    pub fn expect_match<P, F>(mut self, check: F) -> Self
+    where
+        P: PacketDecoder + Send + 'static,
+        F: FnOnce(&P) -> bool + Send + 'static,
+    {
+        self.steps.push((
+            format!("expect {}", type_name::<P>()),
+            Box::new(move |conn| {
+                Box::pin(async move {
+                    match conn.recv_as::<P>().await? {
+                        Some(packet) if check(&packet) => Ok(()),
+                        Some(packet) => Err(unexpected(packet)),
+                        None => Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "connection closed",
+                        )),
+                    }
+                })
+            }),
+        ));
+        self
+    }
+
+    /// Expects the peer to close the connection.
+    pub fn expect_close(mut self) -> Self {
+        self.steps.push((
+            "expect close".into(),
+            Box::new(|conn| {
+                Box::pin(async move {
+                    // through the codec, as frames it already read count too
+                    match conn.recv_as::<RawPacket>().await? {
+                        None => Ok(()),
+                        Some(packet) => Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "connection is still sending data, got {:02X?}",
+                                packet.as_bytes()
+                            ),
+                        )),
+                    }
+                })
+            }),
+        ));
+        self
+    }
+
+    /// Runs every step against `conn`.
+    pub async fn run(self, conn: &mut Connection<T, ()>) -> io::Result<()> {
+        for (i, (name, step)) in self.steps.into_iter().enumerate() {
+            step(conn).await.map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("step {} ({}) failed: {}", i + 1, name, err),
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> Default for Script<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn unexpected<P: Debug>(packet: P) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected packet {:?}", packet),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use protocol::{
+        misc::prelude::StatusResponse,
+        packets::{
+            handshake::{Handshake, NextState},
+            status::{Ping, Pong, Request, Response},
+        },
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_duplex_roundtrip() {
+        let (mut client, mut server) = duplex::<Pong, Ping>(ProtocolVersion::new(47));
+
+        client.send(Ping { payload: 7 }).await.unwrap();
+        let Ping { payload } = server.recv().await.unwrap().unwrap();
+        server.send(Pong { payload }).await.unwrap();
+
+        assert!(matches!(
+            client.recv().await.unwrap(),
+            Some(Pong { payload: 7 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_script_against_peer() {
+        let (mut client, server) = duplex::<(), Handshake>(ProtocolVersion::new(47));
+
+        let peer = tokio::spawn(async move {
+            let mut server = server.adapt::<Request>(protocol::ProtocolState::Status);
+            server.recv_as::<Handshake>().await?;
+            server.recv().await?;
+            server
+                .send(Response {
+                    status: StatusResponse::new("1.8.9", 47, Default::default()),
+                })
+                .await?;
+
+            if let Some(Ping { payload }) = server.recv_as::<Ping>().await? {
+                server.send(Pong { payload }).await?;
+            }
+            server.shutdown().await
+        });
+
+        Script::new()
+            .send(Handshake {
+                protocol_version: 47,
+                server_address: "localhost".into(),
+                server_port: 25565,
+                next_state: NextState::Status,
+            })
+            .send(Request)
+            .expect::<Response>()
+            .send(Ping { payload: 42 })
+            .expect_match(|pong: &Pong| pong.payload == 42)
+            .expect_close()
+            .run(&mut client)
+            .await
+            .unwrap();
+
+        peer.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_script_reports_failed_step() {
+        let (mut client, mut server) = duplex::<(), Ping>(ProtocolVersion::new(47));
+
+        let peer = tokio::spawn(async move {
+            let Ping { payload } = server.recv().await?.unwrap();
+            server
+                .send(Pong {
+                    payload: payload + 1,
+                })
+                .await
+        });
+
+        let err = Script::new()
+            .send(Ping { payload: 1 })
+            .expect_match(|pong: &Pong| pong.payload == 1)
+            .run(&mut client)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("step 2 (expect "));
+
+        peer.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expect_close_with_buffered_frame() {
+        let (mut client, mut server) = duplex::<(), Ping>(ProtocolVersion::new(47));
+
+        // both frames reach the client before it reads anything, so they end
+        // up in its read buffer together
+        server.send(Pong { payload: 1 }).await.unwrap();
+        server.send(Pong { payload: 2 }).await.unwrap();
+        server.shutdown().await.unwrap();
+
+        let err = Script::new()
+            .expect_match(|pong: &Pong| pong.payload == 1)
+            .expect_close()
+            .run(&mut client)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("step 2 (expect close)"));
+    }
+}
@@ -0,0 +1,174 @@
+use std::{io, time::Duration};
+
+use network::{
+    legacy,
+    ping::{self, PingOptions},
+    server::{Handler, LoginConnection, PlayConnection, Server, ServerConfig, Transport},
+    testing,
+};
+use protocol::{
+    misc::prelude::{ChatComponent, StatusResponse},
+    packets::{
+        handshake::Handshake,
+        status::{Ping, Pong, Request, Response},
+    },
+    ProtocolState, ProtocolVersion,
+};
+
+/// Stands in for a real server, answering status requests only.
+struct StandIn {
+    protocol: i32,
+}
+
+impl Handler for StandIn {
+    type Player = ();
+
+    async fn status(&self, handshake: &Handshake) -> io::Result<StatusResponse> {
+        let mut status = StatusResponse::new(
+            "stand-in",
+            self.protocol,
+            ChatComponent::new(handshake.server_address.clone()),
+        );
+        status.players.online = 3;
+
+        Ok(status)
+    }
+
+    async fn login<T: Transport>(
+        &self,
+        _: &Handshake,
+        _: &mut LoginConnection<T>,
+    ) -> io::Result<Option<Self::Player>> {
+        Ok(None)
+    }
+
+    async fn play<T: Transport>(&self, _: (), _: &mut PlayConnection<T>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_ping_loopback() {
+    let server = Server::bind(
+        "127.0.0.1:0",
+        ServerConfig::default(),
+        StandIn { protocol: 47 },
+    )
+    .await
+    .unwrap();
+    let port = server.local_addr().unwrap().port();
+    let shutdown = server.shutdown_handle();
+    let task = tokio::spawn(server.run());
+
+    let result = ping::ping("127.0.0.1", port, &PingOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(result.status.version.name, "stand-in");
+    assert_eq!(result.status.players.online, 3);
+    assert_eq!(result.status.description.text.as_deref(), Some("127.0.0.1"));
+    assert!(result.latency < Duration::from_secs(5));
+
+    shutdown.shutdown();
+    task.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_detect_version() {
+    let server = Server::bind(
+        "127.0.0.1:0",
+        ServerConfig::default(),
+        StandIn { protocol: 340 },
+    )
+    .await
+    .unwrap();
+    let port = server.local_addr().unwrap().port();
+    let shutdown = server.shutdown_handle();
+    let task = tokio::spawn(server.run());
+
+    let version = ping::detect_version("127.0.0.1", port, &PingOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(*version, 340);
+
+    shutdown.shutdown();
+    task.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_ping_timeout() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // accepts, but never answers
+    let task = tokio::spawn(async move { listener.accept().await });
+
+    let options = PingOptions {
+        timeout: Duration::from_millis(50),
+        ..Default::default()
+    };
+    let err = ping::ping("127.0.0.1", port, &options).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    task.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_ping_payload_mismatch() {
+    let (mut client, server) = testing::duplex::<(), Handshake>(ProtocolVersion::new(47));
+
+    let peer = tokio::spawn(async move {
+        let mut server = server.adapt::<Request>(ProtocolState::Status);
+        server.recv_as::<Handshake>().await?;
+        server.recv().await?;
+        server
+            .send(Response {
+                status: StatusResponse::new("stand-in", 47, Default::default()),
+            })
+            .await?;
+
+        let Ping { payload } = server.recv_as().await?.unwrap();
+        server
+            .send(Pong {
+                payload: payload + 1,
+            })
+            .await
+    });
+
+    let err = ping::ping_connection(&mut client, "localhost", 25565)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    peer.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_legacy_ping_loopback() {
+    let server = Server::bind(
+        "127.0.0.1:0",
+        ServerConfig::default(),
+        StandIn { protocol: 47 },
+    )
+    .await
+    .unwrap();
+    let port = server.local_addr().unwrap().port();
+    let shutdown = server.shutdown_handle();
+    let task = tokio::spawn(server.run());
+
+    let status = legacy::legacy_ping("127.0.0.1", port, &PingOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(status.protocol, 47);
+    assert_eq!(status.version, "stand-in");
+    assert_eq!(status.motd, "127.0.0.1");
+    assert_eq!((status.online, status.max), (3, 0));
+
+    // modern pings still work with legacy detection on
+    let result = ping::ping("127.0.0.1", port, &PingOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(result.status.players.online, 3);
+
+    shutdown.shutdown();
+    task.await.unwrap().unwrap();
+}
@@ -0,0 +1,378 @@
+use std::io;
+
+use network::{
+    connection::Connection,
+    server::{Handler, LoginConnection, PlayConnection, Server, ServerConfig, Transport},
+    testing::{self, Script},
+};
+use protocol::{
+    misc::prelude::{ChatComponent, StatusResponse},
+    packets::{
+        handshake::{Handshake, NextState},
+        login::{LoginStart, LoginSuccess},
+        play::{client_bound, server_bound, ClientBound, ServerBound},
+        status::{Ping, Pong, Request, Response},
+    },
+    ProtocolState, ProtocolVersion,
+};
+use tokio::net::TcpStream;
+
+struct EchoHandler;
+
+impl Handler for EchoHandler {
+    type Player = String;
+
+    async fn status(&self, _: &Handshake) -> io::Result<StatusResponse> {
+        let mut status = StatusResponse::new("1.8.9", 47, ChatComponent::new("hello"));
+        status.players.max = 20;
+
+        Ok(status)
+    }
+
+    async fn login<T: Transport>(
+        &self,
+        _: &Handshake,
+        conn: &mut LoginConnection<T>,
+    ) -> io::Result<Option<Self::Player>> {
+        let username = match conn.recv().await? {
+            Some(LoginStart { username }) => username,
+            None => return Ok(None),
+        };
+
+        conn.send(LoginSuccess {
+            uuid: uuid::Uuid::nil(),
+            username: username.clone(),
+        })
+        .await?;
+
+        Ok(Some(username))
+    }
+
+    async fn play<T: Transport>(
+        &self,
+        _: Self::Player,
+        conn: &mut PlayConnection<T>,
+    ) -> io::Result<()> {
+        while let Some(packet) = conn.recv().await? {
+            if let ServerBound::KeepAlive(server_bound::KeepAlive { keep_alive_id }) = packet {
+                conn.send(client_bound::KeepAlive { keep_alive_id }).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn version() -> ProtocolVersion {
+    ProtocolVersion::new(47)
+}
+
+async fn connect(addr: std::net::SocketAddr, next_state: NextState) -> Connection<TcpStream, ()> {
+    let mut conn = Connection::connect(addr, version()).await.unwrap();
+    conn.send(Handshake {
+        protocol_version: 47,
+        server_address: "localhost".into(),
+        server_port: addr.port(),
+        next_state,
+    })
+    .await
+    .unwrap();
+
+    conn
+}
+
+async fn login(
+    addr: std::net::SocketAddr,
+    username: &str,
+) -> Connection<TcpStream, ClientBound<'static>> {
+    let mut conn = connect(addr, NextState::Login).await;
+    conn.send(LoginStart {
+        username: username.into(),
+    })
+    .await
+    .unwrap();
+
+    let success = conn.recv_as::<LoginSuccess>().await.unwrap().unwrap();
+    assert_eq!(success.username, username);
+
+    conn.adapt(ProtocolState::Play)
+}
+
+#[tokio::test]
+async fn test_server_status() {
+    let server = Server::bind("127.0.0.1:0", ServerConfig::default(), EchoHandler)
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let shutdown = server.shutdown_handle();
+    let task = tokio::spawn(server.run());
+
+    let mut conn = connect(addr, NextState::Status).await;
+    conn.send(Request).await.unwrap();
+
+    let response = conn.recv_as::<Response>().await.unwrap().unwrap();
+    assert_eq!(response.status.description.text.as_deref(), Some("hello"));
+    assert_eq!(response.status.players.max, 20);
+
+    conn.send(Ping { payload: 42 }).await.unwrap();
+    let pong = conn.recv_as::<Pong>().await.unwrap().unwrap();
+    assert_eq!(pong.payload, 42);
+
+    shutdown.shutdown();
+    task.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_server_play_and_graceful_shutdown() {
+    let server = Server::bind("127.0.0.1:0", ServerConfig::default(), EchoHandler)
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let shutdown = server.shutdown_handle();
+    let task = tokio::spawn(server.run());
+
+    let mut players = vec![login(addr, "Alice").await, login(addr, "Bob").await];
+
+    for (keep_alive_id, player) in players.iter_mut().enumerate() {
+        let keep_alive_id = keep_alive_id as i32;
+        player
+            .send(server_bound::KeepAlive { keep_alive_id })
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            player.recv().await.unwrap(),
+            Some(ClientBound::KeepAlive(client_bound::KeepAlive { keep_alive_id: id })) if id == keep_alive_id
+        ));
+    }
+
+    shutdown.shutdown();
+
+    for player in players.iter_mut() {
+        assert!(matches!(
+            player.recv().await.unwrap(),
+            Some(ClientBound::Disconnect(client_bound::Disconnect { reason }))
+                if reason.text.as_deref() == Some("Server closed")
+        ));
+        assert!(player.recv().await.unwrap().is_none());
+    }
+
+    task.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_server_connection_limit() {
+    let config = ServerConfig {
+        max_connections: 1,
+        ..Default::default()
+    };
+    let server = Server::bind("127.0.0.1:0", config, EchoHandler)
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let shutdown = server.shutdown_handle();
+    let task = tokio::spawn(server.run());
+
+    let _player = login(addr, "Alice").await;
+
+    let mut refused = Connection::<_, ()>::connect(addr, version()).await.unwrap();
+    assert!(!matches!(
+        refused.recv_as::<LoginSuccess>().await,
+        Ok(Some(_))
+    ));
+
+    shutdown.shutdown();
+    task.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_handler_in_memory() {
+    let (mut client, server) = testing::serve(EchoHandler, ServerConfig::default());
+
+    Script::new()
+        .send(Handshake {
+            protocol_version: 47,
+            server_address: "localhost".into(),
+            server_port: 25565,
+            next_state: NextState::Login,
+        })
+        .send(LoginStart {
+            username: "Alice".into(),
+        })
+        .expect_match(|success: &LoginSuccess| success.username == "Alice")
+        .send(server_bound::KeepAlive { keep_alive_id: 9 })
+        .expect_match(|packet: &ClientBound| {
+            matches!(
+                packet,
+                ClientBound::KeepAlive(client_bound::KeepAlive { keep_alive_id: 9 })
+            )
+        })
+        .run(&mut client)
+        .await
+        .unwrap();
+
+    client.shutdown().await.unwrap();
+    server.await.unwrap().unwrap();
+}
+
+/// Tells every login's [`Connection::remote_addr`], then closes it.
+#[cfg(feature = "proxy-protocol")]
+struct RemoteAddrHandler(tokio::sync::mpsc::UnboundedSender<Option<std::net::SocketAddr>>);
+
+#[cfg(feature = "proxy-protocol")]
+impl Handler for RemoteAddrHandler {
+    type Player = ();
+
+    async fn status(&self, handshake: &Handshake) -> io::Result<StatusResponse> {
+        EchoHandler.status(handshake).await
+    }
+
+    async fn login<T: Transport>(
+        &self,
+        _: &Handshake,
+        conn: &mut LoginConnection<T>,
+    ) -> io::Result<Option<Self::Player>> {
+        conn.recv().await?;
+        let _ = self.0.send(conn.remote_addr());
+
+        Ok(None)
+    }
+
+    async fn play<T: Transport>(&self, _: (), _: &mut PlayConnection<T>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "proxy-protocol")]
+#[tokio::test]
+async fn test_server_proxy_protocol() {
+    use network::proxy_protocol::{ProxyHeader, TrustedProxies};
+    use tokio::io::AsyncWriteExt;
+
+    async fn login_through(addr: std::net::SocketAddr, header: Option<ProxyHeader>) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        if let Some(header) = header {
+            stream.write_all(&header.to_bytes().unwrap()).await.unwrap();
+        }
+
+        let mut conn = Connection::<_, ()>::new(stream, version());
+        conn.feed(Handshake {
+            protocol_version: 47,
+            server_address: "localhost".into(),
+            server_port: addr.port(),
+            next_state: NextState::Login,
+        })
+        .await
+        .unwrap();
+        conn.send(LoginStart {
+            username: "Alice".into(),
+        })
+        .await
+        .unwrap();
+    }
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let config = ServerConfig {
+        proxy_protocol: Some(TrustedProxies::new().allow("127.0.0.0/8").unwrap()),
+        ..Default::default()
+    };
+    let server = Server::bind("127.0.0.1:0", config, RemoteAddrHandler(tx))
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let shutdown = server.shutdown_handle();
+    let task = tokio::spawn(server.run());
+
+    let client = "203.0.113.7:51234".parse().unwrap();
+    for version in [1, 2] {
+        login_through(addr, Some(ProxyHeader::new(version, client, addr))).await;
+        assert_eq!(rx.recv().await.unwrap(), Some(client));
+    }
+
+    login_through(addr, None).await;
+    assert_eq!(rx.recv().await.unwrap(), None);
+
+    shutdown.shutdown();
+    task.await.unwrap().unwrap();
+}
+
+#[cfg(feature = "forwarding")]
+#[tokio::test]
+async fn test_server_bungeecord_handshake() {
+    fn forwarded() -> Handshake {
+        Handshake {
+            protocol_version: 47,
+            server_address: format!(
+                "localhost\0203.0.113.7\0069a79f444e94726a5befca90e38aaf5\0[{{\"name\":\"textures\",\"value\":\"{}\"}}]",
+                "e30=".repeat(100)
+            ),
+            server_port: 25565,
+            next_state: NextState::Login,
+        }
+    }
+
+    // vanilla limits unless the server is told it is behind BungeeCord
+    let (mut client, server) = testing::serve(EchoHandler, ServerConfig::default());
+    client.send(forwarded()).await.unwrap();
+    assert!(server.await.unwrap().is_err());
+
+    let config = ServerConfig {
+        bungeecord_forwarding: true,
+        ..Default::default()
+    };
+    let (mut client, server) = testing::serve(EchoHandler, config);
+
+    Script::new()
+        .send(forwarded())
+        .send(LoginStart {
+            username: "Alice".into(),
+        })
+        .expect_match(|success: &LoginSuccess| success.username == "Alice")
+        .run(&mut client)
+        .await
+        .unwrap();
+
+    client.shutdown().await.unwrap();
+    server.await.unwrap().unwrap();
+}
+
+#[cfg(all(feature = "forwarding", feature = "legacy"))]
+#[tokio::test]
+async fn test_server_handshake_frame_starting_like_legacy_ping() {
+    use protocol::PacketEncoder;
+
+    // frames of 254 and 382 bytes, whose lengths start with 0xFE like a
+    // legacy ping does
+    for (frame_len, bungeecord_forwarding) in [(254, false), (382, true)] {
+        let handshake = Handshake {
+            protocol_version: 47,
+            server_address: "a".repeat(frame_len - 7),
+            server_port: 25565,
+            next_state: NextState::Login,
+        };
+        assert_eq!(
+            PacketEncoder::calculate_len(&handshake, &ProtocolVersion::new(47)),
+            frame_len
+        );
+
+        let config = ServerConfig {
+            bungeecord_forwarding,
+            ..Default::default()
+        };
+        assert!(config.legacy_ping);
+        let (mut client, server) = testing::serve(EchoHandler, config);
+
+        Script::new()
+            .send(handshake)
+            .send(LoginStart {
+                username: "Alice".into(),
+            })
+            .expect_match(|success: &LoginSuccess| success.username == "Alice")
+            .run(&mut client)
+            .await
+            .unwrap();
+
+        client.shutdown().await.unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -3,9 +3,9 @@ pub use misc;
 #[cfg(feature = "derive")]
 pub use protocol_derive::{packets, ProtocolSupport};
 pub use protocol_internal::{
-    DynArray, PacketDecoder, PacketEncoder, PacketSizer, ProtocolSupportDecoder,
-    ProtocolSupportEncoder, ProtocolVersion, ProtocolVersionEnum, RangeValidatedSupport, VarNum,
-    VarNumExt,
+    Angle, DecodeContext, DecodeMode, DynArray, FixedPoint, PacketDecoder, PacketEncoder,
+    PacketSizer, ProtocolState, ProtocolSupportDecoder, ProtocolSupportEncoder, ProtocolVersion,
+    ProtocolVersionEnum, RangeValidatedSupport, RemainingBytes, VarNum, VarNumExt,
 };
 
 #[cfg(feature = "packets")]
@@ -1,7 +1,10 @@
 use std::io;
 
 use misc::misc::chat::ChatComponent;
-use protocol_internal::{ProtocolSupportDecoder, ProtocolSupportEncoder, ProtocolVersionEnum};
+use protocol_internal::{
+    PacketDecoder, PacketSizer, ProtocolSupportDecoder, ProtocolSupportEncoder,
+    ProtocolVersionEnum, RemainingBytes, VarNum,
+};
 use uuid::Uuid;
 
 use crate::packet;
@@ -51,8 +54,75 @@ pub struct SetCompression {
     pub threshold: i32,
 }
 
+/// Asks the client about a custom channel before the login finishes, since
+/// 1.13.
+#[derive(Debug, protocol_derive::ProtocolSupport)]
+#[packet(0x04)]
+pub struct LoginPluginRequest {
+    #[protocol_field(varnum)]
+    pub message_id: i32,
+    pub channel: String,
+    pub data: RemainingBytes,
+}
+
+/// Answers a [`LoginPluginRequest`] with the same `message_id`. `data` is
+/// `None` when the client does not understand the channel.
+#[derive(Debug, protocol_derive::ProtocolSupport)]
+#[packet(0x02)]
+pub struct LoginPluginResponse {
+    #[protocol_field(varnum)]
+    pub message_id: i32,
+    pub data: Option<RemainingBytes>,
+}
+
+// Written out instead of going through `packet_enum!`: that macro declares
+// its own structs, which can't carry the `#[packet_size]` bounds above, and
+// its `ProtocolSupportDecoder` is left unimplemented.
+
+/// Any packet the server sends while logging in.
+#[derive(Debug)]
+pub enum ClientBound {
+    Disconnect(Disconnect),
+    EncryptionRequest(EncryptionRequest),
+    LoginSuccess(LoginSuccess),
+    SetCompression(SetCompression),
+    LoginPluginRequest(LoginPluginRequest),
+}
+
 packet!(0x02 => LoginSuccess);
 
+impl ProtocolSupportDecoder for ClientBound {
+    fn decode<R: io::Read>(
+        src: &mut protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> io::Result<Self> {
+        <Self as PacketDecoder>::decode(src, version)
+    }
+}
+
+impl PacketDecoder for ClientBound {
+    fn decode<R: io::Read>(
+        src: &mut protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> io::Result<Self> {
+        Ok(match VarNum::<i32>::decode(src)? {
+            0x00 => Self::Disconnect(ProtocolSupportDecoder::decode(src, version)?),
+            0x01 => Self::EncryptionRequest(ProtocolSupportDecoder::decode(src, version)?),
+            0x02 => Self::LoginSuccess(ProtocolSupportDecoder::decode(src, version)?),
+            0x03 => Self::SetCompression(ProtocolSupportDecoder::decode(src, version)?),
+            0x04 => Self::LoginPluginRequest(ProtocolSupportDecoder::decode(src, version)?),
+            id => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("invalid packet id {}", id),
+                ))
+            }
+        })
+    }
+}
+
+impl PacketSizer for ClientBound {}
+
 impl ProtocolSupportEncoder for LoginSuccess {
     fn calculate_len(&self, version: &protocol_internal::ProtocolVersion) -> usize {
         self.username.calculate_len(version)
@@ -80,7 +150,7 @@ impl ProtocolSupportEncoder for LoginSuccess {
 
 impl ProtocolSupportDecoder for LoginSuccess {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut protocol_internal::DecodeContext<R>,
         version: &protocol_internal::ProtocolVersion,
     ) -> io::Result<Self> {
         let uuid = if version >= &ProtocolVersionEnum::V1_16 {
@@ -99,7 +169,11 @@ impl ProtocolSupportDecoder for LoginSuccess {
 
 #[cfg(test)]
 mod test {
-    use protocol_internal::{ProtocolSupportEncoder, ProtocolVersionEnum};
+    use protocol_internal::{
+        DecodeContext, ProtocolSupportEncoder, ProtocolVersionEnum, RemainingBytes,
+    };
+
+    use super::{LoginPluginRequest, LoginPluginResponse};
 
     #[test]
     fn test_login_success_len() {
@@ -113,4 +187,65 @@ mod test {
             15
         )
     }
+
+    #[test]
+    fn test_login_plugin_roundtrip() {
+        use protocol_internal::{PacketDecoder, PacketEncoder};
+
+        let version = ProtocolVersionEnum::V1_8.into();
+
+        let request = LoginPluginRequest {
+            message_id: 300,
+            channel: "velocity:player_info".into(),
+            data: RemainingBytes(vec![1, 2, 3]),
+        };
+        let mut buf = vec![0; PacketEncoder::calculate_len(&request, &version)];
+        PacketEncoder::encode(&request, &mut &mut buf[..], &version).unwrap();
+
+        let decoded: LoginPluginRequest =
+            PacketDecoder::decode(&mut DecodeContext::from(&buf[..]), &version).unwrap();
+        assert_eq!(decoded.message_id, 300);
+        assert_eq!(decoded.channel, "velocity:player_info");
+        assert_eq!(*decoded.data, [1, 2, 3]);
+
+        for data in [None, Some(RemainingBytes(vec![4, 5]))].iter() {
+            let response = LoginPluginResponse {
+                message_id: 1,
+                data: data.clone(),
+            };
+            let mut buf = vec![0; PacketEncoder::calculate_len(&response, &version)];
+            PacketEncoder::encode(&response, &mut &mut buf[..], &version).unwrap();
+
+            let decoded: LoginPluginResponse =
+                PacketDecoder::decode(&mut DecodeContext::from(&buf[..]), &version).unwrap();
+            assert_eq!(&decoded.data, data);
+        }
+    }
+
+    #[test]
+    fn test_login_success_uuid_forms() {
+        use protocol_internal::{PacketDecoder, ProtocolSupportEncoder};
+
+        let version = ProtocolVersionEnum::V1_8.into();
+        for uuid in [
+            "069a79f4-44e9-4726-a5be-fca90e38aaf5",
+            "069a79f444e94726a5befca90e38aaf5",
+        ]
+        .iter()
+        {
+            let mut buf = vec![0x02];
+            uuid.to_string().encode(&mut buf, &version).unwrap();
+            "Notch".to_string().encode(&mut buf, &version).unwrap();
+
+            let packet: super::ClientBound =
+                PacketDecoder::decode(&mut DecodeContext::from(&buf[..]), &version).unwrap();
+            match packet {
+                super::ClientBound::LoginSuccess(success) => assert_eq!(
+                    success.uuid.to_string(),
+                    "069a79f4-44e9-4726-a5be-fca90e38aaf5"
+                ),
+                packet => panic!("unexpected packet {:?}", packet),
+            }
+        }
+    }
 }
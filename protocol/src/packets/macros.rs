@@ -13,7 +13,7 @@ macro_rules! packet {
         }
 
         impl $(<$($l),+>)? $crate::PacketDecoder for $n $(<$($l),+>)? {
-            fn decode<R: std::io::Read>(src: &mut R, version: &protocol_internal::ProtocolVersion) -> std::io::Result<Self> {
+            fn decode<R: std::io::Read>(src: &mut ::protocol_internal::DecodeContext<R>, version: &protocol_internal::ProtocolVersion) -> std::io::Result<Self> {
                 let id = $crate::VarNum::<i32>::decode(src)? as usize;
                 if id != $id {
                     return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("expected id {}, got {}", $id, id)));
@@ -78,7 +78,7 @@ macro_rules! packet_enum {
         }
 
         impl $(<$($l),+>)? $crate::ProtocolSupportDecoder for $en $(<$($l),+>)? {
-            fn decode<R: std::io::Read>(_: &mut R, _: &::protocol_internal::ProtocolVersion) -> std::io::Result<Self> {
+            fn decode<R: std::io::Read>(_: &mut ::protocol_internal::DecodeContext<R>, _: &::protocol_internal::ProtocolVersion) -> std::io::Result<Self> {
                 unimplemented!();
             }
         }
@@ -98,7 +98,7 @@ macro_rules! packet_enum {
         }
 
         impl $(<$($l),+>)? $crate::PacketDecoder for $en $(<$($l),+>)? {
-            fn decode<R: std::io::Read>(src: &mut R, version: &::protocol_internal::ProtocolVersion) -> std::io::Result<Self> {
+            fn decode<R: std::io::Read>(src: &mut ::protocol_internal::DecodeContext<R>, version: &::protocol_internal::ProtocolVersion) -> std::io::Result<Self> {
                 match $crate::VarNum::<i32>::decode(src)? {
                     $($id => Ok(Self::$pn($crate::ProtocolSupportDecoder::decode(src, version)?))),*,
                     id => Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("invalid packet id {}", id)))
@@ -1,12 +1,30 @@
 use misc::prelude::{
     BlockPosition, ChatComponent, ChatMode, ChatPosition, ChunkPosition, Difficulty, Dimension,
-    DisplayedSkinParts, EntityLocation, GameMode, Property, Vec2D, Vec3D,
+    DisplayedSkinParts, EntityLocation, GameMode, OptionalNbt, Property, Slot, Vec2D, Vec3D,
+};
+use protocol_internal::{
+    Angle, FixedPoint, ProtocolSupportDecoder, ProtocolSupportEncoder, RemainingBytes, VarNum,
 };
-use protocol_internal::{ProtocolSupportDecoder, ProtocolSupportEncoder};
 use uuid::Uuid;
 
+use metadata::Metadata;
+
+/// Round trips a packet literal with [`test::round_trip`], as its own type
+/// and as the variant of the same name in `$packets`.
+#[cfg(test)]
+macro_rules! round_trip {
+    ($packets:ident :: $packet:ident $fields:tt) => {
+        $crate::packets::play::test::round_trip($packet $fields, $packets::$packet)
+    };
+}
+
+pub mod chunk;
 pub mod client_bound;
+pub mod metadata;
+pub mod paletted;
+pub mod plugin;
 pub mod server_bound;
+pub mod tab_list;
 
 pub use client_bound::ClientBound;
 pub use server_bound::ServerBound;
@@ -37,4 +55,32 @@ mod test {
             11
         );
     }
+
+    /// Encodes and decodes `packet` as its own type and as the packet enum
+    /// `variant` belongs to, comparing the debug output since packets are not `PartialEq`.
+    pub(super) fn round_trip<P, E>(packet: P, variant: fn(P) -> E) -> Vec<u8>
+    where
+        P: Clone + PacketEncoder + protocol_internal::PacketDecoder,
+        E: std::fmt::Debug + protocol_internal::PacketDecoder,
+    {
+        use protocol_internal::{DecodeContext, PacketDecoder};
+
+        let version = ProtocolVersionEnum::V1_8.into();
+
+        let mut buf = Vec::new();
+        PacketEncoder::encode(&packet, &mut buf, &version).unwrap();
+        assert_eq!(buf.len(), PacketEncoder::calculate_len(&packet, &version));
+
+        let mut src = DecodeContext::from(&buf[..]);
+        let decoded = <P as PacketDecoder>::decode(&mut src, &version).unwrap();
+        assert!(src.is_empty());
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", packet));
+
+        let packet = variant(packet);
+        let mut src = DecodeContext::from(&buf[..]);
+        let decoded = <E as PacketDecoder>::decode(&mut src, &version).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", packet));
+
+        buf
+    }
 }
@@ -0,0 +1,526 @@
+//! A model of 1.8 chunk columns, turned into the data of `ChunkData` and
+//! `MapChunkBulk` and parsed back from it.
+//!
+//! Blocks are stored as the 1.8 block state, `id << 4 | metadata`, and light
+//! as levels from 0 to 15.
+
+use std::io;
+
+use misc::prelude::ChunkPosition;
+
+use super::client_bound::{ChunkData, ChunkMeta, MapChunkBulk};
+
+/// Sections in a column, each 16 blocks high.
+pub const SECTION_COUNT: usize = 16;
+
+const BLOCKS_LEN: usize = 4096;
+const BLOCKS_BYTES: usize = BLOCKS_LEN * 2;
+pub(super) const LIGHT_BYTES: usize = BLOCKS_LEN / 2;
+const BIOMES_LEN: usize = 256;
+
+/// The biome of a new column, plains.
+pub const DEFAULT_BIOME: u8 = 1;
+
+/// How many bytes a column's data takes, for the sections of `mask`.
+pub fn data_len(mask: u16, ground_up_continuous: bool, sky_light: bool) -> usize {
+    let section_len = BLOCKS_BYTES + LIGHT_BYTES + if sky_light { LIGHT_BYTES } else { 0 };
+
+    mask.count_ones() as usize * section_len + if ground_up_continuous { BIOMES_LEN } else { 0 }
+}
+
+/// 16x16x16 blocks with their light.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkSection {
+    blocks: Vec<u16>,
+    block_light: Vec<u8>,
+    sky_light: Vec<u8>,
+}
+
+impl Default for ChunkSection {
+    /// Air, dark but under the open sky.
+    fn default() -> Self {
+        Self {
+            blocks: vec![0; BLOCKS_LEN],
+            block_light: vec![0; LIGHT_BYTES],
+            sky_light: vec![0xFF; LIGHT_BYTES],
+        }
+    }
+}
+
+impl ChunkSection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Panics if a coordinate is over 15.
+    pub fn block(&self, x: usize, y: usize, z: usize) -> u16 {
+        self.blocks[index(x, y, z)]
+    }
+
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, state: u16) {
+        self.blocks[index(x, y, z)] = state;
+    }
+
+    pub fn block_light(&self, x: usize, y: usize, z: usize) -> u8 {
+        get_nibble(&self.block_light, index(x, y, z))
+    }
+
+    pub fn set_block_light(&mut self, x: usize, y: usize, z: usize, level: u8) {
+        set_nibble(&mut self.block_light, index(x, y, z), level);
+    }
+
+    pub fn sky_light(&self, x: usize, y: usize, z: usize) -> u8 {
+        get_nibble(&self.sky_light, index(x, y, z))
+    }
+
+    pub fn set_sky_light(&mut self, x: usize, y: usize, z: usize, level: u8) {
+        set_nibble(&mut self.sky_light, index(x, y, z), level);
+    }
+
+    /// Whether every block is air, which columns don't send.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.iter().all(|block| *block == 0)
+    }
+}
+
+/// 16 sections stacked up to the build height, with the biome of every
+/// x and z.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkColumn {
+    sections: Vec<Option<ChunkSection>>,
+    biomes: Vec<u8>,
+}
+
+impl Default for ChunkColumn {
+    fn default() -> Self {
+        Self {
+            sections: vec![None; SECTION_COUNT],
+            biomes: vec![DEFAULT_BIOME; BIOMES_LEN],
+        }
+    }
+}
+
+impl ChunkColumn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `None` for a section that was never set, which is all air.
+    pub fn section(&self, y: usize) -> Option<&ChunkSection> {
+        self.sections[y].as_ref()
+    }
+
+    pub fn section_mut(&mut self, y: usize) -> Option<&mut ChunkSection> {
+        self.sections[y].as_mut()
+    }
+
+    /// The section at `y`, created when missing.
+    pub fn section_or_insert(&mut self, y: usize) -> &mut ChunkSection {
+        self.sections[y].get_or_insert_with(ChunkSection::new)
+    }
+
+    pub fn set_section(&mut self, y: usize, section: Option<ChunkSection>) {
+        self.sections[y] = section;
+    }
+
+    /// Panics if `x` or `z` is over 15, or `y` over 255.
+    pub fn block(&self, x: usize, y: usize, z: usize) -> u16 {
+        self.section(y >> 4)
+            .map_or(0, |section| section.block(x, y & 0x0F, z))
+    }
+
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, state: u16) {
+        match self.sections[y >> 4].as_mut() {
+            Some(section) => section.set_block(x, y & 0x0F, z, state),
+            // no need for a section to hold air
+            None if state == 0 => {}
+            None => self
+                .section_or_insert(y >> 4)
+                .set_block(x, y & 0x0F, z, state),
+        }
+    }
+
+    pub fn block_light(&self, x: usize, y: usize, z: usize) -> u8 {
+        self.section(y >> 4)
+            .map_or(0, |section| section.block_light(x, y & 0x0F, z))
+    }
+
+    pub fn set_block_light(&mut self, x: usize, y: usize, z: usize, level: u8) {
+        self.section_or_insert(y >> 4)
+            .set_block_light(x, y & 0x0F, z, level);
+    }
+
+    pub fn sky_light(&self, x: usize, y: usize, z: usize) -> u8 {
+        self.section(y >> 4)
+            .map_or(15, |section| section.sky_light(x, y & 0x0F, z))
+    }
+
+    pub fn set_sky_light(&mut self, x: usize, y: usize, z: usize, level: u8) {
+        self.section_or_insert(y >> 4)
+            .set_sky_light(x, y & 0x0F, z, level);
+    }
+
+    /// Panics if `x` or `z` is over 15.
+    pub fn biome(&self, x: usize, z: usize) -> u8 {
+        self.biomes[z << 4 | x]
+    }
+
+    pub fn set_biome(&mut self, x: usize, z: usize, biome: u8) {
+        self.biomes[z << 4 | x] = biome;
+    }
+
+    /// The sections holding anything but air.
+    pub fn primary_bit_mask(&self) -> u16 {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| matches!(section, Some(section) if !section.is_empty()))
+            .fold(0, |mask, (y, _)| mask | 1 << y)
+    }
+
+    /// The mask of a ground-up send, which keeps the bottom section of an
+    /// empty column since the client takes an empty mask as an unload.
+    fn ground_up_mask(&self) -> u16 {
+        match self.primary_bit_mask() {
+            0 => 1,
+            mask => mask,
+        }
+    }
+
+    /// The data of the sections in `mask` in the 1.8 layout: every section's
+    /// blocks, then their block light, their sky light if the dimension has
+    /// any, and with `ground_up_continuous` the biomes.
+    pub fn encode_data(&self, mask: u16, ground_up_continuous: bool, sky_light: bool) -> Vec<u8> {
+        let empty = ChunkSection::new();
+        let sections: Vec<&ChunkSection> = (0..SECTION_COUNT)
+            .filter(|y| mask & 1 << y != 0)
+            .map(|y| self.section(y).unwrap_or(&empty))
+            .collect();
+
+        let mut buf = Vec::with_capacity(data_len(mask, ground_up_continuous, sky_light));
+        for section in &sections {
+            for block in &section.blocks {
+                buf.extend_from_slice(&block.to_le_bytes());
+            }
+        }
+        for section in &sections {
+            buf.extend_from_slice(&section.block_light);
+        }
+        if sky_light {
+            for section in &sections {
+                buf.extend_from_slice(&section.sky_light);
+            }
+        }
+        if ground_up_continuous {
+            buf.extend_from_slice(&self.biomes);
+        }
+
+        buf
+    }
+
+    /// Reads the data of the sections in `mask` into the column.
+    ///
+    /// With `ground_up_continuous` the data describes the whole column, the
+    /// sections missing from `mask` are cleared and the biomes replaced.
+    pub fn decode_data(
+        &mut self,
+        data: &[u8],
+        mask: u16,
+        ground_up_continuous: bool,
+        sky_light: bool,
+    ) -> io::Result<()> {
+        let expected = data_len(mask, ground_up_continuous, sky_light);
+        if data.len() != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "expected {} bytes of chunk data for mask {:#06x}, got {}",
+                    expected,
+                    mask,
+                    data.len()
+                ),
+            ));
+        }
+
+        let ys: Vec<usize> = (0..SECTION_COUNT).filter(|y| mask & 1 << y != 0).collect();
+        let mut sections = vec![ChunkSection::new(); ys.len()];
+
+        let (blocks, mut rest) = data.split_at(ys.len() * BLOCKS_BYTES);
+        for (section, blocks) in sections.iter_mut().zip(blocks.chunks(BLOCKS_BYTES)) {
+            for (block, bytes) in section.blocks.iter_mut().zip(blocks.chunks(2)) {
+                *block = u16::from_le_bytes([bytes[0], bytes[1]]);
+            }
+        }
+        for section in sections.iter_mut() {
+            section.block_light.copy_from_slice(&rest[..LIGHT_BYTES]);
+            rest = &rest[LIGHT_BYTES..];
+        }
+        if sky_light {
+            for section in sections.iter_mut() {
+                section.sky_light.copy_from_slice(&rest[..LIGHT_BYTES]);
+                rest = &rest[LIGHT_BYTES..];
+            }
+        }
+
+        if ground_up_continuous {
+            self.biomes.copy_from_slice(rest);
+            self.sections = vec![None; SECTION_COUNT];
+        }
+        for (y, section) in ys.into_iter().zip(sections) {
+            self.sections[y] = Some(section);
+        }
+
+        Ok(())
+    }
+
+    /// The whole column, its non-empty sections and biomes.
+    ///
+    /// An empty column still sends its bottom section, not to be mistaken
+    /// for [`ChunkColumn::unload`].
+    pub fn to_chunk_data(&self, position: ChunkPosition, sky_light: bool) -> ChunkData {
+        let primary_bit_mask = self.ground_up_mask();
+
+        ChunkData {
+            position,
+            ground_up_continuous: true,
+            primary_bit_mask,
+            data: self.encode_data(primary_bit_mask, true, sky_light),
+        }
+    }
+
+    /// Only the sections in `mask`, sent even when empty to clear them on
+    /// the client.
+    pub fn to_section_update(
+        &self,
+        position: ChunkPosition,
+        mask: u16,
+        sky_light: bool,
+    ) -> ChunkData {
+        ChunkData {
+            position,
+            ground_up_continuous: false,
+            primary_bit_mask: mask,
+            data: self.encode_data(mask, false, sky_light),
+        }
+    }
+
+    /// Makes the client forget the column.
+    pub fn unload(position: ChunkPosition) -> ChunkData {
+        ChunkData {
+            position,
+            ground_up_continuous: true,
+            primary_bit_mask: 0,
+            data: Vec::new(),
+        }
+    }
+
+    /// A column sent as a whole, `sky_light` depending on the dimension as
+    /// the packet doesn't tell.
+    pub fn from_chunk_data(packet: &ChunkData, sky_light: bool) -> io::Result<Self> {
+        let mut column = Self::new();
+        column.decode_data(
+            &packet.data,
+            packet.primary_bit_mask,
+            packet.ground_up_continuous,
+            sky_light,
+        )?;
+        Ok(column)
+    }
+}
+
+/// Whole columns at once, as when a player joins.
+pub fn map_chunk_bulk<'a, I>(columns: I, sky_light: bool) -> MapChunkBulk
+where
+    I: IntoIterator<Item = (ChunkPosition, &'a ChunkColumn)>,
+{
+    let (meta, data) = columns
+        .into_iter()
+        .map(|(position, column)| {
+            let primary_bit_mask = column.ground_up_mask();
+            (
+                ChunkMeta {
+                    position,
+                    primary_bit_mask,
+                },
+                column.encode_data(primary_bit_mask, true, sky_light),
+            )
+        })
+        .unzip();
+
+    MapChunkBulk {
+        sky_light_sent: sky_light,
+        meta,
+        data,
+    }
+}
+
+/// The columns of a `MapChunkBulk`.
+pub fn columns_of_bulk(packet: &MapChunkBulk) -> io::Result<Vec<(ChunkPosition, ChunkColumn)>> {
+    packet
+        .meta
+        .iter()
+        .zip(&packet.data)
+        .map(|(meta, data)| {
+            let mut column = ChunkColumn::new();
+            column.decode_data(data, meta.primary_bit_mask, true, packet.sky_light_sent)?;
+            Ok((meta.position.clone(), column))
+        })
+        .collect()
+}
+
+pub(super) fn index(x: usize, y: usize, z: usize) -> usize {
+    assert!(
+        x < 16 && y < 16 && z < 16,
+        "{} {} {} is out of the section",
+        x,
+        y,
+        z
+    );
+    y << 8 | z << 4 | x
+}
+
+pub(super) fn get_nibble(nibbles: &[u8], index: usize) -> u8 {
+    match index & 1 {
+        0 => nibbles[index >> 1] & 0x0F,
+        _ => nibbles[index >> 1] >> 4,
+    }
+}
+
+pub(super) fn set_nibble(nibbles: &mut [u8], index: usize, value: u8) {
+    let byte = &mut nibbles[index >> 1];
+    *byte = match index & 1 {
+        0 => *byte & 0xF0 | value & 0x0F,
+        _ => *byte & 0x0F | value << 4,
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use misc::prelude::Vec2D;
+    use protocol_internal::{
+        DecodeContext, PacketDecoder, PacketEncoder, ProtocolVersion, ProtocolVersionEnum,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_blocks_and_light() {
+        let mut column = ChunkColumn::new();
+        assert_eq!(column.primary_bit_mask(), 0);
+
+        column.set_block(0, 0, 0, 7 << 4);
+        column.set_block(15, 255, 15, 35 << 4 | 14);
+        column.set_block(3, 100, 3, 0);
+        column.set_block_light(1, 64, 2, 14);
+        column.set_sky_light(2, 64, 1, 3);
+        column.set_biome(15, 0, 2);
+
+        assert_eq!(column.block(0, 0, 0), 7 << 4);
+        assert_eq!(column.block(15, 255, 15), 35 << 4 | 14);
+        assert_eq!(column.block(1, 1, 1), 0);
+        assert!(column.section(6).is_none());
+        assert_eq!(column.block_light(1, 64, 2), 14);
+        assert_eq!(column.block_light(2, 64, 1), 0);
+        assert_eq!(column.sky_light(2, 64, 1), 3);
+        assert_eq!(column.sky_light(1, 64, 2), 15);
+        assert_eq!(column.biome(15, 0), 2);
+
+        // the section holding only light is all air
+        assert_eq!(column.primary_bit_mask(), 1 | 1 << 15);
+    }
+
+    #[test]
+    fn test_encode_layout() {
+        let mut column = ChunkColumn::new();
+        column.set_block(1, 16, 0, 1 << 4 | 2);
+        column.set_block_light(0, 16, 0, 5);
+
+        let data = column.encode_data(0b10, true, true);
+        assert_eq!(data.len(), data_len(0b10, true, true));
+        assert_eq!(&data[..4], [0, 0, 0x12, 0]);
+        assert_eq!(data[BLOCKS_BYTES], 5);
+        assert_eq!(data[BLOCKS_BYTES + LIGHT_BYTES], 0xFF);
+        assert_eq!(data[data.len() - 1], DEFAULT_BIOME);
+
+        assert_eq!(column.encode_data(0b10, false, false).len(), 10240);
+    }
+
+    #[test]
+    fn test_chunk_data() {
+        let mut column = ChunkColumn::new();
+        for y in 0..70 {
+            column.set_block(y % 16, y, 4, (y as u16) << 4);
+        }
+        column.set_sky_light(8, 69, 8, 9);
+
+        let packet = column.to_chunk_data(Vec2D::new(-1, 3), true);
+        assert_eq!(packet.primary_bit_mask, 0b11111);
+        assert_eq!(ChunkColumn::from_chunk_data(&packet, true).unwrap(), column);
+        assert!(ChunkColumn::from_chunk_data(&packet, false).is_err());
+
+        // a section update leaves the other sections alone
+        let mut received = column.clone();
+        column.set_block(0, 200, 0, 1 << 4);
+        column.set_block(0, 0, 4, 0);
+        let update = column.to_section_update(Vec2D::new(-1, 3), 1 | 1 << 12, true);
+        received
+            .decode_data(&update.data, update.primary_bit_mask, false, true)
+            .unwrap();
+        assert_eq!(received.block(0, 200, 0), 1 << 4);
+        assert_eq!(received.block(0, 0, 4), 0);
+        assert_eq!(received.block(1, 17, 4), 17 << 4);
+
+        let unload = ChunkColumn::unload(Vec2D::new(-1, 3));
+        assert!(unload.ground_up_continuous && unload.data.is_empty());
+    }
+
+    #[test]
+    fn test_empty_column() {
+        let mut column = ChunkColumn::new();
+        column.set_biome(3, 3, 8);
+        assert_eq!(column.primary_bit_mask(), 0);
+
+        // an empty mask would unload the column on the client
+        let packet = column.to_chunk_data(Vec2D::new(0, 0), true);
+        assert!(packet.ground_up_continuous);
+        assert_eq!(packet.primary_bit_mask, 1);
+        assert_eq!(packet.data.len(), data_len(1, true, true));
+
+        let received = ChunkColumn::from_chunk_data(&packet, true).unwrap();
+        assert_eq!(received.primary_bit_mask(), 0);
+        assert_eq!(received.biome(3, 3), 8);
+
+        let bulk = map_chunk_bulk(vec![(Vec2D::new(0, 0), &column)], true);
+        assert_eq!(bulk.meta[0].primary_bit_mask, 1);
+    }
+
+    #[test]
+    fn test_map_chunk_bulk() {
+        let mut first = ChunkColumn::new();
+        first.set_block(0, 0, 0, 7 << 4);
+        let mut second = ChunkColumn::new();
+        second.set_block(0, 40, 0, 1 << 4);
+        second.set_biome(0, 0, 8);
+
+        let bulk = map_chunk_bulk(
+            vec![(Vec2D::new(0, 0), &first), (Vec2D::new(0, 1), &second)],
+            false,
+        );
+        let version: ProtocolVersion = ProtocolVersionEnum::V1_8.into();
+        let mut buf = Vec::new();
+        bulk.encode(&mut buf, &version).unwrap();
+        assert_eq!(buf.len(), bulk.calculate_len(&version));
+        // the id, sky light, count and 2 metas, then the data of 2 sections
+        assert_eq!(buf.len(), 1 + 1 + 1 + 2 * 10 + 2 * (10240 + 256));
+
+        let mut src = DecodeContext::from(&buf[..]);
+        let bulk = <MapChunkBulk as PacketDecoder>::decode(&mut src, &version).unwrap();
+        assert!(src.is_empty());
+
+        let columns = columns_of_bulk(&bulk).unwrap();
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0].1, first);
+        assert_eq!(columns[1].0, Vec2D::new(0, 1));
+        assert_eq!(columns[1].1, second);
+    }
+}
@@ -2,6 +2,8 @@ use std::borrow::Cow;
 
 use crate::packets::macros::{packet_enum, proto_enum};
 
+use super::server_bound::PlayerAbilitiesFlags;
+
 packet_enum! {
     ClientBound<'a> {
         0x00 => KeepAlive {
@@ -21,10 +23,35 @@ packet_enum! {
             json_data: ChatComponent<'a>,
             position: ChatPosition
         },
+        0x03 => TimeUpdate {
+            world_age: i64,
+            /// Negative to stop the time at its absolute value.
+            time_of_day: i64
+        },
+        0x04 => EntityEquipment {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            slot: EquipmentSlot,
+            item: Slot
+        },
         0x05 => SpawnPosition {
             #[protocol_field(position)]
             location: EntityLocation
         },
+        0x06 => UpdateHealth {
+            /// Dead at `0`, full at `20`.
+            health: f32,
+            #[protocol_field(varnum)]
+            food: i32,
+            food_saturation: f32
+        },
+        0x07 => Respawn<'a> {
+            /// The `Dimension`, sent as an int unlike in `JoinGame`.
+            dimension: i32,
+            difficulty: Difficulty,
+            game_mode: GameMode,
+            level_type: Cow<'a, str>
+        },
         0x08 => PlayerPositionAndLook {
             entity_location: EntityLocation,
             flags: PlayerPositionAndLookFlags
@@ -37,12 +64,62 @@ packet_enum! {
             entity_id: i32,
             animation: AnimationAction
         },
+        0x0C => SpawnPlayer {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            player_uuid: Uuid,
+            position: Vec3D<FixedPoint<i32>>,
+            yaw: Angle,
+            pitch: Angle,
+            current_item: i16,
+            metadata: Metadata
+        },
         0x0D => CollectItem {
             #[protocol_field(varnum)]
             collected_entity_id: i32,
             #[protocol_field(varnum)]
             collector_entity_id: i32
         },
+        0x0E => SpawnObject {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            object_type: i8,
+            position: Vec3D<FixedPoint<i32>>,
+            pitch: Angle,
+            yaw: Angle,
+            data: ObjectData
+        },
+        0x0F => SpawnMob {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            mob_type: u8,
+            position: Vec3D<FixedPoint<i32>>,
+            yaw: Angle,
+            pitch: Angle,
+            head_pitch: Angle,
+            velocity: Vec3D<i16>,
+            metadata: Metadata
+        },
+        0x10 => SpawnPainting {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            #[protocol_field(range(max = 13))]
+            title: String,
+            #[protocol_field(position)]
+            location: BlockPosition,
+            direction: PaintingDirection
+        },
+        0x11 => SpawnExperienceOrb {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            position: Vec3D<FixedPoint<i32>>,
+            count: i16
+        },
+        0x12 => EntityVelocity {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            velocity: Vec3D<i16>
+        },
         0x13 => DestroyEntities {
             #[protocol_field(varnum)]
             entities: Vec<i32>
@@ -51,10 +128,74 @@ packet_enum! {
             #[protocol_field(varnum)]
             entity_id: i32
         },
+        0x15 => EntityRelativeMove {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            delta: Vec3D<FixedPoint<i8>>,
+            on_ground: bool
+        },
+        0x16 => EntityLook {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            yaw: Angle,
+            pitch: Angle,
+            on_ground: bool
+        },
+        0x17 => EntityLookAndRelativeMove {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            delta: Vec3D<FixedPoint<i8>>,
+            yaw: Angle,
+            pitch: Angle,
+            on_ground: bool
+        },
+        0x18 => EntityTeleport {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            position: Vec3D<FixedPoint<i32>>,
+            yaw: Angle,
+            pitch: Angle,
+            on_ground: bool
+        },
+        0x19 => EntityHeadLook {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            head_yaw: Angle
+        },
+        0x1A => EntityStatus {
+            entity_id: i32,
+            entity_status: i8
+        },
+        0x1B => AttachEntity {
+            entity_id: i32,
+            vehicle_id: i32,
+            leash: bool
+        },
+        0x1C => EntityMetadata {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            metadata: Metadata
+        },
+        0x1D => EntityEffect {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            effect_id: i8,
+            amplifier: i8,
+            #[protocol_field(varnum)]
+            duration: i32,
+            hide_particles: bool
+        },
+        0x1E => RemoveEntityEffect {
+            #[protocol_field(varnum)]
+            entity_id: i32,
+            effect_id: i8
+        },
+        0x20 => EntityProperties,
         0x21 => ChunkData {
             position: ChunkPosition,
             ground_up_continuous: bool,
             primary_bit_mask: u16,
+            /// See [`super::chunk`] to build and read it.
             data: Vec<u8>
         },
         0x22 => MultiBlockChange {
@@ -65,6 +206,7 @@ packet_enum! {
                 pub struct MultiBlockChangeRecord {
                     pub horizontal_position: u8,
                     pub y_coordinate: u8,
+                    /// Resolved to a block state by a `BlockRegistry`.
                     #[protocol_field(varnum)]
                     pub block_id: i32,
                 }
@@ -73,6 +215,7 @@ packet_enum! {
         0x23 => BlockChange {
             #[protocol_field(position)]
             location: BlockPosition,
+            // resolved to a block state by a `BlockRegistry`
             #[protocol_field(varnum)]
             block_id: i32
         },
@@ -90,17 +233,30 @@ packet_enum! {
             location: BlockPosition,
             destroy_stage: i8
         },
-        0x26 => MapChunkBulk {
-            sky_light_sent: bool,
-            meta: Vec<ChunkMeta>,
-            data: Vec<Vec<u8>>;
-            items {
-                #[derive(Clone, Debug, Default, protocol_derive::ProtocolSupport)]
-                pub struct ChunkMeta {
-                    pub position: ChunkPosition,
-                    pub primary_bit_mask: u16,
-                }
-            }
+        0x26 => MapChunkBulk,
+        0x27 => Explosion,
+        0x28 => Effect {
+            /// Like `1005` to play a record or `2001` for a block breaking.
+            effect_id: i32,
+            #[protocol_field(position)]
+            location: BlockPosition,
+            data: i32,
+            disable_relative_volume: bool
+        },
+        0x29 => SoundEffect<'a> {
+            /// Like `random.click`.
+            sound_name: Cow<'a, str>,
+            /// In 1/8 of a block.
+            position: Vec3D<i32>,
+            /// `1.0` is full volume, more is heard further away.
+            volume: f32,
+            /// `63` plays the sound at its normal pitch.
+            pitch: u8
+        },
+        0x2A => Particle,
+        0x2B => ChangeGameState {
+            reason: GameStateReason,
+            value: f32
         },
         0x2D => OpenWindow<'a> {
             window_id: u8,
@@ -111,7 +267,44 @@ packet_enum! {
         0x2E => CloseWindow {
             window_id: u8
         },
+        0x2F => SetSlot {
+            window_id: i8,
+            slot: i16,
+            slot_data: Slot
+        },
+        0x30 => WindowItems,
+        0x31 => WindowProperty {
+            window_id: u8,
+            property: i16,
+            value: i16
+        },
+        0x32 => ConfirmTransaction {
+            window_id: i8,
+            action_number: i16,
+            accepted: bool
+        },
+        0x33 => UpdateSign<'a>,
+        0x34 => Map,
+        0x35 => UpdateBlockEntity {
+            #[protocol_field(position)]
+            location: BlockPosition,
+            action: BlockEntityAction,
+            nbt: OptionalNbt
+        },
+        0x36 => SignEditorOpen {
+            #[protocol_field(position)]
+            location: BlockPosition
+        },
+        0x37 => Statistics {
+            statistics: Vec<Statistic>
+        },
         0x38 => PlayerListItem<'a>,
+        0x39 => PlayerAbilities {
+            flags: PlayerAbilitiesFlags,
+            flying_speed: f32,
+            /// Modified by the walking speed.
+            field_of_view: f32
+        },
         0x3B => ScoreboardObjective<'a> {
             objective_name: Cow<'a, str>,
             mode: ScoreboardObjectiveMode<'a>;
@@ -148,16 +341,27 @@ packet_enum! {
         },
         0x3F => PluginMessage<'a> {
             channel: Cow<'a, str>,
-            #[protocol_field(dynarray)]
-            data: Vec<u8>
+            data: RemainingBytes
         },
         0x40 => Disconnect<'a> {
             reason: ChatComponent<'a>
         },
+        0x43 => Camera {
+            // the entity to see the world through, the player itself to stop
+            // spectating
+            #[protocol_field(varnum)]
+            camera_id: i32
+        },
+        0x44 => WorldBorder,
         0x45 => Title<'a>,
         0x47 => PlayerListHeaderAndFooter<'a> {
             header: ChatComponent<'a>,
             footer: ChatComponent<'a>
+        },
+        0x48 => ResourcePackSend<'a> {
+            url: Cow<'a, str>,
+            /// The SHA-1 of the pack in lowercase hex, used to cache it.
+            hash: Cow<'a, str>
         }
     }
 }
@@ -179,6 +383,218 @@ impl Default for PlayerPositionAndLookFlags {
     }
 }
 
+proto_enum! {
+    EquipmentSlot (i16) {
+        Held = 0,
+        Boots = 1,
+        Leggings = 2,
+        Chestplate = 3,
+        Helmet = 4
+    }
+    default Self::Held
+}
+
+proto_enum! {
+    PaintingDirection (u8) {
+        North = 0,
+        West = 1,
+        South = 2,
+        East = 3
+    }
+    default Self::North
+}
+
+/// The `data` of `SpawnObject`, whose meaning depends on the object type.
+///
+/// `velocity` is only sent when `data` is not `0`, and decoded as zero
+/// otherwise.
+#[derive(Clone, Debug, Default)]
+pub struct ObjectData {
+    pub data: i32,
+    /// In 1/8000 of a block per tick.
+    pub velocity: Vec3D<i16>,
+}
+
+impl ProtocolSupportEncoder for ObjectData {
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        match self.data {
+            0 => self.data.calculate_len(version),
+            _ => self.data.calculate_len(version) + self.velocity.calculate_len(version),
+        }
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        self.data.encode(dst, version)?;
+        match self.data {
+            0 => Ok(()),
+            _ => self.velocity.encode(dst, version),
+        }
+    }
+}
+
+impl ProtocolSupportDecoder for ObjectData {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let data = i32::decode(src, version)?;
+        let velocity = match data {
+            0 => Vec3D::default(),
+            _ => Vec3D::decode(src, version)?,
+        };
+
+        Ok(Self { data, velocity })
+    }
+}
+
+/// Every slot of a window, the player's inventory included.
+#[derive(Clone, Debug, Default)]
+pub struct WindowItems {
+    pub window_id: u8,
+    pub slots: Vec<Slot>,
+}
+
+impl ProtocolSupportEncoder for WindowItems {
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        self.window_id.calculate_len(version)
+            + (self.slots.len() as i16).calculate_len(version)
+            + self
+                .slots
+                .iter()
+                .map(|slot| slot.calculate_len(version))
+                .sum::<usize>()
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        if self.slots.len() > i16::MAX as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} slots do not fit in a window", self.slots.len()),
+            ));
+        }
+
+        self.window_id.encode(dst, version)?;
+        // a short, unlike the varint every other array is prefixed with
+        (self.slots.len() as i16).encode(dst, version)?;
+        for slot in &self.slots {
+            slot.encode(dst, version)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProtocolSupportDecoder for WindowItems {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let window_id = u8::decode(src, version)?;
+        let len = i16::decode(src, version)?;
+        if len < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("negative window slot count {}", len),
+            ));
+        }
+
+        let slots = ::protocol_internal::FixedVec::decode(src, version, len as usize)?;
+
+        Ok(Self { window_id, slots })
+    }
+}
+
+/// The attributes of an entity, like its max health or movement speed.
+#[derive(Clone, Debug, Default)]
+pub struct EntityProperties {
+    pub entity_id: i32,
+    pub properties: Vec<EntityProperty>,
+}
+
+impl ProtocolSupportEncoder for EntityProperties {
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        VarNum::<i32>::calculate_len(&self.entity_id)
+            + (self.properties.len() as i32).calculate_len(version)
+            + self
+                .properties
+                .iter()
+                .map(|property| property.calculate_len(version))
+                .sum::<usize>()
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        VarNum::<i32>::encode(&self.entity_id, dst)?;
+        // an int, unlike the varint every other array is prefixed with
+        (self.properties.len() as i32).encode(dst, version)?;
+        for property in &self.properties {
+            property.encode(dst, version)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProtocolSupportDecoder for EntityProperties {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let entity_id = VarNum::<i32>::decode(src)?;
+        let len = i32::decode(src, version)?;
+        if len < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("negative entity property count {}", len),
+            ));
+        }
+
+        let properties = ::protocol_internal::FixedVec::decode(src, version, len as usize)?;
+
+        Ok(Self {
+            entity_id,
+            properties,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, protocol_derive::ProtocolSupport)]
+pub struct EntityProperty {
+    /// Like `generic.maxHealth`.
+    #[protocol_field(range(max = 64))]
+    pub key: String,
+    pub value: f64,
+    pub modifiers: Vec<AttributeModifier>,
+}
+
+#[derive(Clone, Debug, Default, protocol_derive::ProtocolSupport)]
+pub struct AttributeModifier {
+    pub uuid: Uuid,
+    pub amount: f64,
+    pub operation: ModifierOperation,
+}
+
+proto_enum! {
+    ModifierOperation (u8) {
+        Add = 0,
+        AddPercent = 1,
+        Multiply = 2
+    }
+    default Self::Add
+}
+
+/// Changes to the tab list, every entry keyed by the player's uuid.
 #[derive(Clone, Debug)]
 pub enum PlayerListItem<'a> {
     AddPlayer(Vec<(Uuid, PlayerListItemAddPlayer<'a>)>),
@@ -188,26 +604,160 @@ pub enum PlayerListItem<'a> {
     RemovePlayer(Vec<Uuid>),
 }
 
+impl<'a> PlayerListItem<'a> {
+    fn action(&self) -> i32 {
+        match self {
+            Self::AddPlayer(_) => 0,
+            Self::UpdateGameMode(_) => 1,
+            Self::UpdateLatency(_) => 2,
+            Self::UpdateDisplayName(_) => 3,
+            Self::RemovePlayer(_) => 4,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::AddPlayer(players) => players.len(),
+            Self::UpdateGameMode(players) => players.len(),
+            Self::UpdateLatency(players) => players.len(),
+            Self::UpdateDisplayName(players) => players.len(),
+            Self::RemovePlayer(players) => players.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 impl<'a> ProtocolSupportEncoder for PlayerListItem<'a> {
-    fn calculate_len(&self, _version: &::protocol_internal::ProtocolVersion) -> usize {
-        todo!()
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        fn entries<T: ProtocolSupportEncoder>(
+            players: &[(Uuid, T)],
+            version: &::protocol_internal::ProtocolVersion,
+        ) -> usize {
+            players
+                .iter()
+                .map(|(uuid, entry)| uuid.calculate_len(version) + entry.calculate_len(version))
+                .sum()
+        }
+
+        let entries = match self {
+            Self::AddPlayer(players) => entries(players, version),
+            Self::UpdateGameMode(players) => entries(players, version),
+            Self::UpdateLatency(players) => players
+                .iter()
+                .map(|(uuid, ping)| {
+                    uuid.calculate_len(version) + VarNum::<i32>::calculate_len(ping)
+                })
+                .sum(),
+            Self::UpdateDisplayName(players) => entries(players, version),
+            Self::RemovePlayer(players) => {
+                players.iter().map(|uuid| uuid.calculate_len(version)).sum()
+            }
+        };
+
+        VarNum::<i32>::calculate_len(&self.action())
+            + VarNum::<i32>::calculate_len(&(self.len() as i32))
+            + entries
     }
 
     fn encode<W: std::io::Write>(
         &self,
-        _dst: &mut W,
-        _version: &::protocol_internal::ProtocolVersion,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
     ) -> std::io::Result<()> {
-        todo!()
+        fn entries<T: ProtocolSupportEncoder, W: std::io::Write>(
+            players: &[(Uuid, T)],
+            dst: &mut W,
+            version: &::protocol_internal::ProtocolVersion,
+        ) -> std::io::Result<()> {
+            for (uuid, entry) in players {
+                uuid.encode(dst, version)?;
+                entry.encode(dst, version)?;
+            }
+            Ok(())
+        }
+
+        VarNum::<i32>::encode(&self.action(), dst)?;
+        VarNum::<i32>::encode(&(self.len() as i32), dst)?;
+
+        match self {
+            Self::AddPlayer(players) => entries(players, dst, version),
+            Self::UpdateGameMode(players) => entries(players, dst, version),
+            Self::UpdateLatency(players) => {
+                for (uuid, ping) in players {
+                    uuid.encode(dst, version)?;
+                    VarNum::<i32>::encode(ping, dst)?;
+                }
+                Ok(())
+            }
+            Self::UpdateDisplayName(players) => entries(players, dst, version),
+            Self::RemovePlayer(players) => {
+                for uuid in players {
+                    uuid.encode(dst, version)?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
 impl<'a> ProtocolSupportDecoder for PlayerListItem<'a> {
     fn decode<R: std::io::Read>(
-        _src: &mut R,
-        _version: &protocol_internal::ProtocolVersion,
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
     ) -> std::io::Result<Self> {
-        todo!()
+        fn entries<R: std::io::Read, T>(
+            src: &mut ::protocol_internal::DecodeContext<R>,
+            version: &protocol_internal::ProtocolVersion,
+            len: usize,
+            mut decode: impl FnMut(&mut ::protocol_internal::DecodeContext<R>) -> std::io::Result<T>,
+        ) -> std::io::Result<Vec<(Uuid, T)>> {
+            let mut players = Vec::new();
+            for _ in 0..len {
+                let uuid = Uuid::decode(src, version)?;
+                players.push((uuid, decode(src)?));
+            }
+            Ok(players)
+        }
+
+        let action = VarNum::<i32>::decode(src)?;
+        let len = VarNum::<i32>::decode(src)?;
+        if len < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("negative player list item count {}", len),
+            ));
+        }
+        let len = len as usize;
+
+        Ok(match action {
+            0 => Self::AddPlayer(entries(src, version, len, |src| {
+                PlayerListItemAddPlayer::decode(src, version)
+            })?),
+            1 => Self::UpdateGameMode(entries(src, version, len, |src| {
+                GameMode::decode(src, version)
+            })?),
+            2 => Self::UpdateLatency(entries(src, version, len, |src| {
+                VarNum::<i32>::decode(src)
+            })?),
+            3 => Self::UpdateDisplayName(entries(src, version, len, |src| {
+                Option::decode(src, version)
+            })?),
+            4 => Self::RemovePlayer(
+                entries(src, version, len, |_| Ok(()))?
+                    .into_iter()
+                    .map(|(uuid, _)| uuid)
+                    .collect(),
+            ),
+            action => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("unknown player list item action {}", action),
+                ))
+            }
+        })
     }
 }
 
@@ -215,10 +765,12 @@ impl<'a> ProtocolSupportDecoder for PlayerListItem<'a> {
 pub struct PlayerListItemAddPlayer<'a> {
     pub name: Cow<'a, str>,
     pub properties: Vec<Property>,
+    /// A varint on the wire, which takes a single byte like the game mode's
+    /// own encoding.
     pub game_mode: GameMode,
     #[protocol_field(varnum)]
     pub ping: i32,
-    pub display_name: Option<Cow<'a, str>>,
+    pub display_name: Option<ChatComponent<'a>>,
 }
 
 proto_enum! {
@@ -315,3 +867,1094 @@ proto_enum! {
     }
     default Self::Reset
 }
+
+/// Whole chunk columns, their data following each other without a length
+/// as the primary bit mask tells it.
+///
+/// See [`super::chunk`] to build and read the data.
+#[derive(Clone, Debug, Default)]
+pub struct MapChunkBulk {
+    /// Whether the data holds sky light, in the overworld.
+    pub sky_light_sent: bool,
+    pub meta: Vec<ChunkMeta>,
+    /// The data of every column in `meta`, in the same order.
+    pub data: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, Default, protocol_derive::ProtocolSupport)]
+pub struct ChunkMeta {
+    pub position: ChunkPosition,
+    pub primary_bit_mask: u16,
+}
+
+impl ProtocolSupportEncoder for MapChunkBulk {
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        self.sky_light_sent.calculate_len(version)
+            + self.meta.calculate_len(version)
+            + self.data.iter().map(Vec::len).sum::<usize>()
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        if self.meta.len() != self.data.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "{} chunk metas for {} chunks",
+                    self.meta.len(),
+                    self.data.len()
+                ),
+            ));
+        }
+
+        self.sky_light_sent.encode(dst, version)?;
+        self.meta.encode(dst, version)?;
+        for data in &self.data {
+            dst.write_all(data)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProtocolSupportDecoder for MapChunkBulk {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let sky_light_sent = bool::decode(src, version)?;
+        let len = VarNum::<i32>::decode(src)?;
+        if len < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("negative chunk count {}", len),
+            ));
+        }
+
+        let meta: Vec<ChunkMeta> =
+            ::protocol_internal::FixedVec::decode(src, version, len as usize)?;
+
+        let mut data = Vec::with_capacity(meta.len());
+        for chunk in &meta {
+            let len = super::chunk::data_len(chunk.primary_bit_mask, true, sky_light_sent);
+            if len > src.remaining() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("chunk data of {} bytes over the packet", len),
+                ));
+            }
+
+            let mut buf = vec![0; len];
+            std::io::Read::read_exact(src, &mut buf)?;
+            data.push(buf);
+        }
+
+        Ok(Self {
+            sky_light_sent,
+            meta,
+            data,
+        })
+    }
+}
+
+/// An explosion at `position`, the client destroying the blocks and pushing
+/// the player itself.
+#[derive(Clone, Debug, Default)]
+pub struct Explosion {
+    pub position: Vec3D<f32>,
+    pub radius: f32,
+    /// The blocks destroyed, relative to `position`.
+    pub records: Vec<Vec3D<i8>>,
+    /// Added to the player's velocity.
+    pub player_motion: Vec3D<f32>,
+}
+
+impl ProtocolSupportEncoder for Explosion {
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        self.position.calculate_len(version)
+            + self.radius.calculate_len(version)
+            + (self.records.len() as i32).calculate_len(version)
+            + self.records.len() * 3
+            + self.player_motion.calculate_len(version)
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        self.position.encode(dst, version)?;
+        self.radius.encode(dst, version)?;
+        (self.records.len() as i32).encode(dst, version)?;
+        for record in &self.records {
+            record.encode(dst, version)?;
+        }
+        self.player_motion.encode(dst, version)
+    }
+}
+
+impl ProtocolSupportDecoder for Explosion {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let position = Vec3D::decode(src, version)?;
+        let radius = f32::decode(src, version)?;
+        let len = i32::decode(src, version)?;
+        if len < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("negative explosion record count {}", len),
+            ));
+        }
+
+        let records = ::protocol_internal::FixedVec::decode(src, version, len as usize)?;
+
+        Ok(Self {
+            position,
+            radius,
+            records,
+            player_motion: Vec3D::decode(src, version)?,
+        })
+    }
+}
+
+proto_enum! {
+    ParticleKind (i32) {
+        Explode = 0,
+        LargeExplode = 1,
+        HugeExplosion = 2,
+        FireworksSpark = 3,
+        Bubble = 4,
+        Splash = 5,
+        Wake = 6,
+        Suspended = 7,
+        DepthSuspend = 8,
+        Crit = 9,
+        MagicCrit = 10,
+        Smoke = 11,
+        LargeSmoke = 12,
+        Spell = 13,
+        InstantSpell = 14,
+        MobSpell = 15,
+        MobSpellAmbient = 16,
+        WitchMagic = 17,
+        DripWater = 18,
+        DripLava = 19,
+        AngryVillager = 20,
+        HappyVillager = 21,
+        TownAura = 22,
+        Note = 23,
+        Portal = 24,
+        EnchantmentTable = 25,
+        Flame = 26,
+        Lava = 27,
+        Footstep = 28,
+        Cloud = 29,
+        RedDust = 30,
+        SnowballPoof = 31,
+        SnowShovel = 32,
+        Slime = 33,
+        Heart = 34,
+        Barrier = 35,
+        IconCrack = 36,
+        BlockCrack = 37,
+        BlockDust = 38,
+        Droplet = 39,
+        Take = 40,
+        MobAppearance = 41
+    }
+    default Self::Explode
+}
+
+impl ParticleKind {
+    /// How many varints follow the particle: the item id and damage for
+    /// `IconCrack`, the block state for `BlockCrack` and `BlockDust`.
+    pub fn data_len(self) -> usize {
+        match self {
+            Self::IconCrack => 2,
+            Self::BlockCrack | Self::BlockDust => 1,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Particle {
+    pub particle: ParticleKind,
+    /// Shown up to 65536 blocks away instead of 256.
+    pub long_distance: bool,
+    pub position: Vec3D<f32>,
+    /// Multiplied by a random gaussian to spread the particles.
+    pub offset: Vec3D<f32>,
+    /// The speed of most particles.
+    pub particle_data: f32,
+    pub count: i32,
+    /// As many values as [`ParticleKind::data_len`].
+    pub data: Vec<i32>,
+}
+
+impl ProtocolSupportEncoder for Particle {
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        self.particle.calculate_len(version)
+            + self.long_distance.calculate_len(version)
+            + self.position.calculate_len(version)
+            + self.offset.calculate_len(version)
+            + self.particle_data.calculate_len(version)
+            + self.count.calculate_len(version)
+            + self
+                .data
+                .iter()
+                .map(VarNum::<i32>::calculate_len)
+                .sum::<usize>()
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        if self.data.len() != self.particle.data_len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "{:?} takes {} data values, got {}",
+                    self.particle,
+                    self.particle.data_len(),
+                    self.data.len()
+                ),
+            ));
+        }
+
+        self.particle.encode(dst, version)?;
+        self.long_distance.encode(dst, version)?;
+        self.position.encode(dst, version)?;
+        self.offset.encode(dst, version)?;
+        self.particle_data.encode(dst, version)?;
+        self.count.encode(dst, version)?;
+        for value in &self.data {
+            VarNum::<i32>::encode(value, dst)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProtocolSupportDecoder for Particle {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let particle = ParticleKind::decode(src, version)?;
+        let long_distance = bool::decode(src, version)?;
+        let position = Vec3D::decode(src, version)?;
+        let offset = Vec3D::decode(src, version)?;
+        let particle_data = f32::decode(src, version)?;
+        let count = i32::decode(src, version)?;
+        let data = (0..particle.data_len())
+            .map(|_| VarNum::<i32>::decode(src))
+            .collect::<std::io::Result<_>>()?;
+
+        Ok(Self {
+            particle,
+            long_distance,
+            position,
+            offset,
+            particle_data,
+            count,
+            data,
+        })
+    }
+}
+
+proto_enum! {
+    GameStateReason (u8) {
+        InvalidBed = 0,
+        EndRaining = 1,
+        BeginRaining = 2,
+        // the value is the GameMode
+        ChangeGameMode = 3,
+        EnterCredits = 4,
+        // 0 shows the welcome screen, 101 to 104 the controls
+        DemoMessage = 5,
+        ArrowHitPlayer = 6,
+        // the sky darkness of rain, from 0 to 1
+        FadeValue = 7,
+        // the sky darkness of a thunderstorm, from 0 to 1
+        FadeTime = 8,
+        // the elder guardian appearing in front of the player
+        MobAppearance = 10
+    }
+    default Self::InvalidBed
+}
+
+/// The text of a sign.
+///
+/// The lines are boxed, four chat components inline would make every
+/// [`ClientBound`] packet several times larger.
+#[derive(Clone, Debug, Default)]
+pub struct UpdateSign<'a> {
+    pub location: BlockPosition,
+    pub lines: Box<[ChatComponent<'a>; 4]>,
+}
+
+impl<'a> ProtocolSupportEncoder for UpdateSign<'a> {
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        8 + self
+            .lines
+            .iter()
+            .map(|line| line.calculate_len(version))
+            .sum::<usize>()
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        ::protocol_internal::ProtocolPositionSupport::encode(&self.location, dst)?;
+        for line in self.lines.iter() {
+            line.encode(dst, version)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> ProtocolSupportDecoder for UpdateSign<'a> {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let location = ::protocol_internal::ProtocolPositionSupport::decode(src)?;
+        let lines = [
+            ChatComponent::decode(src, version)?,
+            ChatComponent::decode(src, version)?,
+            ChatComponent::decode(src, version)?,
+            ChatComponent::decode(src, version)?,
+        ];
+
+        Ok(Self {
+            location,
+            lines: Box::new(lines),
+        })
+    }
+}
+
+/// A map item's icons and, optionally, an update of its colors.
+#[derive(Clone, Debug, Default)]
+pub struct Map {
+    /// The damage of the map item.
+    pub map_id: i32,
+    /// From `0` for 1 block per pixel to `4` for 16.
+    pub scale: i8,
+    pub icons: Vec<MapIcon>,
+    pub data: Option<MapData>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MapIcon {
+    /// From `0` to `15`, like `0` for a white arrow.
+    pub kind: u8,
+    /// From `0` to `15`, in 22.5 degree steps.
+    pub direction: u8,
+    pub x: i8,
+    pub z: i8,
+}
+
+/// A rectangle of colors to update on the map.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MapData {
+    pub columns: u8,
+    pub rows: u8,
+    pub x: u8,
+    pub z: u8,
+    /// `columns * rows` colors, row by row.
+    pub data: Vec<u8>,
+}
+
+impl ProtocolSupportEncoder for Map {
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        VarNum::<i32>::calculate_len(&self.map_id)
+            + self.scale.calculate_len(version)
+            + VarNum::<i32>::calculate_len(&(self.icons.len() as i32))
+            + self.icons.len() * 3
+            + match &self.data {
+                Some(data) => 4 + data.data.calculate_len(version),
+                None => 1,
+            }
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        VarNum::<i32>::encode(&self.map_id, dst)?;
+        self.scale.encode(dst, version)?;
+
+        VarNum::<i32>::encode(&(self.icons.len() as i32), dst)?;
+        for icon in &self.icons {
+            (icon.kind << 4 | icon.direction & 0x0F).encode(dst, version)?;
+            icon.x.encode(dst, version)?;
+            icon.z.encode(dst, version)?;
+        }
+
+        let data = match &self.data {
+            Some(data) => data,
+            None => return 0u8.encode(dst, version),
+        };
+        if data.columns == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "map data without columns",
+            ));
+        }
+
+        data.columns.encode(dst, version)?;
+        data.rows.encode(dst, version)?;
+        data.x.encode(dst, version)?;
+        data.z.encode(dst, version)?;
+        data.data.encode(dst, version)
+    }
+}
+
+impl ProtocolSupportDecoder for Map {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let map_id = VarNum::<i32>::decode(src)?;
+        let scale = i8::decode(src, version)?;
+
+        let len = VarNum::<i32>::decode(src)?;
+        if len < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("negative map icon count {}", len),
+            ));
+        }
+
+        let mut icons = Vec::new();
+        for _ in 0..len {
+            let kind_and_direction = u8::decode(src, version)?;
+            icons.push(MapIcon {
+                kind: kind_and_direction >> 4,
+                direction: kind_and_direction & 0x0F,
+                x: i8::decode(src, version)?,
+                z: i8::decode(src, version)?,
+            });
+        }
+
+        let data = match u8::decode(src, version)? {
+            0 => None,
+            columns => Some(MapData {
+                columns,
+                rows: u8::decode(src, version)?,
+                x: u8::decode(src, version)?,
+                z: u8::decode(src, version)?,
+                data: <Vec<u8> as ::protocol_internal::RangeValidatedSupport>::decode(
+                    src,
+                    version,
+                    0,
+                    128 * 128,
+                )?,
+            }),
+        };
+
+        Ok(Self {
+            map_id,
+            scale,
+            icons,
+            data,
+        })
+    }
+}
+
+proto_enum! {
+    BlockEntityAction (u8) {
+        MobSpawner = 1,
+        CommandBlock = 2,
+        Beacon = 3,
+        Skull = 4,
+        FlowerPot = 5,
+        Banner = 6
+    }
+    default Self::MobSpawner
+}
+
+#[derive(Clone, Debug, Default, protocol_derive::ProtocolSupport)]
+pub struct Statistic {
+    /// Like `stat.leaveGame` or `achievement.openInventory`.
+    pub name: String,
+    #[protocol_field(varnum)]
+    pub value: i32,
+}
+
+/// Changes to the world border, diameters in blocks and speeds in real time
+/// milliseconds.
+#[derive(Clone, Debug)]
+pub enum WorldBorder {
+    SetSize {
+        diameter: f64,
+    },
+    LerpSize {
+        old_diameter: f64,
+        new_diameter: f64,
+        speed: i64,
+    },
+    SetCenter {
+        x: f64,
+        z: f64,
+    },
+    Initialize {
+        x: f64,
+        z: f64,
+        old_diameter: f64,
+        new_diameter: f64,
+        speed: i64,
+        /// Usually `29999984`.
+        portal_teleport_boundary: i32,
+        /// In seconds.
+        warning_time: i32,
+        warning_blocks: i32,
+    },
+    SetWarningTime {
+        warning_time: i32,
+    },
+    SetWarningBlocks {
+        warning_blocks: i32,
+    },
+}
+
+impl Default for WorldBorder {
+    fn default() -> Self {
+        Self::SetSize {
+            diameter: 60000000.0,
+        }
+    }
+}
+
+impl ProtocolSupportEncoder for WorldBorder {
+    fn calculate_len(&self, _: &::protocol_internal::ProtocolVersion) -> usize {
+        1 + match self {
+            Self::SetSize { .. } => 8,
+            Self::LerpSize { speed, .. } => 16 + VarNum::<i64>::calculate_len(speed),
+            Self::SetCenter { .. } => 16,
+            Self::Initialize {
+                speed,
+                portal_teleport_boundary,
+                warning_time,
+                warning_blocks,
+                ..
+            } => {
+                32 + VarNum::<i64>::calculate_len(speed)
+                    + VarNum::<i32>::calculate_len(portal_teleport_boundary)
+                    + VarNum::<i32>::calculate_len(warning_time)
+                    + VarNum::<i32>::calculate_len(warning_blocks)
+            }
+            Self::SetWarningTime { warning_time } => VarNum::<i32>::calculate_len(warning_time),
+            Self::SetWarningBlocks { warning_blocks } => {
+                VarNum::<i32>::calculate_len(warning_blocks)
+            }
+        }
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        match self {
+            Self::SetSize { diameter } => {
+                VarNum::<i32>::encode(&0, dst)?;
+                diameter.encode(dst, version)
+            }
+            Self::LerpSize {
+                old_diameter,
+                new_diameter,
+                speed,
+            } => {
+                VarNum::<i32>::encode(&1, dst)?;
+                old_diameter.encode(dst, version)?;
+                new_diameter.encode(dst, version)?;
+                VarNum::<i64>::encode(speed, dst)
+            }
+            Self::SetCenter { x, z } => {
+                VarNum::<i32>::encode(&2, dst)?;
+                x.encode(dst, version)?;
+                z.encode(dst, version)
+            }
+            Self::Initialize {
+                x,
+                z,
+                old_diameter,
+                new_diameter,
+                speed,
+                portal_teleport_boundary,
+                warning_time,
+                warning_blocks,
+            } => {
+                VarNum::<i32>::encode(&3, dst)?;
+                x.encode(dst, version)?;
+                z.encode(dst, version)?;
+                old_diameter.encode(dst, version)?;
+                new_diameter.encode(dst, version)?;
+                VarNum::<i64>::encode(speed, dst)?;
+                VarNum::<i32>::encode(portal_teleport_boundary, dst)?;
+                VarNum::<i32>::encode(warning_time, dst)?;
+                VarNum::<i32>::encode(warning_blocks, dst)
+            }
+            Self::SetWarningTime { warning_time } => {
+                VarNum::<i32>::encode(&4, dst)?;
+                VarNum::<i32>::encode(warning_time, dst)
+            }
+            Self::SetWarningBlocks { warning_blocks } => {
+                VarNum::<i32>::encode(&5, dst)?;
+                VarNum::<i32>::encode(warning_blocks, dst)
+            }
+        }
+    }
+}
+
+impl ProtocolSupportDecoder for WorldBorder {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        Ok(match VarNum::<i32>::decode(src)? {
+            0 => Self::SetSize {
+                diameter: f64::decode(src, version)?,
+            },
+            1 => Self::LerpSize {
+                old_diameter: f64::decode(src, version)?,
+                new_diameter: f64::decode(src, version)?,
+                speed: VarNum::<i64>::decode(src)?,
+            },
+            2 => Self::SetCenter {
+                x: f64::decode(src, version)?,
+                z: f64::decode(src, version)?,
+            },
+            3 => Self::Initialize {
+                x: f64::decode(src, version)?,
+                z: f64::decode(src, version)?,
+                old_diameter: f64::decode(src, version)?,
+                new_diameter: f64::decode(src, version)?,
+                speed: VarNum::<i64>::decode(src)?,
+                portal_teleport_boundary: VarNum::<i32>::decode(src)?,
+                warning_time: VarNum::<i32>::decode(src)?,
+                warning_blocks: VarNum::<i32>::decode(src)?,
+            },
+            4 => Self::SetWarningTime {
+                warning_time: VarNum::<i32>::decode(src)?,
+            },
+            5 => Self::SetWarningBlocks {
+                warning_blocks: VarNum::<i32>::decode(src)?,
+            },
+            action => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("invalid world border action {}", action),
+                ))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use misc::prelude::*;
+    use protocol_internal::{
+        DecodeContext, PacketDecoder, PacketEncoder, ProtocolSupportEncoder, ProtocolVersionEnum,
+    };
+
+    use super::*;
+    use crate::packets::play::test::round_trip;
+
+    #[test]
+    fn test_player_list_item() {
+        let version = ProtocolVersionEnum::V1_8.into();
+        let uuid = uuid::Uuid::from_u128(1);
+        let items = vec![
+            PlayerListItem::AddPlayer(vec![(
+                uuid,
+                PlayerListItemAddPlayer {
+                    name: "Notch".into(),
+                    properties: vec![Property {
+                        name: "textures".into(),
+                        value: "e30=".into(),
+                        signature: None,
+                    }],
+                    game_mode: GameMode::Creative,
+                    ping: 300,
+                    display_name: Some(ChatComponent::new("Notch")),
+                },
+            )]),
+            PlayerListItem::UpdateGameMode(vec![(uuid, GameMode::Spectator)]),
+            PlayerListItem::UpdateLatency(vec![(uuid, 300), (uuid::Uuid::nil(), 1)]),
+            PlayerListItem::UpdateDisplayName(vec![(uuid, None)]),
+            PlayerListItem::RemovePlayer(vec![uuid]),
+        ];
+
+        for item in items {
+            let mut buf = Vec::new();
+            PacketEncoder::encode(&item, &mut buf, &version).unwrap();
+            assert_eq!(buf.len(), PacketEncoder::calculate_len(&item, &version));
+            assert_eq!(buf[0], 0x38);
+
+            let decoded = <PlayerListItem as PacketDecoder>::decode(
+                &mut DecodeContext::from(&buf[..]),
+                &version,
+            )
+            .unwrap();
+            assert_eq!(format!("{:?}", decoded), format!("{:?}", item));
+        }
+
+        // action and count, then the uuid
+        let latency = PlayerListItem::UpdateLatency(vec![(uuid, 300)]);
+        assert_eq!(
+            ProtocolSupportEncoder::calculate_len(&latency, &version),
+            1 + 1 + 16 + 2
+        );
+    }
+
+    #[test]
+    fn test_entity_packets() {
+        use super::super::metadata::*;
+        use protocol_internal::{Angle, FixedPoint};
+
+        let position = Vec3D::new(
+            FixedPoint::<i32>::from_f64(-12.5),
+            FixedPoint::<i32>::from_f64(64.0),
+            FixedPoint::<i32>::from_f64(0.03125),
+        );
+        let mut metadata = Metadata::new();
+        metadata.set(0, MetadataValue::Byte(0));
+        metadata.set(6, MetadataValue::Float(20.0));
+
+        let spawn = round_trip!(ClientBound::SpawnPlayer {
+            entity_id: 7,
+            player_uuid: uuid::Uuid::from_u128(7),
+            position: position.clone(),
+            yaw: Angle::from_degrees(90.0),
+            pitch: Angle(0),
+            current_item: 0,
+            metadata: metadata.clone(),
+        });
+        assert_eq!(
+            &spawn[18..30],
+            [0xFF, 0xFF, 0xFE, 0x70, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x01]
+        );
+
+        round_trip!(ClientBound::SpawnObject {
+            entity_id: 8,
+            object_type: 60,
+            position: position.clone(),
+            pitch: Angle(0),
+            yaw: Angle(64),
+            data: ObjectData {
+                data: 7,
+                velocity: Vec3D::new(100, -200, 300),
+            },
+        });
+        let no_data = round_trip!(ClientBound::SpawnObject {
+            entity_id: 8,
+            object_type: 2,
+            data: ObjectData::default(),
+            ..Default::default()
+        });
+        assert_eq!(no_data.len(), 1 + 1 + 1 + 12 + 2 + 4);
+
+        round_trip!(ClientBound::SpawnMob {
+            entity_id: 9,
+            mob_type: 50,
+            position: position.clone(),
+            yaw: Angle(1),
+            pitch: Angle(2),
+            head_pitch: Angle(3),
+            velocity: Vec3D::new(0, -8000, 0),
+            metadata,
+        });
+        round_trip!(ClientBound::SpawnPainting {
+            entity_id: 10,
+            title: "Kebab".into(),
+            location: Vec3D::new(1, 2, 3),
+            direction: PaintingDirection::East,
+        });
+        round_trip!(ClientBound::SpawnExperienceOrb {
+            entity_id: 11,
+            position: position.clone(),
+            count: 5,
+        });
+        round_trip!(ClientBound::EntityVelocity {
+            entity_id: 12,
+            velocity: Vec3D::new(1, 2, 3),
+        });
+        round_trip!(ClientBound::EntityRelativeMove {
+            entity_id: 13,
+            delta: Vec3D::new(
+                FixedPoint::<i8>::from_f64(0.5),
+                FixedPoint::<i8>::from_f64(-1.0),
+                FixedPoint(0),
+            ),
+            on_ground: true,
+        });
+        round_trip!(ClientBound::EntityLook {
+            entity_id: 14,
+            yaw: Angle(10),
+            pitch: Angle(20),
+            on_ground: false,
+        });
+        round_trip!(ClientBound::EntityLookAndRelativeMove {
+            entity_id: 15,
+            delta: Vec3D::new(FixedPoint(1), FixedPoint(2), FixedPoint(3)),
+            yaw: Angle(30),
+            pitch: Angle(40),
+            on_ground: true,
+        });
+        round_trip!(ClientBound::EntityTeleport {
+            entity_id: 16,
+            position,
+            yaw: Angle(50),
+            pitch: Angle(60),
+            on_ground: false,
+        });
+        round_trip!(ClientBound::EntityHeadLook {
+            entity_id: 17,
+            head_yaw: Angle(70),
+        });
+        round_trip!(ClientBound::EntityStatus {
+            entity_id: 18,
+            entity_status: 2,
+        });
+        round_trip!(ClientBound::AttachEntity {
+            entity_id: 19,
+            vehicle_id: -1,
+            leash: false,
+        });
+        round_trip!(ClientBound::EntityMetadata {
+            entity_id: 20,
+            metadata: Metadata(vec![(
+                10,
+                MetadataValue::Slot(ItemStack::new(280, 1).into()),
+            )]),
+        });
+        round_trip!(ClientBound::EntityEffect {
+            entity_id: 21,
+            effect_id: 1,
+            amplifier: 0,
+            duration: 600,
+            hide_particles: true,
+        });
+        round_trip!(ClientBound::RemoveEntityEffect {
+            entity_id: 22,
+            effect_id: 1,
+        });
+        let properties = round_trip!(ClientBound::EntityProperties {
+            entity_id: 23,
+            properties: vec![EntityProperty {
+                key: "generic.movementSpeed".into(),
+                value: 0.1,
+                modifiers: vec![AttributeModifier {
+                    uuid: uuid::Uuid::from_u128(1),
+                    amount: 0.3,
+                    operation: ModifierOperation::AddPercent,
+                }],
+            }],
+        });
+        assert_eq!(&properties[..6], [0x20, 23, 0, 0, 0, 1]);
+        round_trip!(ClientBound::EntityEquipment {
+            entity_id: 24,
+            slot: EquipmentSlot::Helmet,
+            item: ItemStack::new(310, 1).into(),
+        });
+    }
+
+    #[test]
+    fn test_window_packets() {
+        round_trip!(ClientBound::SetSlot {
+            window_id: -1,
+            slot: -1,
+            slot_data: Slot::EMPTY,
+        });
+        let items = round_trip!(ClientBound::WindowItems {
+            window_id: 0,
+            slots: vec![Slot::EMPTY, ItemStack::new(1, 64).into()],
+        });
+        assert_eq!(&items[..4], [0x30, 0, 0, 2]);
+        round_trip!(ClientBound::WindowProperty {
+            window_id: 1,
+            property: 2,
+            value: 150,
+        });
+        round_trip!(ClientBound::ConfirmTransaction {
+            window_id: 1,
+            action_number: 12,
+            accepted: false,
+        });
+    }
+
+    #[test]
+    fn test_world_packets() {
+        let location = Vec3D::new(-20, 70, 300);
+
+        round_trip!(ClientBound::TimeUpdate {
+            world_age: 24000,
+            time_of_day: -6000,
+        });
+        round_trip!(ClientBound::UpdateHealth {
+            health: 20.0,
+            food: 20,
+            food_saturation: 5.0,
+        });
+        round_trip!(ClientBound::Respawn {
+            dimension: Dimension::Nether as i32,
+            difficulty: Difficulty::Hard,
+            game_mode: GameMode::Survival,
+            level_type: "default".into(),
+        });
+        round_trip!(ClientBound::Explosion {
+            position: Vec3D::new(0.5, 64.0, 0.5),
+            radius: 4.0,
+            records: vec![Vec3D::new(0, -1, 0), Vec3D::new(1, 0, -2)],
+            player_motion: Vec3D::new(0.0, 0.25, 0.0),
+        });
+        round_trip!(ClientBound::Effect {
+            effect_id: 2001,
+            location: location.clone(),
+            data: 1,
+            disable_relative_volume: false,
+        });
+        round_trip!(ClientBound::SoundEffect {
+            sound_name: "random.click".into(),
+            position: Vec3D::new(4, 512, -4),
+            volume: 1.0,
+            pitch: 63,
+        });
+        let particle = round_trip!(ClientBound::Particle {
+            particle: ParticleKind::IconCrack,
+            long_distance: false,
+            position: Vec3D::new(0.0, 65.0, 0.0),
+            offset: Vec3D::new(0.1, 0.1, 0.1),
+            particle_data: 0.05,
+            count: 8,
+            data: vec![280, 0],
+        });
+        assert_eq!(&particle[particle.len() - 3..], [0x98, 0x02, 0x00]);
+        round_trip!(ClientBound::Particle {
+            particle: ParticleKind::Heart,
+            ..Default::default()
+        });
+        round_trip!(ClientBound::ChangeGameState {
+            reason: GameStateReason::ChangeGameMode,
+            value: GameMode::Creative as u8 as f32,
+        });
+        round_trip!(ClientBound::UpdateSign {
+            location: location.clone(),
+            lines: Box::new([
+                ChatComponent::new("Welcome"),
+                ChatComponent::new("to the").color(ChatColor::Gold),
+                ChatComponent::new("server"),
+                Default::default(),
+            ]),
+        });
+        round_trip!(ClientBound::Map {
+            map_id: 3,
+            scale: 0,
+            icons: vec![MapIcon {
+                kind: 0,
+                direction: 8,
+                x: -12,
+                z: 40,
+            }],
+            data: Some(MapData {
+                columns: 2,
+                rows: 2,
+                x: 64,
+                z: 64,
+                data: vec![4, 5, 6, 7],
+            }),
+        });
+        let map = round_trip!(ClientBound::Map {
+            map_id: 3,
+            ..Default::default()
+        });
+        assert_eq!(map, [0x34, 3, 0, 0, 0]);
+
+        let mut nbt = NbtCompound::new();
+        nbt.insert("EntityId", "Zombie");
+        let block_entity = round_trip!(ClientBound::UpdateBlockEntity {
+            location: location.clone(),
+            action: BlockEntityAction::MobSpawner,
+            nbt: nbt.into(),
+        });
+        // the compound holding a byte array of -1 bytes instead
+        let malformed = [
+            &block_entity[..10],
+            &[0x0A, 0x00, 0x00, 0x07, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF],
+        ]
+        .concat();
+        assert_eq!(
+            <ClientBound as PacketDecoder>::decode(
+                &mut DecodeContext::from(&malformed[..]),
+                &ProtocolVersionEnum::V1_8.into()
+            )
+            .unwrap_err()
+            .kind(),
+            std::io::ErrorKind::InvalidData
+        );
+        round_trip!(ClientBound::SignEditorOpen { location });
+        round_trip!(ClientBound::Statistics {
+            statistics: vec![Statistic {
+                name: "stat.leaveGame".into(),
+                value: 1,
+            }],
+        });
+        round_trip!(ClientBound::PlayerAbilities {
+            flags: PlayerAbilitiesFlags::ALLOW_FLYING | PlayerAbilitiesFlags::CREATIVE_MODE,
+            flying_speed: 0.05,
+            field_of_view: 0.1,
+        });
+        round_trip!(ClientBound::Camera { camera_id: 42 });
+        round_trip(WorldBorder::default(), ClientBound::WorldBorder);
+        let border = round_trip(
+            WorldBorder::LerpSize {
+                old_diameter: 100.0,
+                new_diameter: 200.0,
+                speed: 5_000_000_000,
+            },
+            ClientBound::WorldBorder,
+        );
+        assert_eq!(border.len(), 1 + 1 + 16 + 5);
+        round_trip(
+            WorldBorder::Initialize {
+                x: 0.0,
+                z: 0.0,
+                old_diameter: 200.0,
+                new_diameter: 200.0,
+                speed: 0,
+                portal_teleport_boundary: 29999984,
+                warning_time: 15,
+                warning_blocks: 5,
+            },
+            ClientBound::WorldBorder,
+        );
+        round_trip!(ClientBound::ResourcePackSend {
+            url: "https://example.com/pack.zip".into(),
+            hash: "a".repeat(40).into(),
+        });
+
+        let mut invalid = Vec::new();
+        let particle = Particle {
+            particle: ParticleKind::BlockCrack,
+            ..Default::default()
+        };
+        assert!(
+            PacketEncoder::encode(&particle, &mut invalid, &ProtocolVersionEnum::V1_8.into())
+                .is_err()
+        );
+    }
+}
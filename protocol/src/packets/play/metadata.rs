@@ -0,0 +1,182 @@
+//! Entity metadata, the indexed values describing an entity's state like
+//! its flags, health or the item it shows.
+
+use std::io;
+
+use misc::prelude::{Slot, Vec3D};
+use protocol_internal::{
+    DecodeContext, ProtocolSupportDecoder, ProtocolSupportEncoder, ProtocolVersion,
+};
+
+/// Ends the list of entries.
+const END: u8 = 0x7F;
+/// Indexes take the 5 low bits of an entry's header, the type the 3 high.
+const MAX_INDEX: u8 = 0x1F;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetadataValue {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Float(f32),
+    String(String),
+    Slot(Slot),
+    Position(Vec3D<i32>),
+    Rotation(Vec3D<f32>),
+}
+
+impl MetadataValue {
+    fn type_id(&self) -> u8 {
+        match self {
+            Self::Byte(_) => 0,
+            Self::Short(_) => 1,
+            Self::Int(_) => 2,
+            Self::Float(_) => 3,
+            Self::String(_) => 4,
+            Self::Slot(_) => 5,
+            Self::Position(_) => 6,
+            Self::Rotation(_) => 7,
+        }
+    }
+}
+
+impl ProtocolSupportEncoder for MetadataValue {
+    fn calculate_len(&self, version: &ProtocolVersion) -> usize {
+        match self {
+            Self::Byte(value) => value.calculate_len(version),
+            Self::Short(value) => value.calculate_len(version),
+            Self::Int(value) => value.calculate_len(version),
+            Self::Float(value) => value.calculate_len(version),
+            Self::String(value) => value.calculate_len(version),
+            Self::Slot(value) => value.calculate_len(version),
+            Self::Position(value) => value.calculate_len(version),
+            Self::Rotation(value) => value.calculate_len(version),
+        }
+    }
+
+    fn encode<W: io::Write>(&self, dst: &mut W, version: &ProtocolVersion) -> io::Result<()> {
+        match self {
+            Self::Byte(value) => value.encode(dst, version),
+            Self::Short(value) => value.encode(dst, version),
+            Self::Int(value) => value.encode(dst, version),
+            Self::Float(value) => value.encode(dst, version),
+            Self::String(value) => value.encode(dst, version),
+            Self::Slot(value) => value.encode(dst, version),
+            Self::Position(value) => value.encode(dst, version),
+            Self::Rotation(value) => value.encode(dst, version),
+        }
+    }
+}
+
+/// The values of an entity by index, as sent in `SpawnPlayer`, `SpawnMob`
+/// and `EntityMetadata`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata(pub Vec<(u8, MetadataValue)>);
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value at `index`, which must be below 32.
+    pub fn set(&mut self, index: u8, value: MetadataValue) {
+        match self.0.iter_mut().find(|(i, _)| *i == index) {
+            Some((_, old)) => *old = value,
+            None => self.0.push((index, value)),
+        }
+    }
+
+    pub fn get(&self, index: u8) -> Option<&MetadataValue> {
+        self.0
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, value)| value)
+    }
+}
+
+impl ProtocolSupportEncoder for Metadata {
+    fn calculate_len(&self, version: &ProtocolVersion) -> usize {
+        self.0
+            .iter()
+            .map(|(_, value)| 1 + value.calculate_len(version))
+            .sum::<usize>()
+            + 1
+    }
+
+    fn encode<W: io::Write>(&self, dst: &mut W, version: &ProtocolVersion) -> io::Result<()> {
+        for (index, value) in &self.0 {
+            if *index > MAX_INDEX {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("metadata index {} is over {}", index, MAX_INDEX),
+                ));
+            }
+
+            (value.type_id() << 5 | index).encode(dst, version)?;
+            value.encode(dst, version)?;
+        }
+
+        END.encode(dst, version)
+    }
+}
+
+impl ProtocolSupportDecoder for Metadata {
+    fn decode<R: io::Read>(
+        src: &mut DecodeContext<R>,
+        version: &ProtocolVersion,
+    ) -> io::Result<Self> {
+        let mut entries = Vec::new();
+
+        loop {
+            let header = u8::decode(src, version)?;
+            if header == END {
+                return Ok(Self(entries));
+            }
+
+            let value = match header >> 5 {
+                0 => MetadataValue::Byte(i8::decode(src, version)?),
+                1 => MetadataValue::Short(i16::decode(src, version)?),
+                2 => MetadataValue::Int(i32::decode(src, version)?),
+                3 => MetadataValue::Float(f32::decode(src, version)?),
+                4 => MetadataValue::String(String::decode(src, version)?),
+                5 => MetadataValue::Slot(Slot::decode(src, version)?),
+                6 => MetadataValue::Position(Vec3D::decode(src, version)?),
+                _ => MetadataValue::Rotation(Vec3D::decode(src, version)?),
+            };
+
+            entries.push((header & MAX_INDEX, value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use misc::prelude::ItemStack;
+
+    use super::*;
+
+    #[test]
+    fn test_metadata() {
+        let version = ProtocolVersion::new(47);
+
+        let mut metadata = Metadata::new();
+        metadata.set(0, MetadataValue::Byte(0x02));
+        metadata.set(2, MetadataValue::String("Dinnerbone".into()));
+        metadata.set(6, MetadataValue::Float(20.0));
+        metadata.set(10, MetadataValue::Slot(ItemStack::new(1, 1).into()));
+        metadata.set(0, MetadataValue::Byte(0x08));
+
+        let mut buf = Vec::new();
+        metadata.encode(&mut buf, &version).unwrap();
+        assert_eq!(buf.len(), metadata.calculate_len(&version));
+        assert_eq!(&buf[..2], [0x00, 0x08]);
+        assert_eq!(buf.last(), Some(&END));
+
+        let decoded = Metadata::decode(&mut DecodeContext::from(&buf[..]), &version).unwrap();
+        assert_eq!(decoded, metadata);
+        assert_eq!(decoded.get(6), Some(&MetadataValue::Float(20.0)));
+
+        metadata.set(32, MetadataValue::Int(0));
+        assert!(metadata.encode(&mut Vec::new(), &version).is_err());
+    }
+}
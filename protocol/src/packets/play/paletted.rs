@@ -0,0 +1,1284 @@
+//! Chunk sections as sent from 1.9 on: block states through a palette, packed
+//! with as few bits as the palette needs into longs.
+//!
+//! [`PalettedColumn`] stacks them into a column, turned into the data of a
+//! [`PalettedChunkData`] for any version from 1.9 on and parsed back from it.
+
+use std::{
+    borrow::Cow,
+    convert::TryFrom,
+    io::{self, Read},
+};
+
+use misc::prelude::{ChunkPosition, NbtCompound, OptionalNbt};
+use protocol_internal::{
+    DecodeContext, ProtocolSupportDecoder, ProtocolSupportEncoder, ProtocolVersion,
+    ProtocolVersionEnum, VarNum,
+};
+
+use super::chunk::{self, DEFAULT_BIOME, LIGHT_BYTES, SECTION_COUNT};
+
+/// Blocks in a section.
+pub const SECTION_VOLUME: usize = 4096;
+
+/// Indirect palettes never use less bits per block.
+const MIN_BITS: u8 = 4;
+/// Past this many bits per block, states are stored without a palette.
+const MAX_INDIRECT_BITS: u8 = 8;
+
+/// Bits per block of the direct palette, enough for every block state of the
+/// version.
+pub fn global_bits(version: &ProtocolVersion) -> u8 {
+    if *version >= ProtocolVersionEnum::V1_16 {
+        15
+    } else if *version >= ProtocolVersionEnum::V1_13 {
+        14
+    } else {
+        13
+    }
+}
+
+/// Whether entries are padded to not span two longs, since 1.16.
+pub fn is_padded(version: &ProtocolVersion) -> bool {
+    *version >= ProtocolVersionEnum::V1_16
+}
+
+/// Fixed size entries packed into longs, from the lowest bits up.
+///
+/// Entries span two longs when they don't fit the rest of one, unless the
+/// array is padded, leaving the highest bits of every long unused instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompactedLongArray {
+    bits_per_entry: u8,
+    len: usize,
+    padded: bool,
+    data: Vec<u64>,
+}
+
+impl CompactedLongArray {
+    /// `len` entries of zero. Panics if `bits_per_entry` isn't from 1 to 32.
+    pub fn new(bits_per_entry: u8, len: usize, padded: bool) -> Self {
+        assert!(
+            (1..=32).contains(&bits_per_entry),
+            "{} bits per entry",
+            bits_per_entry
+        );
+
+        Self {
+            bits_per_entry,
+            len,
+            padded,
+            data: vec![0; Self::longs_len(bits_per_entry, len, padded)],
+        }
+    }
+
+    /// An array over received longs, of which there must be as many as the
+    /// entries take.
+    pub fn from_longs(
+        bits_per_entry: u8,
+        len: usize,
+        padded: bool,
+        data: Vec<u64>,
+    ) -> io::Result<Self> {
+        if !(1..=32).contains(&bits_per_entry) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} bits per entry", bits_per_entry),
+            ));
+        }
+
+        let expected = Self::longs_len(bits_per_entry, len, padded);
+        if data.len() != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "expected {} longs for {} entries of {} bits, got {}",
+                    expected,
+                    len,
+                    bits_per_entry,
+                    data.len()
+                ),
+            ));
+        }
+
+        Ok(Self {
+            bits_per_entry,
+            len,
+            padded,
+            data,
+        })
+    }
+
+    /// How many longs `len` entries take.
+    pub fn longs_len(bits_per_entry: u8, len: usize, padded: bool) -> usize {
+        if padded {
+            let per_long = 64 / bits_per_entry as usize;
+            len.div_ceil(per_long)
+        } else {
+            (len * bits_per_entry as usize).div_ceil(64)
+        }
+    }
+
+    pub fn bits_per_entry(&self) -> u8 {
+        self.bits_per_entry
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_padded(&self) -> bool {
+        self.padded
+    }
+
+    pub fn as_longs(&self) -> &[u64] {
+        &self.data
+    }
+
+    pub fn into_longs(self) -> Vec<u64> {
+        self.data
+    }
+
+    fn mask(&self) -> u64 {
+        (1 << self.bits_per_entry) - 1
+    }
+
+    /// The long and bit offset of an entry.
+    fn position(&self, index: usize) -> (usize, usize) {
+        assert!(index < self.len, "{} is out of {} entries", index, self.len);
+
+        let bits = self.bits_per_entry as usize;
+        if self.padded {
+            let per_long = 64 / bits;
+            (index / per_long, index % per_long * bits)
+        } else {
+            (index * bits / 64, index * bits % 64)
+        }
+    }
+
+    /// Panics if `index` is out of the array.
+    pub fn get(&self, index: usize) -> u32 {
+        let (long, offset) = self.position(index);
+
+        let mut value = self.data[long] >> offset;
+        if offset + self.bits_per_entry as usize > 64 {
+            value |= self.data[long + 1] << (64 - offset);
+        }
+
+        (value & self.mask()) as u32
+    }
+
+    /// Panics if `index` is out of the array. Bits of `value` over
+    /// `bits_per_entry` are dropped.
+    pub fn set(&mut self, index: usize, value: u32) {
+        let (long, offset) = self.position(index);
+        let mask = self.mask();
+        let value = value as u64 & mask;
+
+        self.data[long] = self.data[long] & !(mask << offset) | value << offset;
+        if offset + self.bits_per_entry as usize > 64 {
+            let shift = 64 - offset;
+            self.data[long + 1] = self.data[long + 1] & !(mask >> shift) | value >> shift;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.len).map(move |index| self.get(index))
+    }
+
+    /// The same entries with another size or packing, which must fit them.
+    pub fn repacked(&self, bits_per_entry: u8, padded: bool) -> Self {
+        let mut array = Self::new(bits_per_entry, self.len, padded);
+        for (index, value) in self.iter().enumerate() {
+            array.set(index, value);
+        }
+        array
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Palette {
+    /// The states in the section, entries being their index.
+    Indirect(Vec<u32>),
+    /// Entries are the global block state ids.
+    Direct,
+}
+
+/// The block states of a section.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PalettedContainer {
+    palette: Palette,
+    storage: CompactedLongArray,
+    global_bits: u8,
+}
+
+impl PalettedContainer {
+    /// A section of air, state `0`, for `version`.
+    pub fn new(version: &ProtocolVersion) -> Self {
+        Self {
+            palette: Palette::Indirect(vec![0]),
+            storage: CompactedLongArray::new(MIN_BITS, SECTION_VOLUME, is_padded(version)),
+            global_bits: global_bits(version),
+        }
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn bits_per_block(&self) -> u8 {
+        self.storage.bits_per_entry()
+    }
+
+    /// Panics if `index` is over 4095, `y << 8 | z << 4 | x` for a block.
+    pub fn get(&self, index: usize) -> u32 {
+        let value = self.storage.get(index);
+        match &self.palette {
+            Palette::Indirect(states) => states[value as usize],
+            Palette::Direct => value,
+        }
+    }
+
+    /// Adds `state` to the palette when missing, taking more bits per block
+    /// once it's full.
+    ///
+    /// Panics if `index` is over 4095, or if `state` takes more bits than
+    /// the global palette has.
+    pub fn set(&mut self, index: usize, state: u32) {
+        assert!(
+            state >> self.global_bits == 0,
+            "state {} takes more than {} bits",
+            state,
+            self.global_bits
+        );
+
+        let value = match &mut self.palette {
+            Palette::Indirect(states) => match states.iter().position(|s| *s == state) {
+                Some(position) => position as u32,
+                None => {
+                    states.push(state);
+                    let position = states.len() as u32 - 1;
+                    if states.len() > 1 << self.storage.bits_per_entry() {
+                        self.grow();
+                    }
+
+                    match self.palette {
+                        Palette::Indirect(_) => position,
+                        Palette::Direct => state,
+                    }
+                }
+            },
+            Palette::Direct => state,
+        };
+
+        self.storage.set(index, value);
+    }
+
+    /// States are checked by `set` and `decode`, so they all fit the global
+    /// palette when switching to it.
+    fn grow(&mut self) {
+        let bits = self.storage.bits_per_entry() + 1;
+        let padded = self.storage.is_padded();
+
+        if bits <= MAX_INDIRECT_BITS {
+            self.storage = self.storage.repacked(bits, padded);
+            return;
+        }
+
+        let states = match &self.palette {
+            Palette::Indirect(states) => states,
+            Palette::Direct => return,
+        };
+        let mut storage = CompactedLongArray::new(self.global_bits, SECTION_VOLUME, padded);
+        for (index, value) in self.storage.iter().enumerate() {
+            storage.set(index, states[value as usize]);
+        }
+
+        self.palette = Palette::Direct;
+        self.storage = storage;
+    }
+
+    /// The storage as sent to `version`, direct states taking the bits of its
+    /// global palette.
+    fn storage_for(&self, version: &ProtocolVersion) -> Cow<'_, CompactedLongArray> {
+        let bits = match self.palette {
+            Palette::Indirect(_) => self.storage.bits_per_entry(),
+            Palette::Direct => global_bits(version),
+        };
+
+        if bits == self.storage.bits_per_entry() && self.storage.is_padded() == is_padded(version) {
+            Cow::Borrowed(&self.storage)
+        } else {
+            Cow::Owned(self.storage.repacked(bits, is_padded(version)))
+        }
+    }
+
+    /// Blocks that aren't state `0`, sent with sections from 1.14 on.
+    pub fn non_air_count(&self) -> usize {
+        (0..SECTION_VOLUME)
+            .filter(|index| self.get(*index) != 0)
+            .count()
+    }
+}
+
+impl ProtocolSupportEncoder for PalettedContainer {
+    fn calculate_len(&self, version: &ProtocolVersion) -> usize {
+        let palette = match &self.palette {
+            Palette::Indirect(states) => {
+                VarNum::<i32>::calculate_len(&(states.len() as i32))
+                    + states
+                        .iter()
+                        .map(|state| VarNum::<i32>::calculate_len(&(*state as i32)))
+                        .sum::<usize>()
+            }
+            Palette::Direct if *version < ProtocolVersionEnum::V1_13 => 1,
+            Palette::Direct => 0,
+        };
+        let bits = match self.palette {
+            Palette::Indirect(_) => self.storage.bits_per_entry(),
+            Palette::Direct => global_bits(version),
+        };
+        let longs = CompactedLongArray::longs_len(bits, SECTION_VOLUME, is_padded(version));
+
+        1 + palette + VarNum::<i32>::calculate_len(&(longs as i32)) + longs * 8
+    }
+
+    fn encode<W: io::Write>(&self, dst: &mut W, version: &ProtocolVersion) -> io::Result<()> {
+        let bits = global_bits(version);
+        if self.palette == Palette::Direct
+            && bits < self.storage.bits_per_entry()
+            && self.storage.iter().any(|state| state >> bits != 0)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("block state over the {} bits of {:?}", bits, version),
+            ));
+        }
+
+        let storage = self.storage_for(version);
+        storage.bits_per_entry().encode(dst, version)?;
+
+        match &self.palette {
+            Palette::Indirect(states) => {
+                VarNum::<i32>::encode(&(states.len() as i32), dst)?;
+                for state in states {
+                    VarNum::<i32>::encode(&(*state as i32), dst)?;
+                }
+            }
+            // an empty palette before 1.13
+            Palette::Direct if *version < ProtocolVersionEnum::V1_13 => {
+                VarNum::<i32>::encode(&0, dst)?
+            }
+            Palette::Direct => {}
+        }
+
+        VarNum::<i32>::encode(&(storage.as_longs().len() as i32), dst)?;
+        for long in storage.as_longs() {
+            (*long as i64).encode(dst, version)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProtocolSupportDecoder for PalettedContainer {
+    fn decode<R: io::Read>(
+        src: &mut DecodeContext<R>,
+        version: &ProtocolVersion,
+    ) -> io::Result<Self> {
+        let bits = u8::decode(src, version)?;
+        // before `longs_len` divides by it
+        if bits == 0 || bits > global_bits(version) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} bits per block outside of 1 to {}",
+                    bits,
+                    global_bits(version)
+                ),
+            ));
+        }
+
+        let palette = if bits <= MAX_INDIRECT_BITS {
+            let len = VarNum::<i32>::decode(src)?;
+            if len <= 0 || len > 1 << bits {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("palette of {} states for {} bits", len, bits),
+                ));
+            }
+
+            let states = (0..len)
+                .map(|_| VarNum::<i32>::decode(src).map(|state| state as u32))
+                .collect::<io::Result<_>>()?;
+            Palette::Indirect(states)
+        } else {
+            if *version < ProtocolVersionEnum::V1_13 {
+                // always empty, but skipped like the client does
+                for _ in 0..VarNum::<i32>::decode(src)? {
+                    VarNum::<i32>::decode(src)?;
+                }
+            }
+            Palette::Direct
+        };
+
+        let len = VarNum::<i32>::decode(src)?;
+        let padded = is_padded(version);
+        let expected = CompactedLongArray::longs_len(bits, SECTION_VOLUME, padded);
+        if len < 0 || len as usize != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected {} longs for {} bits, got {}", expected, bits, len),
+            ));
+        }
+
+        let data = (0..len)
+            .map(|_| i64::decode(src, version).map(|long| long as u64))
+            .collect::<io::Result<_>>()?;
+        let storage = CompactedLongArray::from_longs(bits, SECTION_VOLUME, padded, data)?;
+
+        let global_bits = match &palette {
+            Palette::Indirect(states) => {
+                if storage.iter().any(|value| value as usize >= states.len()) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "block outside of the palette",
+                    ));
+                }
+
+                let global_bits = global_bits(version);
+                if states.iter().any(|state| state >> global_bits != 0) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("palette state over {} bits", global_bits),
+                    ));
+                }
+                global_bits
+            }
+            Palette::Direct => bits,
+        };
+
+        Ok(Self {
+            palette,
+            storage,
+            global_bits,
+        })
+    }
+}
+
+/// 16x16x16 block states, with their light as sent before 1.14.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PalettedSection {
+    blocks: PalettedContainer,
+    block_light: Vec<u8>,
+    sky_light: Vec<u8>,
+}
+
+impl PalettedSection {
+    /// Air, dark but under the open sky.
+    pub fn new(version: &ProtocolVersion) -> Self {
+        Self {
+            blocks: PalettedContainer::new(version),
+            block_light: vec![0; LIGHT_BYTES],
+            sky_light: vec![0xFF; LIGHT_BYTES],
+        }
+    }
+
+    pub fn blocks(&self) -> &PalettedContainer {
+        &self.blocks
+    }
+
+    /// Panics if a coordinate is over 15.
+    pub fn block(&self, x: usize, y: usize, z: usize) -> u32 {
+        self.blocks.get(chunk::index(x, y, z))
+    }
+
+    /// Panics like [`PalettedContainer::set`].
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, state: u32) {
+        self.blocks.set(chunk::index(x, y, z), state);
+    }
+
+    pub fn block_light(&self, x: usize, y: usize, z: usize) -> u8 {
+        chunk::get_nibble(&self.block_light, chunk::index(x, y, z))
+    }
+
+    pub fn set_block_light(&mut self, x: usize, y: usize, z: usize, level: u8) {
+        chunk::set_nibble(&mut self.block_light, chunk::index(x, y, z), level);
+    }
+
+    pub fn sky_light(&self, x: usize, y: usize, z: usize) -> u8 {
+        chunk::get_nibble(&self.sky_light, chunk::index(x, y, z))
+    }
+
+    pub fn set_sky_light(&mut self, x: usize, y: usize, z: usize, level: u8) {
+        chunk::set_nibble(&mut self.sky_light, chunk::index(x, y, z), level);
+    }
+
+    /// Whether every block is air, which columns don't send.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.non_air_count() == 0
+    }
+
+    /// The block count first from 1.14 on, the light after the blocks
+    /// before, as it moved to `UpdateLight`.
+    fn write(
+        &self,
+        dst: &mut Vec<u8>,
+        sky_light: bool,
+        version: &ProtocolVersion,
+    ) -> io::Result<()> {
+        if *version >= ProtocolVersionEnum::V1_14 {
+            (self.blocks.non_air_count() as i16).encode(dst, version)?;
+        }
+
+        self.blocks.encode(dst, version)?;
+
+        if *version < ProtocolVersionEnum::V1_14 {
+            dst.extend_from_slice(&self.block_light);
+            if sky_light {
+                dst.extend_from_slice(&self.sky_light);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read<R: io::Read>(
+        src: &mut DecodeContext<R>,
+        sky_light: bool,
+        version: &ProtocolVersion,
+    ) -> io::Result<Self> {
+        let mut section = Self::new(version);
+
+        if *version >= ProtocolVersionEnum::V1_14 {
+            // counted again from the blocks when needed
+            i16::decode(src, version)?;
+        }
+
+        section.blocks = PalettedContainer::decode(src, version)?;
+
+        if *version < ProtocolVersionEnum::V1_14 {
+            src.read_exact(&mut section.block_light)?;
+            if sky_light {
+                src.read_exact(&mut section.sky_light)?;
+            }
+        }
+
+        Ok(section)
+    }
+}
+
+/// How many biomes a column holds: one per x and z before 1.15, one per
+/// 4x4x4 blocks after.
+pub fn biomes_len(version: &ProtocolVersion) -> usize {
+    if *version >= ProtocolVersionEnum::V1_15 {
+        1024
+    } else {
+        256
+    }
+}
+
+/// 16 paletted sections stacked up to the build height, with their biomes,
+/// for a version from 1.9 on.
+#[derive(Clone, Debug)]
+pub struct PalettedColumn {
+    sections: Vec<Option<PalettedSection>>,
+    biomes: Vec<i32>,
+    version: ProtocolVersion,
+}
+
+impl PalettedColumn {
+    pub fn new(version: &ProtocolVersion) -> Self {
+        Self {
+            sections: vec![None; SECTION_COUNT],
+            biomes: vec![DEFAULT_BIOME as i32; biomes_len(version)],
+            version: *version,
+        }
+    }
+
+    pub fn version(&self) -> &ProtocolVersion {
+        &self.version
+    }
+
+    /// `None` for a section that was never set, which is all air.
+    pub fn section(&self, y: usize) -> Option<&PalettedSection> {
+        self.sections[y].as_ref()
+    }
+
+    pub fn section_mut(&mut self, y: usize) -> Option<&mut PalettedSection> {
+        self.sections[y].as_mut()
+    }
+
+    /// The section at `y`, created when missing.
+    pub fn section_or_insert(&mut self, y: usize) -> &mut PalettedSection {
+        let version = self.version;
+        self.sections[y].get_or_insert_with(|| PalettedSection::new(&version))
+    }
+
+    /// Panics if `x` or `z` is over 15, or `y` over 255.
+    pub fn block(&self, x: usize, y: usize, z: usize) -> u32 {
+        self.section(y >> 4)
+            .map_or(0, |section| section.block(x, y & 0x0F, z))
+    }
+
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, state: u32) {
+        match self.sections[y >> 4].as_mut() {
+            Some(section) => section.set_block(x, y & 0x0F, z, state),
+            // no need for a section to hold air
+            None if state == 0 => {}
+            None => self
+                .section_or_insert(y >> 4)
+                .set_block(x, y & 0x0F, z, state),
+        }
+    }
+
+    /// See [`biomes_len`] for their layout in the version.
+    pub fn biomes(&self) -> &[i32] {
+        &self.biomes
+    }
+
+    pub fn biomes_mut(&mut self) -> &mut [i32] {
+        &mut self.biomes
+    }
+
+    /// The sections holding anything but air.
+    pub fn primary_bit_mask(&self) -> u16 {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| matches!(section, Some(section) if !section.is_empty()))
+            .fold(0, |mask, (y, _)| mask | 1 << y)
+    }
+
+    /// The data of the sections in `mask`, followed with `full_chunk` by the
+    /// biomes before 1.15, bytes until 1.13 and ints after.
+    pub fn encode_data(&self, mask: u16, full_chunk: bool, sky_light: bool) -> io::Result<Vec<u8>> {
+        let version = &self.version;
+        let empty = PalettedSection::new(version);
+
+        let mut buf = Vec::new();
+        for y in (0..SECTION_COUNT).filter(|y| mask & 1 << y != 0) {
+            self.section(y)
+                .unwrap_or(&empty)
+                .write(&mut buf, sky_light, version)?;
+        }
+
+        if full_chunk && *version < ProtocolVersionEnum::V1_15 {
+            for biome in &self.biomes {
+                if *version >= ProtocolVersionEnum::V1_13 {
+                    biome.encode(&mut buf, version)?;
+                } else {
+                    let biome = u8::try_from(*biome).map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("biome {} does not fit a byte", biome),
+                        )
+                    })?;
+                    buf.push(biome);
+                }
+            }
+        }
+
+        Ok(buf)
+    }
+
+    /// Reads the data of the sections in `mask` into the column.
+    ///
+    /// With `full_chunk` the data describes the whole column, the sections
+    /// missing from `mask` are cleared and, before 1.15, the biomes
+    /// replaced.
+    pub fn decode_data(
+        &mut self,
+        data: &[u8],
+        mask: u16,
+        full_chunk: bool,
+        sky_light: bool,
+    ) -> io::Result<()> {
+        let version = self.version;
+        let mut src = DecodeContext::from(data);
+
+        let mut sections = Vec::new();
+        for y in (0..SECTION_COUNT).filter(|y| mask & 1 << y != 0) {
+            sections.push((y, PalettedSection::read(&mut src, sky_light, &version)?));
+        }
+
+        if full_chunk && version < ProtocolVersionEnum::V1_15 {
+            for biome in self.biomes.iter_mut() {
+                *biome = if version >= ProtocolVersionEnum::V1_13 {
+                    i32::decode(&mut src, &version)?
+                } else {
+                    u8::decode(&mut src, &version)? as i32
+                };
+            }
+        }
+
+        if !src.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} bytes left after the chunk data for mask {:#06x}",
+                    src.remaining(),
+                    mask
+                ),
+            ));
+        }
+
+        if full_chunk {
+            self.sections = vec![None; SECTION_COUNT];
+        }
+        for (y, section) in sections {
+            self.sections[y] = Some(section);
+        }
+
+        Ok(())
+    }
+
+    /// The whole column, its non-empty sections and biomes, without
+    /// heightmaps or block entities.
+    pub fn to_chunk_data(
+        &self,
+        position: ChunkPosition,
+        sky_light: bool,
+    ) -> io::Result<PalettedChunkData> {
+        let primary_bit_mask = self.primary_bit_mask();
+
+        Ok(PalettedChunkData {
+            position,
+            full_chunk: true,
+            primary_bit_mask,
+            heightmaps: Default::default(),
+            biomes: match self.version >= ProtocolVersionEnum::V1_15 {
+                true => Some(self.biomes.clone()),
+                false => None,
+            },
+            data: self.encode_data(primary_bit_mask, true, sky_light)?,
+            block_entities: Vec::new(),
+        })
+    }
+
+    /// Only the sections in `mask`, sent even when empty to clear them on
+    /// the client.
+    pub fn to_section_update(
+        &self,
+        position: ChunkPosition,
+        mask: u16,
+        sky_light: bool,
+    ) -> io::Result<PalettedChunkData> {
+        Ok(PalettedChunkData {
+            position,
+            full_chunk: false,
+            primary_bit_mask: mask,
+            heightmaps: Default::default(),
+            biomes: None,
+            data: self.encode_data(mask, false, sky_light)?,
+            block_entities: Vec::new(),
+        })
+    }
+
+    /// A column sent as a whole to `version`, `sky_light` depending on the
+    /// dimension as the packet doesn't tell.
+    pub fn from_chunk_data(
+        packet: &PalettedChunkData,
+        sky_light: bool,
+        version: &ProtocolVersion,
+    ) -> io::Result<Self> {
+        let mut column = Self::new(version);
+        column.decode_data(
+            &packet.data,
+            packet.primary_bit_mask,
+            packet.full_chunk,
+            sky_light,
+        )?;
+
+        if let Some(biomes) = &packet.biomes {
+            if biomes.len() != column.biomes.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "expected {} biomes, got {}",
+                        column.biomes.len(),
+                        biomes.len()
+                    ),
+                ));
+            }
+            column.biomes.copy_from_slice(biomes);
+        }
+
+        Ok(column)
+    }
+}
+
+/// The body of `ChunkData` from 1.9 on, whose id changes with the version.
+///
+/// The fields a version doesn't have are skipped, as the same packet is
+/// encoded for any of them.
+#[derive(Clone, Debug, Default)]
+pub struct PalettedChunkData {
+    pub position: ChunkPosition,
+    pub full_chunk: bool,
+    pub primary_bit_mask: u16,
+    /// From 1.14 on, an empty compound when `None`.
+    pub heightmaps: OptionalNbt,
+    /// From 1.15 on, with `full_chunk`. See [`biomes_len`].
+    pub biomes: Option<Vec<i32>>,
+    /// See [`PalettedColumn`] to build and read it.
+    pub data: Vec<u8>,
+    /// From 1.9.4 on.
+    pub block_entities: Vec<OptionalNbt>,
+}
+
+impl PalettedChunkData {
+    /// Fills empty heightmaps in, which every version with them expects.
+    fn heightmaps(&self) -> Cow<'_, OptionalNbt> {
+        match self.heightmaps.0 {
+            Some(_) => Cow::Borrowed(&self.heightmaps),
+            None => Cow::Owned(NbtCompound::new().into()),
+        }
+    }
+
+    fn has_biomes(&self, version: &ProtocolVersion) -> bool {
+        self.full_chunk && *version >= ProtocolVersionEnum::V1_15
+    }
+}
+
+impl ProtocolSupportEncoder for PalettedChunkData {
+    fn calculate_len(&self, version: &ProtocolVersion) -> usize {
+        let mut len = self.position.calculate_len(version) + 1;
+        if is_ignoring_old_data(version) {
+            len += 1;
+        }
+        len += VarNum::<i32>::calculate_len(&(self.primary_bit_mask as i32));
+        if *version >= ProtocolVersionEnum::V1_14 {
+            len += self.heightmaps().calculate_len(version);
+        }
+        if self.has_biomes(version) {
+            let biomes = self.biomes.as_deref().unwrap_or_default();
+            len += if *version >= ProtocolVersionEnum::V1_16_2 {
+                VarNum::<i32>::calculate_len(&(biomes.len() as i32))
+                    + biomes
+                        .iter()
+                        .map(VarNum::<i32>::calculate_len)
+                        .sum::<usize>()
+            } else {
+                biomes.len() * 4
+            };
+        }
+        len += self.data.calculate_len(version);
+        if *version >= ProtocolVersionEnum::V1_9_4 {
+            len += self.block_entities.calculate_len(version);
+        }
+
+        len
+    }
+
+    fn encode<W: io::Write>(&self, dst: &mut W, version: &ProtocolVersion) -> io::Result<()> {
+        self.position.encode(dst, version)?;
+        self.full_chunk.encode(dst, version)?;
+        if is_ignoring_old_data(version) {
+            self.full_chunk.encode(dst, version)?;
+        }
+        VarNum::<i32>::encode(&(self.primary_bit_mask as i32), dst)?;
+
+        if *version >= ProtocolVersionEnum::V1_14 {
+            self.heightmaps().encode(dst, version)?;
+        }
+
+        if self.has_biomes(version) {
+            let biomes = self.biomes.as_deref().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "full chunk without biomes")
+            })?;
+            if biomes.len() != biomes_len(version) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "expected {} biomes, got {}",
+                        biomes_len(version),
+                        biomes.len()
+                    ),
+                ));
+            }
+
+            if *version >= ProtocolVersionEnum::V1_16_2 {
+                VarNum::<i32>::encode(&(biomes.len() as i32), dst)?;
+                for biome in biomes {
+                    VarNum::<i32>::encode(biome, dst)?;
+                }
+            } else {
+                for biome in biomes {
+                    biome.encode(dst, version)?;
+                }
+            }
+        }
+
+        self.data.encode(dst, version)?;
+        if *version >= ProtocolVersionEnum::V1_9_4 {
+            self.block_entities.encode(dst, version)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProtocolSupportDecoder for PalettedChunkData {
+    fn decode<R: io::Read>(
+        src: &mut DecodeContext<R>,
+        version: &ProtocolVersion,
+    ) -> io::Result<Self> {
+        let position = ChunkPosition::decode(src, version)?;
+        let full_chunk = bool::decode(src, version)?;
+        if is_ignoring_old_data(version) {
+            bool::decode(src, version)?;
+        }
+
+        let primary_bit_mask = VarNum::<i32>::decode(src)?;
+        let primary_bit_mask = u16::try_from(primary_bit_mask).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("primary bit mask {:#x} over 16 sections", primary_bit_mask),
+            )
+        })?;
+
+        let heightmaps = match *version >= ProtocolVersionEnum::V1_14 {
+            true => OptionalNbt::decode(src, version)?,
+            false => OptionalNbt::default(),
+        };
+
+        let biomes = if full_chunk && *version >= ProtocolVersionEnum::V1_15 {
+            let len = match *version >= ProtocolVersionEnum::V1_16_2 {
+                true => decode_len(src, 1)?,
+                false => biomes_len(version),
+            };
+
+            let mut biomes = Vec::with_capacity(len.min(src.remaining()));
+            for _ in 0..len {
+                biomes.push(match *version >= ProtocolVersionEnum::V1_16_2 {
+                    true => VarNum::<i32>::decode(src)?,
+                    false => i32::decode(src, version)?,
+                });
+            }
+            Some(biomes)
+        } else {
+            None
+        };
+
+        let mut data = vec![0; decode_len(src, 1)?];
+        src.read_exact(&mut data)?;
+
+        let block_entities = match *version >= ProtocolVersionEnum::V1_9_4 {
+            true => {
+                let len = decode_len(src, 1)?;
+                let mut block_entities = Vec::with_capacity(len);
+                for _ in 0..len {
+                    block_entities.push(OptionalNbt::decode(src, version)?);
+                }
+                block_entities
+            }
+            false => Vec::new(),
+        };
+
+        Ok(Self {
+            position,
+            full_chunk,
+            primary_bit_mask,
+            heightmaps,
+            biomes,
+            data,
+            block_entities,
+        })
+    }
+}
+
+/// 1.16 and 1.16.1 tell whether to keep the light of the previous column.
+fn is_ignoring_old_data(version: &ProtocolVersion) -> bool {
+    *version >= ProtocolVersionEnum::V1_16 && *version < ProtocolVersionEnum::V1_16_2
+}
+
+/// A count of items taking at least `min_len` bytes each, which the frame
+/// must still have room for.
+fn decode_len<R: io::Read>(src: &mut DecodeContext<R>, min_len: usize) -> io::Result<usize> {
+    let len = VarNum::<i32>::decode(src)?;
+    if len < 0 || len as usize > src.remaining() / min_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} items in {} bytes", len, src.remaining()),
+        ));
+    }
+
+    Ok(len as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VALUES: [u32; 24] = [
+        1, 2, 2, 3, 4, 4, 5, 6, 6, 4, 8, 0, 7, 4, 3, 13, 15, 16, 9, 14, 10, 12, 0, 2,
+    ];
+
+    #[test]
+    fn test_compacted_long_array() {
+        let mut spanning = CompactedLongArray::new(5, VALUES.len(), false);
+        let mut padded = CompactedLongArray::new(5, VALUES.len(), true);
+        for (index, value) in VALUES.iter().enumerate() {
+            spanning.set(index, *value);
+            padded.set(index, *value);
+        }
+
+        assert_eq!(
+            spanning.as_longs(),
+            [0x7020863148418841, 0x001018A7260F68C8]
+        );
+        assert_eq!(padded.as_longs(), [0x0020863148418841, 0x01018A7260F68C87]);
+        assert!(spanning.iter().eq(VALUES.iter().copied()));
+        assert!(padded.iter().eq(VALUES.iter().copied()));
+        assert_eq!(spanning.repacked(5, true), padded);
+
+        spanning.set(12, 31);
+        assert_eq!(spanning.get(12), 31);
+        assert_eq!(spanning.get(11), 0);
+        assert_eq!(spanning.get(13), 4);
+
+        assert_eq!(CompactedLongArray::longs_len(14, 4096, false), 896);
+        assert_eq!(CompactedLongArray::longs_len(14, 4096, true), 1024);
+        assert!(CompactedLongArray::from_longs(5, 24, true, vec![0; 3]).is_err());
+    }
+
+    #[test]
+    fn test_palette_resize() {
+        let version = ProtocolVersionEnum::V1_15_2.into();
+        let mut container = PalettedContainer::new(&version);
+        assert_eq!(container.bits_per_block(), 4);
+
+        for index in 0..16 {
+            container.set(index, index as u32 * 16);
+        }
+        assert_eq!(container.bits_per_block(), 4);
+        container.set(16, 1000);
+        assert_eq!(container.bits_per_block(), 5);
+
+        for index in 0..256 {
+            container.set(index + 17, index as u32 + 2000);
+        }
+        assert_eq!(container.palette(), &Palette::Direct);
+        assert_eq!(container.bits_per_block(), 14);
+
+        assert_eq!(container.get(15), 240);
+        assert_eq!(container.get(16), 1000);
+        assert_eq!(container.get(272), 2255);
+        assert_eq!(container.get(4095), 0);
+        assert_eq!(container.non_air_count(), 272);
+    }
+
+    fn round_trip(container: &PalettedContainer, version: &ProtocolVersion) -> Vec<u8> {
+        let mut buf = Vec::new();
+        container.encode(&mut buf, version).unwrap();
+        assert_eq!(buf.len(), container.calculate_len(version));
+
+        let mut src = DecodeContext::from(&buf[..]);
+        let decoded = PalettedContainer::decode(&mut src, version).unwrap();
+        assert!(src.is_empty());
+        assert!((0..SECTION_VOLUME).all(|index| decoded.get(index) == container.get(index)));
+
+        buf
+    }
+
+    #[test]
+    fn test_versions() {
+        let v1_12: ProtocolVersion = ProtocolVersionEnum::V1_12_2.into();
+        let v1_13: ProtocolVersion = ProtocolVersionEnum::V1_13_2.into();
+        let v1_16: ProtocolVersion = ProtocolVersionEnum::V1_16_5.into();
+
+        let mut container = PalettedContainer::new(&v1_12);
+        container.set(0, 1 << 4);
+        container.set(4095, 2 << 4 | 1);
+
+        let buf = round_trip(&container, &v1_12);
+        assert_eq!(&buf[..7], [4, 3, 0, 16, 33, 0x80, 0x02]);
+        // the same container repacked for 1.16
+        let buf = round_trip(&container, &v1_16);
+        assert_eq!(&buf[5..7], [0x80, 0x02]);
+
+        for index in 0..300 {
+            container.set(index, index as u32);
+        }
+        let buf = round_trip(&container, &v1_12);
+        assert_eq!(&buf[..4], [13, 0, 0xC0, 0x06]);
+        let buf = round_trip(&container, &v1_13);
+        assert_eq!(&buf[..3], [14, 0x80, 0x07]);
+        round_trip(&container, &v1_16);
+
+        let mut buf = round_trip(&PalettedContainer::new(&v1_16), &v1_16);
+        // an index past the single state of the palette
+        buf[5] = 1;
+        assert!(PalettedContainer::decode(&mut DecodeContext::from(&buf[..]), &v1_16).is_err());
+
+        // bits per block of 0, over the global palette and far over a long
+        for (bits, version) in [(0, &v1_16), (14, &v1_12), (15, &v1_13), (100, &v1_16)] {
+            let err = PalettedContainer::decode(&mut DecodeContext::from(&[bits, 0][..]), version)
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_state_too_wide() {
+        let mut container = PalettedContainer::new(&ProtocolVersionEnum::V1_12_2.into());
+        container.set(0, 1 << 13);
+    }
+
+    #[test]
+    fn test_state_too_wide_for_version() {
+        let v1_12: ProtocolVersion = ProtocolVersionEnum::V1_12_2.into();
+        let mut container = PalettedContainer::new(&ProtocolVersionEnum::V1_16_5.into());
+        for index in 0..300 {
+            container.set(index, index as u32);
+        }
+        container.encode(&mut Vec::new(), &v1_12).unwrap();
+
+        container.set(0, 1 << 13);
+        assert!(container.encode(&mut Vec::new(), &v1_12).is_err());
+    }
+
+    fn chunk_round_trip(
+        packet: &PalettedChunkData,
+        version: &ProtocolVersion,
+    ) -> PalettedChunkData {
+        let mut buf = Vec::new();
+        packet.encode(&mut buf, version).unwrap();
+        assert_eq!(buf.len(), packet.calculate_len(version));
+
+        let mut src = DecodeContext::from(&buf[..]);
+        let decoded = PalettedChunkData::decode(&mut src, version).unwrap();
+        assert!(src.is_empty());
+
+        decoded
+    }
+
+    #[test]
+    fn test_columns() {
+        // (version, bytes of a section besides its blocks, biome bytes in the data)
+        let versions = [
+            (ProtocolVersionEnum::V1_9_4, 2 * LIGHT_BYTES, 256),
+            (ProtocolVersionEnum::V1_12_2, 2 * LIGHT_BYTES, 256),
+            (ProtocolVersionEnum::V1_13_2, 2 * LIGHT_BYTES, 1024),
+            (ProtocolVersionEnum::V1_14, 2, 1024),
+            (ProtocolVersionEnum::V1_15_2, 2, 0),
+            (ProtocolVersionEnum::V1_16_5, 2, 0),
+        ];
+        // 4 bits, the palette and 256 longs, air always being in the palette
+        let blocks_len = |palette_len: usize| 1 + 1 + palette_len + 2 + 256 * 8;
+
+        for (version, extra_len, biomes_data_len) in versions.iter() {
+            let version: ProtocolVersion = (*version).into();
+            let mut column = PalettedColumn::new(&version);
+            assert_eq!(column.primary_bit_mask(), 0);
+
+            column.set_block(1, 2, 3, 16);
+            column.set_block(0, 255, 0, 0);
+            column.set_block(0, 17, 0, 32);
+            column.set_block(0, 17, 0, 0);
+            column.biomes_mut()[3] = 4;
+            assert_eq!(column.primary_bit_mask(), 0b1);
+            assert!(column.section(1).unwrap().is_empty());
+            assert!(column.section(15).is_none());
+
+            let section = column.section_mut(0).unwrap();
+            section.set_block_light(1, 2, 3, 7);
+            section.set_sky_light(1, 2, 3, 0);
+
+            let packet = column
+                .to_chunk_data(ChunkPosition::new(-1, 2), true)
+                .unwrap();
+            assert_eq!(
+                packet.data.len(),
+                blocks_len(2) + extra_len + biomes_data_len
+            );
+
+            let decoded = chunk_round_trip(&packet, &version);
+            assert_eq!(decoded.position, ChunkPosition::new(-1, 2));
+            assert!(decoded.full_chunk);
+
+            let decoded = PalettedColumn::from_chunk_data(&decoded, true, &version).unwrap();
+            assert_eq!(decoded.block(1, 2, 3), 16);
+            assert_eq!(decoded.block(1, 3, 3), 0);
+            assert_eq!(decoded.biomes(), column.biomes());
+            assert!(decoded.section(1).is_none());
+
+            let section = decoded.section(0).unwrap();
+            if version < ProtocolVersionEnum::V1_14 {
+                assert_eq!(section.block_light(1, 2, 3), 7);
+                assert_eq!(section.sky_light(1, 2, 3), 0);
+            } else {
+                assert_eq!(section.block_light(1, 2, 3), 0);
+                assert_eq!(section.sky_light(1, 2, 3), 15);
+                // the block count before the palette
+                assert_eq!(&packet.data[..2], [0, 1]);
+            }
+
+            // a section update leaves the biomes out
+            let update = column
+                .to_section_update(ChunkPosition::new(-1, 2), 0b10, true)
+                .unwrap();
+            // air and the state set then cleared again
+            assert_eq!(update.data.len(), blocks_len(2) + extra_len);
+            let mut decoded = column.clone();
+            decoded.set_block(1, 2, 3, 0);
+            decoded
+                .decode_data(&chunk_round_trip(&update, &version).data, 0b10, false, true)
+                .unwrap();
+            assert_eq!(decoded.block(1, 2, 3), 0);
+            assert!(decoded.section(1).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_column_errors() {
+        let v1_12: ProtocolVersion = ProtocolVersionEnum::V1_12_2.into();
+        let mut column = PalettedColumn::new(&v1_12);
+        column.biomes_mut()[0] = 256;
+        assert!(column
+            .to_chunk_data(ChunkPosition::new(0, 0), true)
+            .is_err());
+
+        let column = PalettedColumn::new(&v1_12);
+        let mut packet = column
+            .to_chunk_data(ChunkPosition::new(0, 0), true)
+            .unwrap();
+        assert!(PalettedColumn::from_chunk_data(&packet, true, &v1_12).is_ok());
+        // a section the mask doesn't cover
+        packet.data.extend_from_slice(&[0; 8]);
+        assert!(PalettedColumn::from_chunk_data(&packet, true, &v1_12).is_err());
+
+        let v1_16: ProtocolVersion = ProtocolVersionEnum::V1_16_5.into();
+        let mut packet = PalettedColumn::new(&v1_16)
+            .to_chunk_data(ChunkPosition::new(0, 0), true)
+            .unwrap();
+        packet.biomes.as_mut().unwrap().pop();
+        assert!(packet.encode(&mut Vec::new(), &v1_16).is_err());
+    }
+
+    #[test]
+    fn test_chunk_data_malformed_nbt() {
+        // a compound holding a byte array of -1 bytes
+        let nbt = [0x0A, 0x00, 0x00, 0x07, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF];
+        // at 0, 0 and not a full chunk, without any section
+        let header = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        let heightmaps = [&header[..], &nbt].concat();
+        let block_entities = [&header[..], &[0, 1], &nbt].concat();
+        for (frame, version) in [
+            (heightmaps, ProtocolVersionEnum::V1_14),
+            (block_entities, ProtocolVersionEnum::V1_12_2),
+        ] {
+            let err =
+                PalettedChunkData::decode(&mut DecodeContext::from(&frame[..]), &version.into())
+                    .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,797 @@
+//! Typed payloads of the well-known `PluginMessage` channels.
+//!
+//! Channel names were namespaced in 1.13, so every conversion takes the
+//! version the packet is sent with.
+
+use std::{
+    borrow::Cow,
+    io::{self, Error, ErrorKind, Read, Write},
+};
+
+use protocol_internal::{
+    DecodeContext, ProtocolSupportDecoder, ProtocolSupportEncoder, ProtocolVersion,
+    ProtocolVersionEnum,
+};
+
+use super::{client_bound, server_bound};
+
+/// The channels both sides can parse into a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// The name of the client or server software.
+    Brand,
+    /// Channels the sender listens to.
+    Register,
+    /// Channels the sender stopped listening to.
+    Unregister,
+    /// A book and quill being edited, until 1.13 made it a packet.
+    BookEdit,
+    /// A book and quill being signed, until 1.13 made it a packet.
+    BookSign,
+    /// Requests to and answers from a BungeeCord proxy.
+    BungeeCord,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 6] = [
+        Self::Brand,
+        Self::Register,
+        Self::Unregister,
+        Self::BookEdit,
+        Self::BookSign,
+        Self::BungeeCord,
+    ];
+
+    /// The channel's name in `version`, `None` when it does not exist there.
+    pub fn name(self, version: &ProtocolVersion) -> Option<&'static str> {
+        let namespaced = *version >= ProtocolVersionEnum::V1_13;
+
+        Some(match (self, namespaced) {
+            (Self::Brand, false) => "MC|Brand",
+            (Self::Brand, true) => "minecraft:brand",
+            (Self::Register, false) => "REGISTER",
+            (Self::Register, true) => "minecraft:register",
+            (Self::Unregister, false) => "UNREGISTER",
+            (Self::Unregister, true) => "minecraft:unregister",
+            (Self::BookEdit, false) => "MC|BEdit",
+            (Self::BookSign, false) => "MC|BSign",
+            (Self::BookEdit, true) | (Self::BookSign, true) => return None,
+            (Self::BungeeCord, false) => "BungeeCord",
+            (Self::BungeeCord, true) => "bungeecord:main",
+        })
+    }
+
+    pub fn from_name(name: &str, version: &ProtocolVersion) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|channel| channel.name(version) == Some(name))
+    }
+}
+
+/// A plugin message sent by the client, or by a proxy to the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerBoundMessage {
+    Brand(String),
+    Register(Vec<String>),
+    Unregister(Vec<String>),
+    /// The edited book, as an encoded slot.
+    BookEdit(Vec<u8>),
+    /// The signed book, as an encoded slot.
+    BookSign(Vec<u8>),
+    BungeeCord(BungeeCordResponse),
+    Unknown {
+        channel: String,
+        data: Vec<u8>,
+    },
+}
+
+impl ServerBoundMessage {
+    pub fn from_packet(
+        packet: &server_bound::PluginMessage,
+        version: &ProtocolVersion,
+    ) -> io::Result<Self> {
+        let data = &packet.data[..];
+
+        Ok(match Channel::from_name(&packet.channel, version) {
+            Some(Channel::Brand) => Self::Brand(decode_brand(data, version)?),
+            Some(Channel::Register) => Self::Register(decode_channels(data)?),
+            Some(Channel::Unregister) => Self::Unregister(decode_channels(data)?),
+            Some(Channel::BookEdit) => Self::BookEdit(data.to_vec()),
+            Some(Channel::BookSign) => Self::BookSign(data.to_vec()),
+            Some(Channel::BungeeCord) => Self::BungeeCord(BungeeCordResponse::decode(data)?),
+            None => Self::Unknown {
+                channel: packet.channel.clone(),
+                data: data.to_vec(),
+            },
+        })
+    }
+
+    pub fn to_packet(&self, version: &ProtocolVersion) -> io::Result<server_bound::PluginMessage> {
+        let (channel, data) = match self {
+            Self::Brand(brand) => (Channel::Brand, encode_brand(brand, version)?),
+            Self::Register(channels) => (Channel::Register, encode_channels(channels)),
+            Self::Unregister(channels) => (Channel::Unregister, encode_channels(channels)),
+            Self::BookEdit(book) => (Channel::BookEdit, book.clone()),
+            Self::BookSign(book) => (Channel::BookSign, book.clone()),
+            Self::BungeeCord(response) => (Channel::BungeeCord, response.encode()?),
+            Self::Unknown { channel, data } => {
+                return Ok(server_bound::PluginMessage {
+                    channel: channel.clone(),
+                    data: data.clone().into(),
+                })
+            }
+        };
+
+        Ok(server_bound::PluginMessage {
+            channel: channel_name(channel, version)?.into(),
+            data: data.into(),
+        })
+    }
+}
+
+/// A plugin message sent by the server, to the client or to a proxy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientBoundMessage {
+    Brand(String),
+    Register(Vec<String>),
+    Unregister(Vec<String>),
+    BungeeCord(BungeeCordRequest),
+    Unknown { channel: String, data: Vec<u8> },
+}
+
+impl ClientBoundMessage {
+    pub fn from_packet(
+        packet: &client_bound::PluginMessage,
+        version: &ProtocolVersion,
+    ) -> io::Result<Self> {
+        let data = &packet.data[..];
+
+        Ok(match Channel::from_name(&packet.channel, version) {
+            Some(Channel::Brand) => Self::Brand(decode_brand(data, version)?),
+            Some(Channel::Register) => Self::Register(decode_channels(data)?),
+            Some(Channel::Unregister) => Self::Unregister(decode_channels(data)?),
+            Some(Channel::BungeeCord) => Self::BungeeCord(BungeeCordRequest::decode(data)?),
+            Some(Channel::BookEdit) | Some(Channel::BookSign) | None => Self::Unknown {
+                channel: packet.channel.to_string(),
+                data: data.to_vec(),
+            },
+        })
+    }
+
+    pub fn to_packet(
+        &self,
+        version: &ProtocolVersion,
+    ) -> io::Result<client_bound::PluginMessage<'static>> {
+        let (channel, data) = match self {
+            Self::Brand(brand) => (Channel::Brand, encode_brand(brand, version)?),
+            Self::Register(channels) => (Channel::Register, encode_channels(channels)),
+            Self::Unregister(channels) => (Channel::Unregister, encode_channels(channels)),
+            Self::BungeeCord(request) => (Channel::BungeeCord, request.encode()?),
+            Self::Unknown { channel, data } => {
+                return Ok(client_bound::PluginMessage {
+                    channel: Cow::Owned(channel.clone()),
+                    data: data.clone().into(),
+                })
+            }
+        };
+
+        Ok(client_bound::PluginMessage {
+            channel: Cow::Borrowed(channel_name(channel, version)?),
+            data: data.into(),
+        })
+    }
+}
+
+fn channel_name(channel: Channel, version: &ProtocolVersion) -> io::Result<&'static str> {
+    channel.name(version).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("{:?} has no channel in {:?}", channel, version),
+        )
+    })
+}
+
+fn decode_brand(data: &[u8], version: &ProtocolVersion) -> io::Result<String> {
+    String::decode(&mut DecodeContext::from(data), version)
+}
+
+fn encode_brand(brand: &str, version: &ProtocolVersion) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(brand.calculate_len(version));
+    brand.encode(&mut buf, version)?;
+    Ok(buf)
+}
+
+fn decode_channels(data: &[u8]) -> io::Result<Vec<String>> {
+    std::str::from_utf8(data)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+        .map(|channels| {
+            channels
+                .split('\0')
+                .filter(|channel| !channel.is_empty())
+                .map(String::from)
+                .collect()
+        })
+}
+
+fn encode_channels(channels: &[String]) -> Vec<u8> {
+    channels.join("\0").into_bytes()
+}
+
+/// A request a server sends through the BungeeCord channel, handled by the
+/// proxy instead of the player's client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BungeeCordRequest {
+    /// Sends the player to another server.
+    Connect {
+        server: String,
+    },
+    ConnectOther {
+        player: String,
+        server: String,
+    },
+    IP,
+    IPOther {
+        player: String,
+    },
+    /// `server` can be `ALL`.
+    PlayerCount {
+        server: String,
+    },
+    /// `server` can be `ALL`.
+    PlayerList {
+        server: String,
+    },
+    GetServers,
+    /// `player` can be `ALL`.
+    Message {
+        player: String,
+        message: String,
+    },
+    /// Like `Message`, with a json chat component.
+    MessageRaw {
+        player: String,
+        message: String,
+    },
+    GetServer,
+    /// Relays `data` to the servers `server` names, `ALL` or `ONLINE`.
+    Forward {
+        server: String,
+        channel: String,
+        data: Vec<u8>,
+    },
+    ForwardToPlayer {
+        player: String,
+        channel: String,
+        data: Vec<u8>,
+    },
+    UUID,
+    UUIDOther {
+        player: String,
+    },
+    ServerIP {
+        server: String,
+    },
+    KickPlayer {
+        player: String,
+        reason: String,
+    },
+}
+
+impl BungeeCordRequest {
+    pub fn decode(mut data: &[u8]) -> io::Result<Self> {
+        let src = &mut data;
+
+        let request = match &read_utf(src)?[..] {
+            "Connect" => Self::Connect {
+                server: read_utf(src)?,
+            },
+            "ConnectOther" => Self::ConnectOther {
+                player: read_utf(src)?,
+                server: read_utf(src)?,
+            },
+            "IP" => Self::IP,
+            "IPOther" => Self::IPOther {
+                player: read_utf(src)?,
+            },
+            "PlayerCount" => Self::PlayerCount {
+                server: read_utf(src)?,
+            },
+            "PlayerList" => Self::PlayerList {
+                server: read_utf(src)?,
+            },
+            "GetServers" => Self::GetServers,
+            "Message" => Self::Message {
+                player: read_utf(src)?,
+                message: read_utf(src)?,
+            },
+            "MessageRaw" => Self::MessageRaw {
+                player: read_utf(src)?,
+                message: read_utf(src)?,
+            },
+            "GetServer" => Self::GetServer,
+            "Forward" => Self::Forward {
+                server: read_utf(src)?,
+                channel: read_utf(src)?,
+                data: read_short_bytes(src)?,
+            },
+            "ForwardToPlayer" => Self::ForwardToPlayer {
+                player: read_utf(src)?,
+                channel: read_utf(src)?,
+                data: read_short_bytes(src)?,
+            },
+            "UUID" => Self::UUID,
+            "UUIDOther" => Self::UUIDOther {
+                player: read_utf(src)?,
+            },
+            "ServerIP" => Self::ServerIP {
+                server: read_utf(src)?,
+            },
+            "KickPlayer" => Self::KickPlayer {
+                player: read_utf(src)?,
+                reason: read_utf(src)?,
+            },
+            subchannel => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unknown BungeeCord subchannel {}", subchannel),
+                ))
+            }
+        };
+
+        Ok(request)
+    }
+
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let dst = &mut buf;
+
+        match self {
+            Self::Connect { server } => {
+                write_utf(dst, "Connect")?;
+                write_utf(dst, server)?;
+            }
+            Self::ConnectOther { player, server } => {
+                write_utf(dst, "ConnectOther")?;
+                write_utf(dst, player)?;
+                write_utf(dst, server)?;
+            }
+            Self::IP => write_utf(dst, "IP")?,
+            Self::IPOther { player } => {
+                write_utf(dst, "IPOther")?;
+                write_utf(dst, player)?;
+            }
+            Self::PlayerCount { server } => {
+                write_utf(dst, "PlayerCount")?;
+                write_utf(dst, server)?;
+            }
+            Self::PlayerList { server } => {
+                write_utf(dst, "PlayerList")?;
+                write_utf(dst, server)?;
+            }
+            Self::GetServers => write_utf(dst, "GetServers")?,
+            Self::Message { player, message } => {
+                write_utf(dst, "Message")?;
+                write_utf(dst, player)?;
+                write_utf(dst, message)?;
+            }
+            Self::MessageRaw { player, message } => {
+                write_utf(dst, "MessageRaw")?;
+                write_utf(dst, player)?;
+                write_utf(dst, message)?;
+            }
+            Self::GetServer => write_utf(dst, "GetServer")?,
+            Self::Forward {
+                server,
+                channel,
+                data,
+            } => {
+                write_utf(dst, "Forward")?;
+                write_utf(dst, server)?;
+                write_utf(dst, channel)?;
+                write_short_bytes(dst, data)?;
+            }
+            Self::ForwardToPlayer {
+                player,
+                channel,
+                data,
+            } => {
+                write_utf(dst, "ForwardToPlayer")?;
+                write_utf(dst, player)?;
+                write_utf(dst, channel)?;
+                write_short_bytes(dst, data)?;
+            }
+            Self::UUID => write_utf(dst, "UUID")?,
+            Self::UUIDOther { player } => {
+                write_utf(dst, "UUIDOther")?;
+                write_utf(dst, player)?;
+            }
+            Self::ServerIP { server } => {
+                write_utf(dst, "ServerIP")?;
+                write_utf(dst, server)?;
+            }
+            Self::KickPlayer { player, reason } => {
+                write_utf(dst, "KickPlayer")?;
+                write_utf(dst, player)?;
+                write_utf(dst, reason)?;
+            }
+        }
+
+        Ok(buf)
+    }
+}
+
+/// The proxy's answer to a [`BungeeCordRequest`], sent to the server through
+/// the BungeeCord channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BungeeCordResponse {
+    IP {
+        ip: String,
+        port: i32,
+    },
+    IPOther {
+        player: String,
+        ip: String,
+        port: i32,
+    },
+    PlayerCount {
+        server: String,
+        count: i32,
+    },
+    PlayerList {
+        server: String,
+        players: Vec<String>,
+    },
+    GetServers {
+        servers: Vec<String>,
+    },
+    GetServer {
+        server: String,
+    },
+    UUID {
+        uuid: String,
+    },
+    UUIDOther {
+        player: String,
+        uuid: String,
+    },
+    ServerIP {
+        server: String,
+        ip: String,
+        port: u16,
+    },
+    /// Data relayed from another server by a `Forward` request.
+    Forwarded {
+        channel: String,
+        data: Vec<u8>,
+    },
+}
+
+impl BungeeCordResponse {
+    pub fn decode(mut data: &[u8]) -> io::Result<Self> {
+        let src = &mut data;
+
+        let response = match &read_utf(src)?[..] {
+            "IP" => Self::IP {
+                ip: read_utf(src)?,
+                port: read_i32(src)?,
+            },
+            "IPOther" => Self::IPOther {
+                player: read_utf(src)?,
+                ip: read_utf(src)?,
+                port: read_i32(src)?,
+            },
+            "PlayerCount" => Self::PlayerCount {
+                server: read_utf(src)?,
+                count: read_i32(src)?,
+            },
+            "PlayerList" => Self::PlayerList {
+                server: read_utf(src)?,
+                players: read_list(src)?,
+            },
+            "GetServers" => Self::GetServers {
+                servers: read_list(src)?,
+            },
+            "GetServer" => Self::GetServer {
+                server: read_utf(src)?,
+            },
+            "UUID" => Self::UUID {
+                uuid: read_utf(src)?,
+            },
+            "UUIDOther" => Self::UUIDOther {
+                player: read_utf(src)?,
+                uuid: read_utf(src)?,
+            },
+            "ServerIP" => Self::ServerIP {
+                server: read_utf(src)?,
+                ip: read_utf(src)?,
+                port: read_u16(src)?,
+            },
+            channel => Self::Forwarded {
+                channel: channel.into(),
+                data: read_short_bytes(src)?,
+            },
+        };
+
+        Ok(response)
+    }
+
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let dst = &mut buf;
+
+        match self {
+            Self::IP { ip, port } => {
+                write_utf(dst, "IP")?;
+                write_utf(dst, ip)?;
+                dst.write_all(&port.to_be_bytes())?;
+            }
+            Self::IPOther { player, ip, port } => {
+                write_utf(dst, "IPOther")?;
+                write_utf(dst, player)?;
+                write_utf(dst, ip)?;
+                dst.write_all(&port.to_be_bytes())?;
+            }
+            Self::PlayerCount { server, count } => {
+                write_utf(dst, "PlayerCount")?;
+                write_utf(dst, server)?;
+                dst.write_all(&count.to_be_bytes())?;
+            }
+            Self::PlayerList { server, players } => {
+                write_utf(dst, "PlayerList")?;
+                write_utf(dst, server)?;
+                write_utf(dst, &players.join(", "))?;
+            }
+            Self::GetServers { servers } => {
+                write_utf(dst, "GetServers")?;
+                write_utf(dst, &servers.join(", "))?;
+            }
+            Self::GetServer { server } => {
+                write_utf(dst, "GetServer")?;
+                write_utf(dst, server)?;
+            }
+            Self::UUID { uuid } => {
+                write_utf(dst, "UUID")?;
+                write_utf(dst, uuid)?;
+            }
+            Self::UUIDOther { player, uuid } => {
+                write_utf(dst, "UUIDOther")?;
+                write_utf(dst, player)?;
+                write_utf(dst, uuid)?;
+            }
+            Self::ServerIP { server, ip, port } => {
+                write_utf(dst, "ServerIP")?;
+                write_utf(dst, server)?;
+                write_utf(dst, ip)?;
+                dst.write_all(&port.to_be_bytes())?;
+            }
+            Self::Forwarded { channel, data } => {
+                write_utf(dst, channel)?;
+                write_short_bytes(dst, data)?;
+            }
+        }
+
+        Ok(buf)
+    }
+}
+
+fn read_u16(src: &mut &[u8]) -> io::Result<u16> {
+    let mut buf = [0; 2];
+    src.read_exact(&mut buf)?;
+    Ok(u16::from_be_bytes(buf))
+}
+
+fn read_i32(src: &mut &[u8]) -> io::Result<i32> {
+    let mut buf = [0; 4];
+    src.read_exact(&mut buf)?;
+    Ok(i32::from_be_bytes(buf))
+}
+
+fn read_short_bytes(src: &mut &[u8]) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; read_u16(src)? as usize];
+    src.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn write_short_bytes(dst: &mut Vec<u8>, data: &[u8]) -> io::Result<()> {
+    if data.len() > u16::MAX as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "forwarded data is longer than 65535 bytes",
+        ));
+    }
+
+    dst.write_all(&(data.len() as u16).to_be_bytes())?;
+    dst.write_all(data)
+}
+
+/// A `", "` separated list, empty when the string is.
+fn read_list(src: &mut &[u8]) -> io::Result<Vec<String>> {
+    let list = read_utf(src)?;
+    if list.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(list.split(", ").map(String::from).collect())
+}
+
+/// Reads a string written by Java's `DataOutput::writeUTF`, a u16 len
+/// followed by modified UTF-8.
+fn read_utf(src: &mut &[u8]) -> io::Result<String> {
+    let mut buf = vec![0; read_u16(src)? as usize];
+    src.read_exact(&mut buf)?;
+
+    let invalid = || Error::new(ErrorKind::InvalidData, "malformed modified utf-8");
+
+    let mut units = Vec::with_capacity(buf.len());
+    let mut bytes = buf.into_iter();
+    while let Some(byte) = bytes.next() {
+        let mut continuation = || match bytes.next() {
+            Some(byte) if byte & 0xC0 == 0x80 => Ok(u16::from(byte & 0x3F)),
+            _ => Err(invalid()),
+        };
+
+        units.push(match byte {
+            0x00..=0x7F => u16::from(byte),
+            0xC0..=0xDF => (u16::from(byte & 0x1F) << 6) | continuation()?,
+            0xE0..=0xEF => {
+                (u16::from(byte & 0x0F) << 12) | (continuation()? << 6) | continuation()?
+            }
+            _ => return Err(invalid()),
+        });
+    }
+
+    String::from_utf16(&units).map_err(|_| invalid())
+}
+
+/// Writes a string the way Java's `DataOutput::writeUTF` does, with nul as
+/// two bytes and every char outside the BMP as two 3 byte surrogates.
+fn write_utf(dst: &mut Vec<u8>, string: &str) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(string.len());
+    for unit in string.encode_utf16() {
+        match unit {
+            0x01..=0x7F => buf.push(unit as u8),
+            0x00 | 0x80..=0x7FF => {
+                buf.push(0xC0 | (unit >> 6) as u8);
+                buf.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                buf.push(0xE0 | (unit >> 12) as u8);
+                buf.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                buf.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+
+    if buf.len() > u16::MAX as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "string is longer than 65535 bytes",
+        ));
+    }
+
+    dst.write_all(&(buf.len() as u16).to_be_bytes())?;
+    dst.write_all(&buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn v1_8() -> ProtocolVersion {
+        ProtocolVersionEnum::V1_8.into()
+    }
+
+    fn v1_13() -> ProtocolVersion {
+        ProtocolVersionEnum::V1_13.into()
+    }
+
+    #[test]
+    fn test_channel_names() {
+        assert_eq!(Channel::Brand.name(&v1_8()), Some("MC|Brand"));
+        assert_eq!(Channel::Brand.name(&v1_13()), Some("minecraft:brand"));
+        assert_eq!(Channel::BookSign.name(&v1_13()), None);
+        assert_eq!(
+            Channel::from_name("bungeecord:main", &v1_13()),
+            Some(Channel::BungeeCord)
+        );
+        assert_eq!(Channel::from_name("bungeecord:main", &v1_8()), None);
+    }
+
+    #[test]
+    fn test_brand_and_register() {
+        let packet = server_bound::PluginMessage {
+            channel: "MC|Brand".into(),
+            data: b"\x07vanilla".to_vec().into(),
+        };
+        assert_eq!(
+            ServerBoundMessage::from_packet(&packet, &v1_8()).unwrap(),
+            ServerBoundMessage::Brand("vanilla".into())
+        );
+
+        let register = ClientBoundMessage::Register(vec!["a:b".into(), "c:d".into()]);
+        let packet = register.to_packet(&v1_13()).unwrap();
+        assert_eq!(packet.channel, "minecraft:register");
+        assert_eq!(&packet.data[..], b"a:b\0c:d");
+        assert_eq!(
+            ClientBoundMessage::from_packet(&packet, &v1_13()).unwrap(),
+            register
+        );
+
+        // the 1.8 name means nothing to a 1.13 client
+        let packet = ServerBoundMessage::Brand("vanilla".into())
+            .to_packet(&v1_8())
+            .unwrap();
+        assert!(matches!(
+            ServerBoundMessage::from_packet(&packet, &v1_13()).unwrap(),
+            ServerBoundMessage::Unknown { .. }
+        ));
+        assert!(ServerBoundMessage::BookSign(Vec::new())
+            .to_packet(&v1_13())
+            .is_err());
+    }
+
+    #[test]
+    fn test_bungeecord_request() {
+        let request = BungeeCordRequest::Forward {
+            server: "ALL".into(),
+            channel: "test".into(),
+            data: vec![1, 2, 3],
+        };
+        let data = request.encode().unwrap();
+        assert_eq!(
+            data,
+            b"\x00\x07Forward\x00\x03ALL\x00\x04test\x00\x03\x01\x02\x03"
+        );
+        assert_eq!(BungeeCordRequest::decode(&data).unwrap(), request);
+
+        let request = BungeeCordRequest::Connect {
+            server: "lobby".into(),
+        };
+        let packet = ClientBoundMessage::BungeeCord(request.clone())
+            .to_packet(&v1_8())
+            .unwrap();
+        assert_eq!(packet.channel, "BungeeCord");
+        assert_eq!(
+            ClientBoundMessage::from_packet(&packet, &v1_8()).unwrap(),
+            ClientBoundMessage::BungeeCord(request)
+        );
+    }
+
+    #[test]
+    fn test_bungeecord_response() {
+        let responses = [
+            BungeeCordResponse::PlayerCount {
+                server: "lobby".into(),
+                count: 12,
+            },
+            BungeeCordResponse::PlayerList {
+                server: "lobby".into(),
+                players: vec!["Alice".into(), "Bob".into()],
+            },
+            BungeeCordResponse::GetServers {
+                servers: Vec::new(),
+            },
+            BungeeCordResponse::Forwarded {
+                channel: "test".into(),
+                data: vec![4, 5],
+            },
+        ];
+
+        for response in responses.iter() {
+            let data = response.encode().unwrap();
+            assert_eq!(&BungeeCordResponse::decode(&data).unwrap(), response);
+        }
+    }
+
+    #[test]
+    fn test_modified_utf8() {
+        let mut buf = Vec::new();
+        write_utf(&mut buf, "a\0é😀").unwrap();
+        assert_eq!(
+            buf,
+            b"\x00\x0Ba\xC0\x80\xC3\xA9\xED\xA0\xBD\xED\xB8\x80".to_vec()
+        );
+        assert_eq!(read_utf(&mut &buf[..]).unwrap(), "a\0é😀");
+    }
+}
@@ -10,6 +10,11 @@ packet_enum! {
             #[protocol_field(range(min = 1, max = 100))]
             message: String
         },
+        0x02 => UseEntity {
+            #[protocol_field(varnum)]
+            target: i32,
+            action: UseEntityAction
+        },
         0x03 => Player {
             on_ground: bool
         },
@@ -31,6 +36,16 @@ packet_enum! {
             location: Vec3D<i32>,
             face: i8
         },
+        0x08 => PlayerBlockPlacement {
+            #[protocol_field(position)]
+            location: BlockPosition,
+            // the face clicked, or -1 with a location of -1 on every axis
+            // when using the held item without aiming at a block
+            face: i8,
+            held_item: Slot,
+            /// Where the face was clicked, from 0 to 15 on each axis.
+            cursor_position: Vec3D<u8>
+        },
         0x09 => HeldItemChange {
             slot: i16
         },
@@ -42,20 +57,49 @@ packet_enum! {
             #[protocol_field(varnum)]
             action_parameter: i32
         },
+        0x0C => SteerVehicle {
+            /// Positive to the left of the player.
+            sideways: f32,
+            /// Positive forward.
+            forward: f32,
+            flags: SteerVehicleFlags
+        },
         0x0D => CloseWindow {
             window_id: u8
         },
+        0x0E => ClickWindow,
+        0x0F => ConfirmTransaction {
+            window_id: i8,
+            action_number: i16,
+            accepted: bool
+        },
         0x10 => CreativeInventoryAction {
+            /// `-1` drops the item outside the inventory.
             slot: i16,
-            #[protocol_field(dynarray)]
-            clicked_item: Vec<u8>
+            /// Empty to delete the item in `slot`.
+            clicked_item: Slot
+        },
+        0x11 => EnchantItem {
+            window_id: i8,
+            /// The position of the enchantment in the table, from 0 to 2.
+            enchantment: i8
         },
-        0x14 => TabComplete {
-            #[protocol_field(range(max = 100))]
-            text: String,
+        0x12 => UpdateSign {
             #[protocol_field(position)]
-            position: Option<Vec3D<i32>>
+            location: BlockPosition,
+            // the lines as JSON text, which the client sends as a bare
+            // string like "Hello" for unstyled lines
+            line_1: String,
+            line_2: String,
+            line_3: String,
+            line_4: String
         },
+        0x13 => PlayerAbilities {
+            flags: PlayerAbilitiesFlags,
+            flying_speed: f32,
+            walking_speed: f32
+        },
+        0x14 => TabComplete,
         0x15 => ClientSettings {
             locale: String,
             view_distance: i8,
@@ -66,12 +110,32 @@ packet_enum! {
         0x16 => ClientStatus,
         0x17 => PluginMessage {
             channel: String,
-            #[protocol_field(dynarray)]
-            data: Vec<u8>
+            data: RemainingBytes
+        },
+        0x18 => Spectate {
+            target: Uuid
+        },
+        0x19 => ResourcePackStatus {
+            #[protocol_field(range(max = 40))]
+            hash: String,
+            result: ResourcePackResult
         }
     }
 }
 
+proto_enum! {
+    #[protocol_field(varnum)]
+    UseEntityAction (i32) {
+        Interact = 0,
+        Attack = 1,
+        InteractAt {
+            // where the entity was clicked, relative to its position
+            target: Vec3D<f32>
+        } = 2
+    }
+    default Self::Interact
+}
+
 proto_enum! {
     PlayerDiggingStatus (u8) {
         StartedDigging = 0,
@@ -105,3 +169,587 @@ proto_enum! {
     }
     default Self::PerformRespawn
 }
+
+bitflags::bitflags! {
+    #[derive(protocol_derive::ProtocolSupport)]
+    pub struct SteerVehicleFlags: u8 {
+        const JUMP = 0x01;
+        const UNMOUNT = 0x02;
+    }
+}
+
+impl Default for SteerVehicleFlags {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+bitflags::bitflags! {
+    /// Only `FLYING` is honoured coming from the client, the rest is for
+    /// the server to decide.
+    #[derive(protocol_derive::ProtocolSupport)]
+    pub struct PlayerAbilitiesFlags: u8 {
+        const INVULNERABLE = 0x01;
+        const FLYING = 0x02;
+        const ALLOW_FLYING = 0x04;
+        const CREATIVE_MODE = 0x08;
+    }
+}
+
+impl Default for PlayerAbilitiesFlags {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+proto_enum! {
+    #[protocol_field(varnum)]
+    ResourcePackResult (i32) {
+        Loaded = 0,
+        Declined = 1,
+        Failed = 2,
+        Accepted = 3
+    }
+    default Self::Accepted
+}
+
+/// The chat input to complete, with the block the player looks at.
+#[derive(Clone, Debug, Default)]
+pub struct TabComplete {
+    pub text: String,
+    pub position: Option<BlockPosition>,
+}
+
+impl ProtocolSupportEncoder for TabComplete {
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        self.text.calculate_len(version) + 1 + self.position.as_ref().map_or(0, |_| 8)
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        self.text.encode(dst, version)?;
+        // the position is behind a bool, but still sent as a packed long
+        self.position.is_some().encode(dst, version)?;
+        if let Some(position) = &self.position {
+            ::protocol_internal::ProtocolPositionSupport::encode(position, dst)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProtocolSupportDecoder for TabComplete {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let text =
+            <String as ::protocol_internal::RangeValidatedSupport>::decode(src, version, 0, 100)?;
+        let position = match bool::decode(src, version)? {
+            true => Some(::protocol_internal::ProtocolPositionSupport::decode(src)?),
+            false => None,
+        };
+
+        Ok(Self { text, position })
+    }
+}
+
+/// The slot standing for a click outside of the window.
+pub const OUTSIDE_WINDOW_SLOT: i16 = -999;
+
+/// A click in a window, which the server answers to with a
+/// `ConfirmTransaction` of the same `action_number`.
+#[derive(Clone, Debug, Default)]
+pub struct ClickWindow {
+    pub window_id: u8,
+    /// [`OUTSIDE_WINDOW_SLOT`] when clicking outside of the window.
+    pub slot: i16,
+    pub action_number: i16,
+    pub action: ClickAction,
+    /// What the client saw in the slot before clicking.
+    pub clicked_item: Slot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DragStage {
+    Start,
+    AddSlot,
+    End,
+}
+
+/// The mode and button of a [`ClickWindow`], sent apart but only meaningful
+/// together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClickAction {
+    Click(MouseButton),
+    ShiftClick(MouseButton),
+    /// Swaps the slot with the hotbar slot of the number key, from 0 to 8.
+    NumberKey(u8),
+    /// Picks a full stack of the item, in creative mode only.
+    MiddleClick,
+    /// `Q`, dropping one item or with control the whole stack.
+    Drop {
+        whole_stack: bool,
+    },
+    /// Spreading the held stack over slots, left evenly, right one by one
+    /// and middle as full stacks in creative mode.
+    ///
+    /// Starting and ending are sent with [`OUTSIDE_WINDOW_SLOT`].
+    Drag {
+        button: MouseButton,
+        stage: DragStage,
+    },
+    DoubleClick,
+}
+
+impl Default for ClickAction {
+    fn default() -> Self {
+        Self::Click(MouseButton::Left)
+    }
+}
+
+impl ClickAction {
+    /// The mode and button, as sent.
+    pub fn to_raw(self) -> (i8, i8) {
+        fn button_id(button: MouseButton) -> i8 {
+            match button {
+                MouseButton::Left => 0,
+                MouseButton::Right => 1,
+                MouseButton::Middle => 2,
+            }
+        }
+
+        match self {
+            Self::Click(button) => (0, button_id(button)),
+            Self::ShiftClick(button) => (1, button_id(button)),
+            Self::NumberKey(key) => (2, key as i8),
+            Self::MiddleClick => (3, 2),
+            Self::Drop { whole_stack } => (4, whole_stack as i8),
+            Self::Drag { button, stage } => {
+                let stage = match stage {
+                    DragStage::Start => 0,
+                    DragStage::AddSlot => 1,
+                    DragStage::End => 2,
+                };
+                (5, button_id(button) * 4 + stage)
+            }
+            Self::DoubleClick => (6, 0),
+        }
+    }
+
+    /// The action for a mode and button, if the combination exists.
+    pub fn from_raw(mode: i8, button: i8) -> Option<Self> {
+        let mouse_button = |button| match button {
+            0 => Some(MouseButton::Left),
+            1 => Some(MouseButton::Right),
+            2 => Some(MouseButton::Middle),
+            _ => None,
+        };
+
+        Some(match (mode, button) {
+            (0, 0..=1) => Self::Click(mouse_button(button)?),
+            (1, 0..=1) => Self::ShiftClick(mouse_button(button)?),
+            (2, 0..=8) => Self::NumberKey(button as u8),
+            (3, 2) => Self::MiddleClick,
+            (4, 0..=1) => Self::Drop {
+                whole_stack: button == 1,
+            },
+            (5, 0..=2) | (5, 4..=6) | (5, 8..=10) => Self::Drag {
+                button: mouse_button(button / 4)?,
+                stage: match button % 4 {
+                    0 => DragStage::Start,
+                    1 => DragStage::AddSlot,
+                    _ => DragStage::End,
+                },
+            },
+            (6, 0) => Self::DoubleClick,
+            _ => return None,
+        })
+    }
+}
+
+impl ProtocolSupportEncoder for ClickWindow {
+    fn calculate_len(&self, version: &::protocol_internal::ProtocolVersion) -> usize {
+        self.window_id.calculate_len(version)
+            + self.slot.calculate_len(version)
+            + 1
+            + self.action_number.calculate_len(version)
+            + 1
+            + self.clicked_item.calculate_len(version)
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &::protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        let (mode, button) = self.action.to_raw();
+
+        self.window_id.encode(dst, version)?;
+        self.slot.encode(dst, version)?;
+        button.encode(dst, version)?;
+        self.action_number.encode(dst, version)?;
+        mode.encode(dst, version)?;
+        self.clicked_item.encode(dst, version)
+    }
+}
+
+impl ProtocolSupportDecoder for ClickWindow {
+    fn decode<R: std::io::Read>(
+        src: &mut ::protocol_internal::DecodeContext<R>,
+        version: &protocol_internal::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let window_id = u8::decode(src, version)?;
+        let slot = i16::decode(src, version)?;
+        let button = i8::decode(src, version)?;
+        let action_number = i16::decode(src, version)?;
+        let mode = i8::decode(src, version)?;
+        let action = ClickAction::from_raw(mode, button).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid click, mode {} with button {}", mode, button),
+            )
+        })?;
+        let clicked_item = Slot::decode(src, version)?;
+
+        Ok(Self {
+            window_id,
+            slot,
+            action_number,
+            action,
+            clicked_item,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use misc::prelude::*;
+    use protocol_internal::{DecodeContext, PacketDecoder, PacketEncoder, ProtocolVersionEnum};
+
+    use super::*;
+
+    /// A sword whose nbt holds a byte array of -1 bytes.
+    const MALFORMED_SLOT: [u8; 15] = [
+        0x01, 0x14, 0x01, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x07, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+    ];
+
+    /// Decodes `header` followed by [`MALFORMED_SLOT`], which must fail
+    /// rather than panic.
+    fn assert_malformed(header: &[u8]) {
+        let frame = [header, &MALFORMED_SLOT[..]].concat();
+        let result = <ServerBound as PacketDecoder>::decode(
+            &mut DecodeContext::from(&frame[..]),
+            &ProtocolVersionEnum::V1_8.into(),
+        );
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_window_packets() {
+        round_trip!(ServerBound::ConfirmTransaction {
+            window_id: 1,
+            action_number: 12,
+            accepted: true,
+        });
+        round_trip!(ServerBound::CreativeInventoryAction {
+            slot: 36,
+            clicked_item: ItemStack::new(276, 1).into(),
+        });
+        round_trip!(ServerBound::EnchantItem {
+            window_id: 2,
+            enchantment: 1,
+        });
+
+        let click = round_trip!(ServerBound::ClickWindow {
+            window_id: 0,
+            slot: OUTSIDE_WINDOW_SLOT,
+            action_number: 3,
+            action: ClickAction::Drag {
+                button: MouseButton::Right,
+                stage: DragStage::End,
+            },
+            clicked_item: Slot::EMPTY,
+        });
+        assert_eq!(click, [0x0E, 0, 0xFC, 0x19, 6, 0, 3, 5, 0xFF, 0xFF]);
+
+        for mode in 0..=6 {
+            for button in 0..=10 {
+                if let Some(action) = ClickAction::from_raw(mode, button) {
+                    assert_eq!(action.to_raw(), (mode, button));
+                }
+            }
+        }
+        assert_eq!(ClickAction::from_raw(5, 3), None);
+        assert_eq!(ClickAction::from_raw(3, 0), None);
+
+        let mut invalid = click;
+        invalid[7] = 7;
+        let version = ProtocolVersionEnum::V1_8.into();
+        assert!(<ServerBound as PacketDecoder>::decode(
+            &mut DecodeContext::from(&invalid[..]),
+            &version
+        )
+        .is_err());
+
+        assert_malformed(&[0x0E, 0, 0x00, 0x24, 0, 0, 1, 0]);
+        assert_malformed(&[0x10, 0x00, 0x24]);
+    }
+
+    /// What a 1.8 client sends joining, looking around, fighting, building
+    /// and using its inventory once the connection reached the play state.
+    ///
+    /// The frames are written by hand from the protocol documentation, not
+    /// captured from a client nor made by the encoders, and every field holds
+    /// a value telling it apart from its neighbours so a wrong field order
+    /// shows up in the decoded packets.
+    #[test]
+    fn test_documented_session() {
+        use protocol_internal::ProtocolPosition;
+
+        fn string(value: &str) -> Vec<u8> {
+            [&[value.len() as u8][..], value.as_bytes()].concat()
+        }
+
+        let version = ProtocolVersionEnum::V1_8.into();
+        let sign = Vec3D::new(10, 64, -3).to_position().to_be_bytes();
+        let below = Vec3D::new(10, 63, -3).to_position().to_be_bytes();
+        let hash = "a".repeat(40);
+
+        let session: Vec<Vec<u8>> = vec![
+            [&[0x15][..], &string("en_US"), &[0x08, 0x00, 0x01, 0x7F]].concat(),
+            [&[0x17][..], &string("MC|Brand"), &string("vanilla")].concat(),
+            [
+                &[0x06][..],
+                &0.5f64.to_be_bytes(),
+                &64f64.to_be_bytes(),
+                &0.5f64.to_be_bytes(),
+                &90f32.to_be_bytes(),
+                &0f32.to_be_bytes(),
+                &[0x01],
+            ]
+            .concat(),
+            vec![0x00, 0x2A],
+            [
+                &[0x04][..],
+                &0.5f64.to_be_bytes(),
+                &64f64.to_be_bytes(),
+                &1f64.to_be_bytes(),
+                &[0x01],
+            ]
+            .concat(),
+            [
+                &[0x05][..],
+                &180f32.to_be_bytes(),
+                &10f32.to_be_bytes(),
+                &[0x00],
+            ]
+            .concat(),
+            vec![0x03, 0x01],
+            vec![0x0A],
+            vec![0x02, 0x05, 0x01],
+            [
+                &[0x02, 0x05, 0x02][..],
+                &0.25f32.to_be_bytes(),
+                &1.5f32.to_be_bytes(),
+                &(-0.25f32).to_be_bytes(),
+            ]
+            .concat(),
+            vec![0x0B, 0x01, 0x03, 0x00],
+            [&[0x07, 0x00][..], &below, &[0x01]].concat(),
+            [
+                &[0x08][..],
+                &[0xFF; 8],
+                &[0xFF, 0x01, 0x47, 0x01, 0x00, 0x00, 0x00],
+                &[0x00, 0x00, 0x00],
+            ]
+            .concat(),
+            [&[0x08][..], &below, &[0x01, 0xFF, 0xFF, 0x08, 0x0F, 0x08]].concat(),
+            vec![0x09, 0x00, 0x02],
+            [
+                &[0x0C][..],
+                &0f32.to_be_bytes(),
+                &0.98f32.to_be_bytes(),
+                &[0x02],
+            ]
+            .concat(),
+            vec![0x0E, 0x00, 0x00, 0x24, 0x00, 0x00, 0x01, 0x00, 0xFF, 0xFF],
+            vec![0x0F, 0x00, 0x00, 0x01, 0x01],
+            vec![0x0D, 0x00],
+            vec![0x10, 0x00, 0x24, 0x01, 0x14, 0x01, 0x00, 0x00, 0x00],
+            vec![0x11, 0x01, 0x02],
+            [
+                &[0x12][..],
+                &sign,
+                &string("\"Hello\""),
+                &string("{\"text\":\"world\",\"bold\":true}"),
+                &string("\"\""),
+                &string("\"\""),
+            ]
+            .concat(),
+            [
+                &[0x13, 0x06][..],
+                &0.05f32.to_be_bytes(),
+                &0.1f32.to_be_bytes(),
+            ]
+            .concat(),
+            [&[0x14][..], &string("/he"), &[0x00]].concat(),
+            [&[0x14][..], &string("/setblock 10 6"), &[0x01], &below].concat(),
+            vec![0x16, 0x01],
+            [&[0x18][..], &uuid::Uuid::from_u128(7).as_bytes()[..]].concat(),
+            [&[0x19][..], &string(&hash), &[0x03]].concat(),
+            [&[0x19][..], &string(&hash), &[0x00]].concat(),
+        ];
+
+        let packets: Vec<ServerBound> = session
+            .iter()
+            .map(|frame| {
+                let mut src = DecodeContext::from(&frame[..]);
+                let packet = <ServerBound as PacketDecoder>::decode(&mut src, &version)
+                    .unwrap_or_else(|err| panic!("{:02X?}: {}", frame, err));
+                assert!(src.is_empty(), "{:02X?} not read entirely", frame);
+                packet
+            })
+            .collect();
+
+        assert!(matches!(
+            &packets[0],
+            ServerBound::ClientSettings(ClientSettings {
+                locale,
+                view_distance: 8,
+                chat_mode: ChatMode::Enabled,
+                chat_colors: true,
+                displayed_skin_parts,
+            }) if locale == "en_US" && displayed_skin_parts.bits() == 0x7F
+        ));
+        assert!(matches!(
+            &packets[2],
+            ServerBound::PlayerPositionAndLook(PlayerPositionAndLook {
+                entity_location: EntityLocation { x, y, z, yaw, pitch },
+                on_ground: true,
+            }) if (*x, *y, *z, *yaw, *pitch) == (0.5, 64.0, 0.5, 90.0, 0.0)
+        ));
+        assert!(matches!(
+            &packets[4],
+            ServerBound::PlayerPosition(PlayerPosition { position, on_ground: true })
+            if *position == Vec3D::new(0.5, 64.0, 1.0)
+        ));
+        assert!(matches!(
+            &packets[5],
+            ServerBound::PlayerLook(PlayerLook { look, on_ground: false })
+            if *look == Vec2D::new(180.0, 10.0)
+        ));
+        assert!(matches!(
+            &packets[9],
+            ServerBound::UseEntity(UseEntity {
+                target: 5,
+                action: UseEntityAction::InteractAt { target },
+            }) if *target == Vec3D::new(0.25, 1.5, -0.25)
+        ));
+        assert!(matches!(
+            &packets[10],
+            ServerBound::EntityAction(EntityAction {
+                entity_id: 1,
+                action: EntityActionType::StartSprinting,
+                action_parameter: 0,
+            })
+        ));
+        assert!(matches!(
+            &packets[11],
+            ServerBound::PlayerDigging(PlayerDigging {
+                status: PlayerDiggingStatus::StartedDigging,
+                location,
+                face: 1,
+            }) if *location == Vec3D::new(10, 63, -3)
+        ));
+        assert!(matches!(
+            &packets[12],
+            ServerBound::PlayerBlockPlacement(PlayerBlockPlacement { face: -1, held_item, .. })
+            if held_item.item().map(|item| item.item_id) == Some(327)
+        ));
+        assert!(matches!(
+            &packets[13],
+            ServerBound::PlayerBlockPlacement(PlayerBlockPlacement { location, cursor_position, .. })
+            if *location == Vec3D::new(10, 63, -3) && *cursor_position == Vec3D::new(8, 15, 8)
+        ));
+        assert!(matches!(
+            &packets[15],
+            ServerBound::SteerVehicle(SteerVehicle { flags, .. }) if *flags == SteerVehicleFlags::UNMOUNT
+        ));
+        assert!(matches!(
+            &packets[16],
+            ServerBound::ClickWindow(ClickWindow {
+                window_id: 0,
+                slot: 36,
+                action_number: 1,
+                action: ClickAction::Click(MouseButton::Left),
+                ..
+            })
+        ));
+        assert!(matches!(
+            &packets[19],
+            ServerBound::CreativeInventoryAction(CreativeInventoryAction { slot: 36, clicked_item })
+            if clicked_item.item().map(|item| (item.item_id, item.count)) == Some((276, 1))
+        ));
+        assert!(matches!(
+            &packets[21],
+            ServerBound::UpdateSign(UpdateSign { line_1, .. }) if line_1 == "\"Hello\""
+        ));
+        assert!(matches!(
+            &packets[22],
+            ServerBound::PlayerAbilities(PlayerAbilities { flags, .. })
+            if *flags == PlayerAbilitiesFlags::FLYING | PlayerAbilitiesFlags::ALLOW_FLYING
+        ));
+        assert!(matches!(
+            &packets[23],
+            ServerBound::TabComplete(TabComplete { text, position: None }) if text == "/he"
+        ));
+        assert!(matches!(
+            &packets[24],
+            ServerBound::TabComplete(TabComplete { position: Some(position), .. })
+            if *position == Vec3D::new(10, 63, -3)
+        ));
+        assert!(matches!(
+            &packets[26],
+            ServerBound::Spectate(Spectate { target }) if target.as_u128() == 7
+        ));
+        assert!(matches!(
+            &packets[27],
+            ServerBound::ResourcePackStatus(ResourcePackStatus {
+                result: ResourcePackResult::Accepted,
+                ..
+            })
+        ));
+
+        for (frame, packet) in session.iter().zip(&packets) {
+            let mut buf = Vec::new();
+            PacketEncoder::encode(packet, &mut buf, &version).unwrap();
+            assert_eq!(&buf, frame);
+        }
+
+        let too_long = [&[0x19][..], &string(&"a".repeat(41)), &[0x03]].concat();
+        assert!(<ServerBound as PacketDecoder>::decode(
+            &mut DecodeContext::from(&too_long[..]),
+            &version
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_block_placement_malformed_slot() {
+        use protocol_internal::ProtocolPosition;
+
+        let location = Vec3D::new(10, 63, -3).to_position().to_be_bytes();
+        assert_malformed(&[&[0x08][..], &location, &[0x01]].concat());
+    }
+}
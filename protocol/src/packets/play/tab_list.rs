@@ -0,0 +1,273 @@
+//! A model of the client's tab list, turned into the fewest packets needed
+//! to bring the client up to date.
+
+use std::collections::BTreeMap;
+
+use misc::prelude::{ChatComponent, GameMode, Property};
+use uuid::Uuid;
+
+use super::client_bound::{
+    ClientBound, PlayerListHeaderAndFooter, PlayerListItem, PlayerListItemAddPlayer,
+};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TabListEntry {
+    pub name: String,
+    /// The skin and cape, usually the `textures` property of the profile.
+    pub properties: Vec<Property>,
+    pub game_mode: GameMode,
+    /// Round trip time in milliseconds, drawn as the connection bars.
+    pub ping: i32,
+    /// Shown instead of `name` when set.
+    pub display_name: Option<ChatComponent<'static>>,
+}
+
+impl TabListEntry {
+    pub fn new<N: Into<String>>(name: N) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    fn to_add_player(&self) -> PlayerListItemAddPlayer<'static> {
+        PlayerListItemAddPlayer {
+            name: self.name.clone().into(),
+            properties: self.properties.clone(),
+            game_mode: self.game_mode,
+            ping: self.ping,
+            display_name: self.display_name.clone(),
+        }
+    }
+}
+
+/// The tab list of a single client.
+///
+/// Changes are made to the model freely, [`TabList::flush`] then compares it
+/// with what the client was last sent.
+#[derive(Clone, Debug, Default)]
+pub struct TabList {
+    entries: BTreeMap<Uuid, TabListEntry>,
+    header: ChatComponent<'static>,
+    footer: ChatComponent<'static>,
+
+    sent_entries: BTreeMap<Uuid, TabListEntry>,
+    sent_header: ChatComponent<'static>,
+    sent_footer: ChatComponent<'static>,
+}
+
+impl TabList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, uuid: Uuid, entry: TabListEntry) -> Option<TabListEntry> {
+        self.entries.insert(uuid, entry)
+    }
+
+    pub fn remove(&mut self, uuid: &Uuid) -> Option<TabListEntry> {
+        self.entries.remove(uuid)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn get(&self, uuid: &Uuid) -> Option<&TabListEntry> {
+        self.entries.get(uuid)
+    }
+
+    pub fn get_mut(&mut self, uuid: &Uuid) -> Option<&mut TabListEntry> {
+        self.entries.get_mut(uuid)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&Uuid, &TabListEntry)> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn header(&self) -> &ChatComponent<'static> {
+        &self.header
+    }
+
+    /// An empty component hides the header.
+    pub fn set_header(&mut self, header: ChatComponent<'static>) {
+        self.header = header;
+    }
+
+    pub fn footer(&self) -> &ChatComponent<'static> {
+        &self.footer
+    }
+
+    /// An empty component hides the footer.
+    pub fn set_footer(&mut self, footer: ChatComponent<'static>) {
+        self.footer = footer;
+    }
+
+    /// Makes the next flush send everything again, for a client that has
+    /// not seen this tab list yet.
+    pub fn reset(&mut self) {
+        self.sent_entries.clear();
+        self.sent_header = Default::default();
+        self.sent_footer = Default::default();
+    }
+
+    /// The packets turning the tab list the client was last sent into this
+    /// one, in the order they have to be sent.
+    ///
+    /// Names and properties can't be updated in place, changing them removes
+    /// the entry and adds it again.
+    pub fn flush(&mut self) -> Vec<ClientBound<'static>> {
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        let mut game_modes = Vec::new();
+        let mut pings = Vec::new();
+        let mut display_names = Vec::new();
+
+        for uuid in self.sent_entries.keys() {
+            if !self.entries.contains_key(uuid) {
+                removed.push(*uuid);
+            }
+        }
+
+        for (uuid, entry) in &self.entries {
+            let sent = match self.sent_entries.get(uuid) {
+                Some(sent) if sent.name == entry.name && sent.properties == entry.properties => {
+                    sent
+                }
+                Some(_) => {
+                    removed.push(*uuid);
+                    added.push((*uuid, entry.to_add_player()));
+                    continue;
+                }
+                None => {
+                    added.push((*uuid, entry.to_add_player()));
+                    continue;
+                }
+            };
+
+            if sent.game_mode != entry.game_mode {
+                game_modes.push((*uuid, entry.game_mode));
+            }
+            if sent.ping != entry.ping {
+                pings.push((*uuid, entry.ping));
+            }
+            if sent.display_name != entry.display_name {
+                display_names.push((*uuid, entry.display_name.clone()));
+            }
+        }
+
+        let mut packets: Vec<ClientBound<'static>> = vec![
+            PlayerListItem::RemovePlayer(removed),
+            PlayerListItem::AddPlayer(added),
+            PlayerListItem::UpdateGameMode(game_modes),
+            PlayerListItem::UpdateLatency(pings),
+            PlayerListItem::UpdateDisplayName(display_names),
+        ]
+        .into_iter()
+        .filter(|item| !item.is_empty())
+        .map(ClientBound::PlayerListItem)
+        .collect();
+
+        if self.sent_header != self.header || self.sent_footer != self.footer {
+            packets.push(ClientBound::PlayerListHeaderAndFooter(
+                PlayerListHeaderAndFooter {
+                    header: self.header.clone(),
+                    footer: self.footer.clone(),
+                },
+            ));
+        }
+
+        self.sent_entries = self.entries.clone();
+        self.sent_header = self.header.clone();
+        self.sent_footer = self.footer.clone();
+
+        packets
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn uuid(id: u128) -> Uuid {
+        Uuid::from_u128(id)
+    }
+
+    fn item<'a>(packet: &'a ClientBound<'static>) -> &'a PlayerListItem<'static> {
+        match packet {
+            ClientBound::PlayerListItem(item) => item,
+            packet => panic!("expected a player list item, got {:?}", packet),
+        }
+    }
+
+    #[test]
+    fn test_flush_diffs() {
+        let mut tab = TabList::new();
+        tab.insert(uuid(1), TabListEntry::new("Alice"));
+        tab.insert(uuid(2), TabListEntry::new("Bob"));
+
+        let packets = tab.flush();
+        assert_eq!(packets.len(), 1);
+        assert!(
+            matches!(item(&packets[0]), PlayerListItem::AddPlayer(players) if players.len() == 2)
+        );
+        assert!(tab.flush().is_empty());
+
+        tab.get_mut(&uuid(1)).unwrap().ping = 40;
+        tab.get_mut(&uuid(2)).unwrap().game_mode = GameMode::Creative;
+        tab.remove(&uuid(2));
+
+        let packets = tab.flush();
+        assert_eq!(packets.len(), 2);
+        assert!(
+            matches!(item(&packets[0]), PlayerListItem::RemovePlayer(players) if players == &[uuid(2)])
+        );
+        assert!(
+            matches!(item(&packets[1]), PlayerListItem::UpdateLatency(players) if players == &[(uuid(1), 40)])
+        );
+    }
+
+    #[test]
+    fn test_flush_renamed() {
+        let mut tab = TabList::new();
+        tab.insert(uuid(1), TabListEntry::new("Alice"));
+        tab.flush();
+
+        let entry = tab.get_mut(&uuid(1)).unwrap();
+        entry.name = "Alicia".into();
+        entry.display_name = Some(ChatComponent::new("[Admin] Alicia"));
+
+        let packets = tab.flush();
+        assert_eq!(packets.len(), 2);
+        assert!(matches!(item(&packets[0]), PlayerListItem::RemovePlayer(_)));
+        assert!(
+            matches!(item(&packets[1]), PlayerListItem::AddPlayer(players)
+            if players[0].1.display_name == Some(ChatComponent::new("[Admin] Alicia")))
+        );
+    }
+
+    #[test]
+    fn test_flush_header_and_footer() {
+        let mut tab = TabList::new();
+        assert!(tab.flush().is_empty());
+
+        tab.set_header(ChatComponent::new("Welcome"));
+        let packets = tab.flush();
+        assert!(matches!(
+            &packets[..],
+            [ClientBound::PlayerListHeaderAndFooter(packet)] if packet.header.text.as_deref() == Some("Welcome")
+        ));
+        assert!(tab.flush().is_empty());
+
+        tab.reset();
+        assert_eq!(tab.flush().len(), 1);
+    }
+}
@@ -1,3 +1,5 @@
+use misc::misc::status::StatusResponse;
+
 #[derive(Debug, protocol_derive::ProtocolSupport)]
 #[packet(0x00)]
 #[packet_size(eq = 0)]
@@ -13,7 +15,7 @@ pub struct Ping {
 #[derive(Debug, protocol_derive::ProtocolSupport)]
 #[packet(0x00)]
 pub struct Response {
-    pub json_response: String,
+    pub status: StatusResponse,
 }
 
 #[derive(Debug, protocol_derive::ProtocolSupport)]
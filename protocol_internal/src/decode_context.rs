@@ -0,0 +1,74 @@
+use std::io::{self, Read};
+
+/// A reader bounded to the packet frame being decoded.
+///
+/// Decoders can ask how many bytes are left in the frame, which is how fields
+/// spanning the rest of the packet know where to stop.
+#[derive(Debug)]
+pub struct DecodeContext<R> {
+    inner: R,
+    remaining: usize,
+}
+
+impl<R: Read> DecodeContext<R> {
+    /// Wraps a reader holding exactly `len` bytes of the current frame.
+    pub fn new(inner: R, len: usize) -> Self {
+        Self {
+            inner,
+            remaining: len,
+        }
+    }
+
+    /// Bytes left unread in the current frame.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining == 0
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<'a> From<&'a [u8]> for DecodeContext<&'a [u8]> {
+    fn from(frame: &'a [u8]) -> Self {
+        Self::new(frame, frame.len())
+    }
+}
+
+impl<R: Read> Read for DecodeContext<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = buf.len().min(self.remaining);
+        if max == 0 {
+            return Ok(0);
+        }
+
+        let read = self.inner.read(&mut buf[..max])?;
+        self.remaining -= read;
+
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use super::DecodeContext;
+
+    #[test]
+    fn test_decode_context_is_bounded() {
+        let frame = [0u8, 1, 2, 3, 4, 5];
+        let mut ctx = DecodeContext::new(&frame[..], 4);
+
+        let mut buf = [0u8; 3];
+        ctx.read_exact(&mut buf).unwrap();
+        assert_eq!(ctx.remaining(), 1);
+
+        let mut buf = [0u8; 2];
+        assert!(ctx.read_exact(&mut buf).is_err());
+    }
+}
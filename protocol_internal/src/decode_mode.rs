@@ -0,0 +1,15 @@
+/// What to do when a packet decoder did not consume its whole frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DecodeMode {
+    /// Fails the decode, reporting how many bytes were left unread.
+    Strict,
+    /// Logs the leftover bytes and hands out the packet anyway.
+    #[default]
+    Lenient,
+}
+
+impl DecodeMode {
+    pub const fn is_strict(&self) -> bool {
+        matches!(self, Self::Strict)
+    }
+}
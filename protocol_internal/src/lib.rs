@@ -3,10 +3,14 @@ pub mod types;
 #[cfg(feature = "types")]
 pub use types::*;
 
+pub mod decode_context;
+pub mod decode_mode;
 pub mod protocol_direction;
 pub mod protocol_state;
 pub mod protocol_version;
 
+pub use decode_context::DecodeContext;
+pub use decode_mode::DecodeMode;
 pub use protocol_direction::ProtocolDirection;
 pub use protocol_state::ProtocolState;
 pub use protocol_version::{ProtocolVersion, ProtocolVersionEnum};
@@ -20,7 +24,7 @@ pub trait PacketEncoder: std::fmt::Debug + ProtocolSupportEncoder {
 
 pub trait PacketDecoder: std::fmt::Debug + ProtocolSupportDecoder + PacketSizer {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut DecodeContext<R>,
         version: &ProtocolVersion,
     ) -> io::Result<Self>;
 }
@@ -41,7 +45,7 @@ pub trait ProtocolSupportEncoder {
 
 pub trait ProtocolSupportDecoder: Sized {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut DecodeContext<R>,
         version: &ProtocolVersion,
     ) -> io::Result<Self>;
 }
@@ -1,4 +1,5 @@
 #[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ProtocolState {
     Handshake = 0,
     Status = 1,
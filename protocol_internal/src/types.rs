@@ -1,6 +1,8 @@
+mod angle;
 mod bool;
 mod cow;
 mod dyn_array;
+mod fixed_point;
 mod fixed_vec;
 mod numeral {
     mod int;
@@ -10,13 +12,17 @@ mod option;
 mod position;
 mod range_validation;
 mod regex;
+mod remaining_bytes;
 mod string;
 mod uuid;
 mod vec;
 
 pub use self::regex::Regex;
+pub use angle::Angle;
 pub use dyn_array::DynArray;
+pub use fixed_point::FixedPoint;
 pub use fixed_vec::FixedVec;
 pub use numeral::varnum::{VarNum, VarNumExt};
 pub use position::{ProtocolPosition, ProtocolPositionSupport};
 pub use range_validation::RangeValidatedSupport;
+pub use remaining_bytes::RemainingBytes;
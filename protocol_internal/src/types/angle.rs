@@ -0,0 +1,53 @@
+use crate::{ProtocolSupportDecoder, ProtocolSupportEncoder};
+
+/// A rotation in steps of 1/256 of a full turn, sent as a single byte.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    /// Wraps around like vanilla does, so `-90` and `270` are the same angle.
+    pub fn from_degrees(degrees: f32) -> Self {
+        Self((degrees * 256.0 / 360.0) as i32 as u8)
+    }
+
+    /// In `0..360`.
+    pub fn to_degrees(self) -> f32 {
+        self.0 as f32 * 360.0 / 256.0
+    }
+}
+
+impl ProtocolSupportEncoder for Angle {
+    fn calculate_len(&self, _: &crate::ProtocolVersion) -> usize {
+        1
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &crate::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        self.0.encode(dst, version)
+    }
+}
+
+impl ProtocolSupportDecoder for Angle {
+    fn decode<R: std::io::Read>(
+        src: &mut crate::DecodeContext<R>,
+        version: &crate::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        u8::decode(src, version).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Angle;
+
+    #[test]
+    fn test_angle_degrees() {
+        assert_eq!(Angle::from_degrees(90.0), Angle(64));
+        assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+        assert_eq!(Angle::from_degrees(450.0), Angle(64));
+        assert_eq!(Angle(128).to_degrees(), 180.0);
+    }
+}
@@ -20,7 +20,7 @@ impl ProtocolSupportEncoder for bool {
 
 impl ProtocolSupportDecoder for bool {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         _: &crate::ProtocolVersion,
     ) -> std::io::Result<Self> {
         Ok(match src.read_u8()? {
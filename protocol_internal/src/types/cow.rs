@@ -32,7 +32,7 @@ where
     T::Owned: ProtocolSupportDecoder,
 {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         version: &crate::ProtocolVersion,
     ) -> std::io::Result<Self> {
         T::Owned::decode(src, version).map(Cow::Owned)
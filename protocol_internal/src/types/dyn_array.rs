@@ -1,5 +1,6 @@
-use crate::{ProtocolSupportDecoder, ProtocolSupportEncoder, RangeValidatedSupport};
+use crate::{DecodeContext, ProtocolSupportDecoder, ProtocolSupportEncoder, RangeValidatedSupport};
 
+/// Elements spanning the rest of the packet frame, without a len prefix.
 pub struct DynArray;
 
 impl DynArray {
@@ -15,19 +16,13 @@ impl DynArray {
     }
 
     pub fn decode<R: std::io::Read, T: ProtocolSupportDecoder>(
-        src: &mut R,
+        src: &mut DecodeContext<R>,
         version: &crate::ProtocolVersion,
     ) -> std::io::Result<Vec<T>> {
         let mut buf = Vec::new();
 
-        loop {
-            match <T as ProtocolSupportDecoder>::decode(src, version) {
-                Ok(out) => buf.push(out),
-                Err(err) => match err.kind() {
-                    std::io::ErrorKind::UnexpectedEof => break,
-                    _ => Err(err)?,
-                },
-            }
+        while !src.is_empty() {
+            buf.push(decode_element(src, version)?);
         }
 
         Ok(buf)
@@ -48,28 +43,22 @@ impl DynArray {
 
 impl<T: ProtocolSupportDecoder> RangeValidatedSupport<Vec<T>> for DynArray {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut DecodeContext<R>,
         version: &crate::ProtocolVersion,
         min: usize,
         max: usize,
     ) -> std::io::Result<Vec<T>> {
-        let mut buf = Vec::with_capacity(max);
+        let mut buf = Vec::new();
 
-        loop {
-            if max < buf.len() {
+        while !src.is_empty() {
+            if max != 0 && buf.len() >= max {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::InvalidInput,
                     format!("dynarray is bigger than max {}", max),
                 ));
             }
 
-            match <T as ProtocolSupportDecoder>::decode(src, version) {
-                Ok(out) => buf.push(out),
-                Err(err) => match err.kind() {
-                    std::io::ErrorKind::UnexpectedEof => break,
-                    _ => Err(err)?,
-                },
-            }
+            buf.push(decode_element(src, version)?);
         }
 
         if min > buf.len() {
@@ -82,3 +71,46 @@ impl<T: ProtocolSupportDecoder> RangeValidatedSupport<Vec<T>> for DynArray {
         Ok(buf)
     }
 }
+
+/// Decodes a single element, failing if it would loop forever by not
+/// consuming anything from the frame.
+fn decode_element<R: std::io::Read, T: ProtocolSupportDecoder>(
+    src: &mut DecodeContext<R>,
+    version: &crate::ProtocolVersion,
+) -> std::io::Result<T> {
+    let remaining = src.remaining();
+    let element = <T as ProtocolSupportDecoder>::decode(src, version)?;
+
+    if src.remaining() == remaining {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "dynarray element did not consume any bytes",
+        ));
+    }
+
+    Ok(element)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DecodeContext, DynArray, ProtocolVersion};
+
+    #[test]
+    fn test_dynarray_reads_until_frame_end() {
+        let frame = [0x00, 0x01, 0x00, 0x02];
+        let mut ctx = DecodeContext::from(&frame[..]);
+
+        let values: Vec<u16> = DynArray::decode(&mut ctx, &ProtocolVersion::new(47)).unwrap();
+        assert_eq!(values, vec![1, 2]);
+        assert!(ctx.is_empty());
+    }
+
+    #[test]
+    fn test_dynarray_rejects_truncated_element() {
+        let frame = [0x00, 0x01, 0x00];
+        let mut ctx = DecodeContext::from(&frame[..]);
+
+        let result = DynArray::decode::<_, u16>(&mut ctx, &ProtocolVersion::new(47));
+        assert!(matches!(result, Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof));
+    }
+}
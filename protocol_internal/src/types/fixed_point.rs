@@ -0,0 +1,66 @@
+use crate::{ProtocolSupportDecoder, ProtocolSupportEncoder};
+
+/// A number with 5 fractional bits, how entity positions are sent before
+/// 1.9: absolute ones as `FixedPoint<i32>` and relative moves as
+/// `FixedPoint<i8>`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedPoint<T>(pub T);
+
+macro_rules! impl_fixed_point {
+    ($($n:ty),*) => {
+        $(
+            impl FixedPoint<$n> {
+                pub const FRACTIONAL_BITS: u32 = 5;
+
+                /// Rounds down to the nearest 1/32, saturating when out of
+                /// range.
+                pub fn from_f64(value: f64) -> Self {
+                    Self((value * 32.0).floor() as $n)
+                }
+
+                pub fn to_f64(self) -> f64 {
+                    self.0 as f64 / 32.0
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_point!(i8, i32);
+
+impl<T: ProtocolSupportEncoder> ProtocolSupportEncoder for FixedPoint<T> {
+    fn calculate_len(&self, version: &crate::ProtocolVersion) -> usize {
+        self.0.calculate_len(version)
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        version: &crate::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        self.0.encode(dst, version)
+    }
+}
+
+impl<T: ProtocolSupportDecoder> ProtocolSupportDecoder for FixedPoint<T> {
+    fn decode<R: std::io::Read>(
+        src: &mut crate::DecodeContext<R>,
+        version: &crate::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        T::decode(src, version).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FixedPoint;
+
+    #[test]
+    fn test_fixed_point() {
+        assert_eq!(FixedPoint::<i32>::from_f64(1.5), FixedPoint(48));
+        assert_eq!(FixedPoint::<i32>::from_f64(-0.01), FixedPoint(-1));
+        assert_eq!(FixedPoint(48i32).to_f64(), 1.5);
+        assert_eq!(FixedPoint::<i8>::from_f64(-4.0), FixedPoint(-128));
+        assert_eq!(FixedPoint::<i8>::from_f64(5.0), FixedPoint(127));
+    }
+}
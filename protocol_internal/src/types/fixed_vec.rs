@@ -4,13 +4,15 @@ pub struct FixedVec;
 
 impl FixedVec {
     pub fn decode<R: std::io::Read, T: ProtocolSupportDecoder>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         version: &crate::ProtocolVersion,
         len: usize,
     ) -> std::io::Result<Vec<T>> {
-        let mut buf = Vec::with_capacity(len);
+        // every item takes a byte at least, so `len` is only trusted up to
+        // the bytes left in the frame
+        let mut buf = Vec::with_capacity(len.min(src.remaining()));
 
-        while buf.len() < buf.capacity() {
+        for _ in 0..len {
             buf.push(<T as ProtocolSupportDecoder>::decode(src, version)?);
         }
 
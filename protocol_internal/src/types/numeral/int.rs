@@ -20,7 +20,7 @@ macro_rules! impl_numeral {
 
         impl $crate::ProtocolSupportDecoder for $n {
             fn decode<R: std::io::Read>(
-                src: &mut R,
+                src: &mut $crate::DecodeContext<R>,
                 _: &$crate::ProtocolVersion,
             ) -> std::io::Result<$n> {
                 src.$r()
@@ -44,7 +44,7 @@ macro_rules! impl_numeral {
 
         impl $crate::ProtocolSupportDecoder for $n {
             fn decode<R: std::io::Read>(
-                src: &mut R,
+                src: &mut $crate::DecodeContext<R>,
                 _: &$crate::ProtocolVersion,
             ) -> std::io::Result<$n> {
                 src.$r::<BigEndian>()
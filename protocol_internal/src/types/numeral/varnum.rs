@@ -64,23 +64,47 @@ impl VarNum<i32> {
 
 impl VarNum<i64> {
     #[inline(always)]
-    #[rustfmt::skip]
     pub fn calculate_len(value: &i64) -> usize {
-        let value = *value as u64;
-
-        if value as u32 & 0xF0000000 != 0 { 5 }
-        else if value as u32 & 0xFFE00000 != 0 { 4 }
-        else if value as u32 & 0xFFFFC000 != 0 { 3 }
-        else if value as u32 & 0xFFFFFF80 != 0 { 2 }
-        else { 1 }
+        match *value as u64 {
+            0 => 1,
+            value => (64 - value.leading_zeros() as usize).div_ceil(7),
+        }
     }
 
-    pub fn encode<W: std::io::Write>(_value: &i64, _dst: &mut W) -> io::Result<()> {
+    pub fn encode<W: std::io::Write>(value: &i64, dst: &mut W) -> io::Result<()> {
+        let mut temp = *value as u64;
+
+        loop {
+            let byte = (temp & 0x7F) as u8;
+            temp >>= 7;
+
+            if temp != 0 {
+                dst.write_u8(byte | 0x80)?;
+            } else {
+                dst.write_u8(byte)?;
+                break;
+            }
+        }
+
         Ok(())
     }
 
-    pub fn decode<R: std::io::Read>(_src: &mut R) -> io::Result<i64> {
-        Ok(0)
+    pub fn decode<R: std::io::Read>(src: &mut R) -> io::Result<i64> {
+        let mut result = 0i64;
+
+        for i in &VarNum::<i64>::NUM_SHIFT {
+            let byte = src.read_u8()?;
+            result |= (byte as i64 & 0x7F) << i;
+
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "varlong is too big",
+        ))
     }
 }
 
@@ -129,3 +153,36 @@ impl VarNumExt for i64 {
         VarNum::<i64>::calculate_len(&self)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_varlong() {
+        for (value, bytes) in [
+            (0i64, &[0x00][..]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x01]),
+            (i32::MAX as i64, &[0xFF, 0xFF, 0xFF, 0xFF, 0x07]),
+            (
+                i64::MAX,
+                &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F],
+            ),
+            (
+                -1,
+                &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01],
+            ),
+        ]
+        .iter()
+        {
+            let mut buf = Vec::new();
+            VarNum::<i64>::encode(value, &mut buf).unwrap();
+            assert_eq!(&buf, bytes);
+            assert_eq!(VarNum::<i64>::calculate_len(value), bytes.len());
+            assert_eq!(VarNum::<i64>::decode(&mut &buf[..]).unwrap(), *value);
+        }
+
+        assert!(VarNum::<i64>::decode(&mut &[0xFF; 11][..]).is_err());
+    }
+}
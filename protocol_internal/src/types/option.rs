@@ -30,7 +30,7 @@ where
     T: ProtocolSupportDecoder,
 {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         version: &crate::ProtocolVersion,
     ) -> std::io::Result<Self> {
         if <bool as ProtocolSupportDecoder>::decode(src, version)? {
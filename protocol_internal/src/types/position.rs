@@ -24,9 +24,7 @@ impl<T: ProtocolPosition> ProtocolPositionSupport<T> {
         dst.write_i64::<BigEndian>(ProtocolPosition::to_position(value))
     }
 
-    pub fn decode<R: std::io::Read>(
-        src: &mut R,
-    ) -> std::io::Result<T> {
+    pub fn decode<R: std::io::Read>(src: &mut R) -> std::io::Result<T> {
         Ok(ProtocolPosition::from_position(
             src.read_i64::<BigEndian>()?,
         ))
@@ -5,7 +5,7 @@ where
     T: ProtocolSupportDecoder + Sized,
 {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         version: &crate::ProtocolVersion,
         min: usize,
         max: usize,
@@ -18,7 +18,7 @@ macro_rules! impl_range_validated_numeral {
         impl $crate::RangeValidatedSupport<$n> for $crate::VarNum<$n> {
             #[inline(always)]
             fn decode<R: std::io::Read>(
-                src: &mut R,
+                src: &mut $crate::DecodeContext<R>,
                 _: &crate::ProtocolVersion,
                 min: usize,
                 max: usize,
@@ -47,7 +47,7 @@ macro_rules! impl_range_validated_numeral {
         impl $crate::RangeValidatedSupport for $n {
             #[inline(always)]
             fn decode<R: std::io::Read>(
-                src: &mut R,
+                src: &mut $crate::DecodeContext<R>,
                 version: &crate::ProtocolVersion,
                 min: usize,
                 max: usize,
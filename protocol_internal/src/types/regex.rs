@@ -6,7 +6,7 @@ pub struct Regex;
 
 impl Regex {
     pub fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         version: &crate::ProtocolVersion,
         regex: &regex::Regex,
     ) -> std::io::Result<String> {
@@ -0,0 +1,74 @@
+use std::{
+    io::Read,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{DecodeContext, ProtocolSupportDecoder, ProtocolSupportEncoder};
+
+/// Raw bytes spanning the rest of the packet frame, without a len prefix.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RemainingBytes(pub Vec<u8>);
+
+impl RemainingBytes {
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl ProtocolSupportEncoder for RemainingBytes {
+    fn calculate_len(&self, _: &crate::ProtocolVersion) -> usize {
+        self.0.len()
+    }
+
+    fn encode<W: std::io::Write>(
+        &self,
+        dst: &mut W,
+        _: &crate::ProtocolVersion,
+    ) -> std::io::Result<()> {
+        dst.write_all(&self.0[..])
+    }
+}
+
+impl ProtocolSupportDecoder for RemainingBytes {
+    fn decode<R: std::io::Read>(
+        src: &mut DecodeContext<R>,
+        _: &crate::ProtocolVersion,
+    ) -> std::io::Result<Self> {
+        let mut buf = vec![0u8; src.remaining()];
+        src.read_exact(&mut buf[..])?;
+
+        Ok(Self(buf))
+    }
+}
+
+impl Deref for RemainingBytes {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for RemainingBytes {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<Vec<u8>> for RemainingBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<&[u8]> for RemainingBytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
+impl From<RemainingBytes> for Vec<u8> {
+    fn from(bytes: RemainingBytes) -> Self {
+        bytes.0
+    }
+}
@@ -1,3 +1,5 @@
+use std::io::Read;
+
 use crate::{ProtocolSupportDecoder, ProtocolSupportEncoder, RangeValidatedSupport, VarNum};
 
 impl ProtocolSupportEncoder for String {
@@ -17,7 +19,7 @@ impl ProtocolSupportEncoder for String {
 
 impl ProtocolSupportDecoder for String {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         version: &crate::ProtocolVersion,
     ) -> std::io::Result<Self> {
         <String as RangeValidatedSupport>::decode(src, version, 0, 32767)
@@ -27,7 +29,7 @@ impl ProtocolSupportDecoder for String {
 impl RangeValidatedSupport for String {
     #[inline(always)]
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         version: &crate::ProtocolVersion,
         min: usize,
         max: usize,
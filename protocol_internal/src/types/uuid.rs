@@ -18,7 +18,7 @@ impl ProtocolSupportEncoder for Uuid {
 
 impl ProtocolSupportDecoder for Uuid {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         version: &crate::ProtocolVersion,
     ) -> std::io::Result<Self> {
         Ok(Uuid::from_u128(ProtocolSupportDecoder::decode(
@@ -1,4 +1,6 @@
-use crate::{ProtocolSupportDecoder, ProtocolSupportEncoder, RangeValidatedSupport, VarNum};
+use crate::{
+    FixedVec, ProtocolSupportDecoder, ProtocolSupportEncoder, RangeValidatedSupport, VarNum,
+};
 
 impl<T: ProtocolSupportEncoder> ProtocolSupportEncoder for Vec<T> {
     fn calculate_len(&self, version: &crate::ProtocolVersion) -> usize {
@@ -25,23 +27,18 @@ impl<T: ProtocolSupportEncoder> ProtocolSupportEncoder for Vec<T> {
 
 impl<T: ProtocolSupportDecoder> ProtocolSupportDecoder for Vec<T> {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         version: &crate::ProtocolVersion,
     ) -> std::io::Result<Self> {
         let len = VarNum::<i32>::decode(src)? as usize;
 
-        let mut buf = Vec::with_capacity(len);
-        while buf.len() < buf.capacity() {
-            buf.push(<T as ProtocolSupportDecoder>::decode(src, version)?);
-        }
-
-        Ok(buf)
+        FixedVec::decode(src, version, len)
     }
 }
 
 impl<T: ProtocolSupportDecoder> RangeValidatedSupport for Vec<T> {
     fn decode<R: std::io::Read>(
-        src: &mut R,
+        src: &mut crate::DecodeContext<R>,
         version: &crate::ProtocolVersion,
         min: usize,
         max: usize,
@@ -49,11 +46,21 @@ impl<T: ProtocolSupportDecoder> RangeValidatedSupport for Vec<T> {
         let len =
             <VarNum<i32> as RangeValidatedSupport<i32>>::decode(src, version, min, max)? as usize;
 
-        let mut buf = Vec::with_capacity(len);
-        while buf.len() < buf.capacity() {
-            buf.push(<T as ProtocolSupportDecoder>::decode(src, version)?);
-        }
+        FixedVec::decode(src, version, len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DecodeContext, ProtocolSupportDecoder, ProtocolVersion};
+
+    #[test]
+    fn test_vec_len_over_frame() {
+        // a count of i32::MAX with a single item behind it
+        let frame = [0xFF, 0xFF, 0xFF, 0xFF, 0x07, 0x00, 0x01];
+        let mut ctx = DecodeContext::from(&frame[..]);
 
-        Ok(buf)
+        let result = <Vec<u16>>::decode(&mut ctx, &ProtocolVersion::new(47));
+        assert!(matches!(result, Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof));
     }
 }